@@ -0,0 +1,44 @@
+extern crate irc;
+extern crate test;
+
+use irc::{Message, MessageRef};
+use test::Bencher;
+
+static PRIVMSG: &'static str = ":nick!user@host.example.com PRIVMSG #channel :hello there, this is a fairly typical message\r\n";
+static TAGGED: &'static str = "@time=2014-10-01T12:00:00.000Z;msgid=abc123;account=nick :nick!user@host.example.com PRIVMSG #channel :hello there\r\n";
+static NUMERIC: &'static str = ":irc.example.com 353 nick = #channel :@op +voiced regular another yetanother\r\n";
+
+#[bench]
+fn bench_parse_owned (b: &mut Bencher) {
+    b.iter(|| Message::parse(PRIVMSG).unwrap());
+}
+
+#[bench]
+fn bench_parse_borrowed (b: &mut Bencher) {
+    b.iter(|| MessageRef::parse(PRIVMSG).unwrap().params().len());
+}
+
+#[bench]
+fn bench_parse_owned_tagged (b: &mut Bencher) {
+    b.iter(|| Message::parse(TAGGED).unwrap());
+}
+
+#[bench]
+fn bench_parse_borrowed_tagged (b: &mut Bencher) {
+    b.iter(|| MessageRef::parse(TAGGED).unwrap().raw_tag_value("msgid").map(|v| v.len()));
+}
+
+#[bench]
+fn bench_parse_owned_numeric (b: &mut Bencher) {
+    b.iter(|| Message::parse(NUMERIC).unwrap());
+}
+
+#[bench]
+fn bench_parse_borrowed_numeric (b: &mut Bencher) {
+    b.iter(|| MessageRef::parse(NUMERIC).unwrap().params().len());
+}
+
+#[bench]
+fn bench_borrowed_to_owned (b: &mut Bencher) {
+    b.iter(|| MessageRef::parse(PRIVMSG).unwrap().to_message());
+}
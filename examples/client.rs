@@ -1,7 +1,6 @@
 extern crate irc;
 
 use std::collections::HashMap;
-use std::io;
 
 pub struct ExampleClient {
     karma: HashMap<String, i32>,
@@ -14,11 +13,11 @@ impl ExampleClient {
 }
 
 impl irc::ClientCallbacks for ExampleClient {
-    fn on_rpl_welcome (&mut self, client: &mut irc::Client, _m: &irc::Message) -> io::IoResult<()> {
-        client.join(["#doytest"], [])
+    fn on_rpl_welcome (&mut self, client: &mut irc::Client, _m: &irc::MessageRef) -> irc::IrcResult<()> {
+        Ok(try!(client.join(["#doytest"], [])))
     }
 
-    fn on_privmsg (&mut self, client: &mut irc::Client, from: Option<&str>, receivers: &[&str], text: &str) -> io::IoResult<()> {
+    fn on_privmsg (&mut self, client: &mut irc::Client, from: Option<&str>, receivers: &[&str], text: &str) -> irc::IrcResult<()> {
         let incr = if text.ends_with("++") { 1 }
             else if text.ends_with("--") { -1 }
             else { 0 };
@@ -53,5 +52,6 @@ fn main () {
     let mut builder = irc::ClientBuilder::new("doytest", "chat.freenode.net");
     builder.set_debug(true);
     let client = builder.connect();
-    client.run_loop_with_callbacks(ExampleClient::new());
+    let reason = client.run_loop_with_callbacks(ExampleClient::new());
+    println!("disconnected: {}", reason);
 }
@@ -0,0 +1,127 @@
+// a small mutation fuzzer for the targets in irc::fuzz. it starts from the
+// lines in corpus/, mutates them at random, and runs the chosen target on
+// each result. inputs which make the target panic are saved in crashes/.
+//
+//   cargo run --release -- parse 100000
+//   cargo run --release -- round_trip 100000
+//   cargo run --release -- dispatch 1000
+
+extern crate irc;
+
+use std::io;
+use std::io::fs;
+use std::os;
+use std::rand::{task_rng, Rng};
+use std::task;
+
+use irc::fuzz;
+
+// bytes which are meaningful to the parser somewhere
+static INTERESTING: &'static [u8] = b" :@;=\\!\r\n\x00\x01\xff";
+
+fn load_corpus (dir: &Path) -> Vec<Vec<u8>> {
+    let mut corpus = vec![];
+    let mut files = fs::readdir(dir).unwrap();
+    files.sort();
+    for file in files.iter() {
+        let contents = io::File::open(file).read_to_end().unwrap();
+        for line in contents.as_slice().split(|&b| b == b'\n') {
+            let line = if line.ends_with(b"\r") { line.slice_to(line.len() - 1) } else { line };
+            if line.len() > 0 {
+                let mut line = line.to_vec();
+                line.push_all(b"\r\n");
+                corpus.push(line);
+            }
+        }
+    }
+    corpus
+}
+
+fn mutate<R: Rng> (rng: &mut R, input: &[u8], corpus: &[Vec<u8>]) -> Vec<u8> {
+    let mut data = input.to_vec();
+    for _ in range(0u, rng.gen_range(1u, 5)) {
+        let len = data.len();
+        match rng.gen_range(0u, 5) {
+            // flip a byte
+            0 if len > 0 => {
+                let i = rng.gen_range(0, len);
+                data[i] = rng.gen();
+            },
+            // insert something the parser cares about
+            1 => {
+                let i = rng.gen_range(0, len + 1);
+                data.insert(i, *rng.choose(INTERESTING).unwrap());
+            },
+            // remove a byte
+            2 if len > 0 => {
+                let i = rng.gen_range(0, len);
+                data.remove(i);
+            },
+            // truncate
+            3 if len > 0 => {
+                let i = rng.gen_range(0, len);
+                data.truncate(i);
+            },
+            // splice in part of another line
+            4 => {
+                let other = rng.choose(corpus).unwrap();
+                let start = rng.gen_range(0, other.len());
+                let end = rng.gen_range(start, other.len() + 1);
+                let i = rng.gen_range(0, len + 1);
+                let mut spliced = data.slice_to(i).to_vec();
+                spliced.push_all(other.slice(start, end));
+                spliced.push_all(data.slice_from(i));
+                data = spliced;
+            },
+            _ => {},
+        }
+    }
+    data
+}
+
+fn main () {
+    let args = os::args();
+    if args.len() < 2 {
+        println!("usage: {} <parse|round_trip|dispatch> [iterations]", args[0]);
+        os::set_exit_status(1);
+        return;
+    }
+
+    let target: fn(&[u8]) = match args[1].as_slice() {
+        "parse" => fuzz::parse,
+        "round_trip" => fuzz::round_trip,
+        "dispatch" => fuzz::dispatch,
+        t => {
+            println!("unknown target {}", t);
+            os::set_exit_status(1);
+            return;
+        },
+    };
+    let iterations = args.get(2).and_then(|n| from_str(n.as_slice())).unwrap_or(10000u);
+
+    let corpus = load_corpus(&Path::new("corpus"));
+    let crash_dir = Path::new("crashes");
+    let _ = fs::mkdir_recursive(&crash_dir, io::USER_RWX);
+
+    let mut rng = task_rng();
+    let mut crashes = 0u;
+    for i in range(0, iterations) {
+        let data = {
+            let input = rng.choose(corpus.as_slice()).unwrap();
+            mutate(&mut rng, input.as_slice(), corpus.as_slice())
+        };
+
+        let input = data.clone();
+        if task::try(proc() target(input.as_slice())).is_err() {
+            let path = crash_dir.join(format!("{}-{}", args[1], i));
+            io::File::create(&path).write(data.as_slice()).unwrap();
+            println!("crash saved to {}", path.display());
+            crashes += 1;
+        }
+    }
+
+    println!("{} iterations, {} crashes", iterations, crashes);
+    if crashes > 0 {
+        os::set_exit_status(1);
+    }
+}
@@ -0,0 +1,438 @@
+use std::ascii::AsciiExt;
+
+use constants::{RawCommand, Batch, Fail};
+use message::{Message, MessageRef};
+use standard_reply::StandardReply;
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum MessageReference {
+    MsgId(String),
+    Timestamp(String),
+    Unbounded,
+}
+
+impl MessageReference {
+    pub fn to_param (&self) -> String {
+        match self {
+            &MsgId(ref id) => format!("msgid={}", id),
+            &Timestamp(ref ts) => format!("timestamp={}", ts),
+            &Unbounded => "*".to_string(),
+        }
+    }
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum ChatHistoryRequest {
+    Latest(String, MessageReference, uint),
+    Before(String, MessageReference, uint),
+    After(String, MessageReference, uint),
+    Around(String, MessageReference, uint),
+    Between(String, MessageReference, MessageReference, uint),
+    Targets(MessageReference, MessageReference, uint),
+}
+
+impl ChatHistoryRequest {
+    pub fn subcommand (&self) -> &'static str {
+        match self {
+            &Latest(..) => "LATEST",
+            &Before(..) => "BEFORE",
+            &After(..) => "AFTER",
+            &Around(..) => "AROUND",
+            &Between(..) => "BETWEEN",
+            &Targets(..) => "TARGETS",
+        }
+    }
+
+    pub fn target (&self) -> Option<&str> {
+        match self {
+            &Latest(ref t, _, _) => Some(t.as_slice()),
+            &Before(ref t, _, _) => Some(t.as_slice()),
+            &After(ref t, _, _) => Some(t.as_slice()),
+            &Around(ref t, _, _) => Some(t.as_slice()),
+            &Between(ref t, _, _, _) => Some(t.as_slice()),
+            &Targets(..) => None,
+        }
+    }
+
+    pub fn limit (&self) -> uint {
+        match self {
+            &Latest(_, _, l) => l,
+            &Before(_, _, l) => l,
+            &After(_, _, l) => l,
+            &Around(_, _, l) => l,
+            &Between(_, _, _, l) => l,
+            &Targets(_, _, l) => l,
+        }
+    }
+
+    pub fn to_message (&self) -> Message {
+        let mut params = vec![self.subcommand().to_string()];
+        match self {
+            &Latest(ref t, ref r, _)
+            | &Before(ref t, ref r, _)
+            | &After(ref t, ref r, _)
+            | &Around(ref t, ref r, _) => {
+                params.push(t.clone());
+                params.push(r.to_param());
+            },
+            &Between(ref t, ref r1, ref r2, _) => {
+                params.push(t.clone());
+                params.push(r1.to_param());
+                params.push(r2.to_param());
+            },
+            &Targets(ref r1, ref r2, _) => {
+                params.push(r1.to_param());
+                params.push(r2.to_param());
+            },
+        }
+        params.push(self.limit().to_string());
+        Message::new(None, RawCommand("CHATHISTORY".to_string()), params)
+    }
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub struct HistoryMessage {
+    msgid: Option<String>,
+    time: Option<String>,
+    message: Message,
+}
+
+impl HistoryMessage {
+    pub fn msgid (&self) -> Option<&str> {
+        self.msgid.as_ref().map(|s| s.as_slice())
+    }
+
+    pub fn time (&self) -> Option<&str> {
+        self.time.as_ref().map(|s| s.as_slice())
+    }
+
+    pub fn message (&self) -> &Message {
+        &self.message
+    }
+
+    fn reference (&self) -> Option<MessageReference> {
+        match (self.msgid.as_ref(), self.time.as_ref()) {
+            (Some(id), _) => Some(MsgId(id.clone())),
+            (None, Some(ts)) => Some(Timestamp(ts.clone())),
+            (None, None) => None,
+        }
+    }
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub struct HistoryBatch {
+    target: String,
+    messages: Vec<HistoryMessage>,
+    request: Option<ChatHistoryRequest>,
+}
+
+impl HistoryBatch {
+    pub fn target (&self) -> &str {
+        self.target.as_slice()
+    }
+
+    pub fn messages (&self) -> &[HistoryMessage] {
+        self.messages.as_slice()
+    }
+
+    pub fn request (&self) -> Option<&ChatHistoryRequest> {
+        self.request.as_ref()
+    }
+
+    // the server returns fewer messages than were asked for once there is
+    // nothing more to page through
+    pub fn has_more (&self) -> bool {
+        match self.request {
+            Some(ref req) => self.messages.len() >= req.limit(),
+            None => self.messages.len() > 0,
+        }
+    }
+
+    pub fn next_page_before (&self, limit: uint) -> Option<ChatHistoryRequest> {
+        if !self.has_more() {
+            return None;
+        }
+        self.messages.iter().filter_map(|m| m.reference()).next().map(|r| {
+            Before(self.target.clone(), r, limit)
+        })
+    }
+
+    pub fn next_page_after (&self, limit: uint) -> Option<ChatHistoryRequest> {
+        if !self.has_more() {
+            return None;
+        }
+        self.messages.iter().rev().filter_map(|m| m.reference()).next().map(|r| {
+            After(self.target.clone(), r, limit)
+        })
+    }
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub struct HistoryTarget {
+    target: String,
+    time: String,
+}
+
+impl HistoryTarget {
+    pub fn target (&self) -> &str {
+        self.target.as_slice()
+    }
+
+    pub fn time (&self) -> &str {
+        self.time.as_slice()
+    }
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub struct HistoryError {
+    reply: StandardReply,
+    request: Option<ChatHistoryRequest>,
+}
+
+impl HistoryError {
+    pub fn reply (&self) -> &StandardReply {
+        &self.reply
+    }
+
+    pub fn code (&self) -> &str {
+        self.reply.code()
+    }
+
+    pub fn context (&self) -> &[String] {
+        self.reply.context()
+    }
+
+    pub fn description (&self) -> &str {
+        self.reply.description()
+    }
+
+    pub fn request (&self) -> Option<&ChatHistoryRequest> {
+        self.request.as_ref()
+    }
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum HistoryEvent {
+    HistoryMessages(HistoryBatch),
+    HistoryTargets(Vec<HistoryTarget>),
+    HistoryFailed(HistoryError),
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum HistoryProgress {
+    HistoryUnrelated,
+    HistoryCollected,
+    HistoryComplete(HistoryEvent),
+}
+
+enum OpenBatch {
+    MessagesBatch(HistoryBatch),
+    TargetsBatch(Vec<HistoryTarget>),
+}
+
+pub struct ChatHistoryCollector {
+    pending: Vec<ChatHistoryRequest>,
+    open: Vec<(String, OpenBatch)>,
+}
+
+impl ChatHistoryCollector {
+    pub fn new () -> ChatHistoryCollector {
+        ChatHistoryCollector { pending: vec![], open: vec![] }
+    }
+
+    pub fn expect (&mut self, req: ChatHistoryRequest) {
+        self.pending.push(req);
+    }
+
+    pub fn process (&mut self, m: &MessageRef) -> HistoryProgress {
+        let p = m.params();
+        if *m.message_type() == Batch {
+            match p.get(0) {
+                Some(r) if r.starts_with("+") => {
+                    let reference = r.slice_from(1).to_string();
+                    match (p.get(1).map(|s| *s), p.get(2)) {
+                        (Some("chathistory"), Some(target))
+                        | (Some("draft/chathistory"), Some(target)) => {
+                            let request = self.take_pending(Some(*target));
+                            let batch = HistoryBatch {
+                                target: target.to_string(),
+                                messages: vec![],
+                                request: request,
+                            };
+                            self.open.push((reference, MessagesBatch(batch)));
+                            HistoryCollected
+                        },
+                        (Some("draft/chathistory-targets"), _) => {
+                            let _ = self.take_pending(None);
+                            self.open.push((reference, TargetsBatch(vec![])));
+                            HistoryCollected
+                        },
+                        _ => HistoryUnrelated,
+                    }
+                },
+                Some(r) if r.starts_with("-") => {
+                    let reference = r.slice_from(1);
+                    match self.open.iter().position(|&(ref r, _)| r.as_slice() == reference) {
+                        Some(idx) => {
+                            match self.open.remove(idx) {
+                                Some((_, MessagesBatch(batch))) => {
+                                    HistoryComplete(HistoryMessages(batch))
+                                },
+                                Some((_, TargetsBatch(targets))) => {
+                                    HistoryComplete(HistoryTargets(targets))
+                                },
+                                None => HistoryUnrelated,
+                            }
+                        },
+                        None => HistoryUnrelated,
+                    }
+                },
+                _ => HistoryUnrelated,
+            }
+        }
+        else if *m.message_type() == Fail {
+            match StandardReply::from_message_ref(m) {
+                Some(reply) if reply.command() == "CHATHISTORY" => {
+                    // the context is the subcommand followed by the target,
+                    // when the server includes them
+                    let request = {
+                        let target = reply.context().get(1).map(|s| s.as_slice());
+                        self.take_pending(target)
+                    };
+                    HistoryComplete(HistoryFailed(HistoryError {
+                        reply: reply,
+                        request: request,
+                    }))
+                },
+                _ => HistoryUnrelated,
+            }
+        }
+        else {
+            let reference = match m.tag_value("batch") {
+                Some(r) => r,
+                None => return HistoryUnrelated,
+            };
+            let idx = match self.open.iter().position(|&(ref r, _)| r.as_slice() == reference.as_slice()) {
+                Some(idx) => idx,
+                None => return HistoryUnrelated,
+            };
+            match self.open.as_mut_slice()[idx] {
+                (_, MessagesBatch(ref mut batch)) => {
+                    batch.messages.push(HistoryMessage {
+                        msgid: m.tag_value("msgid").map(|s| s.into_string()),
+                        time: m.tag_value("time").map(|s| s.into_string()),
+                        message: m.to_message(),
+                    });
+                },
+                (_, TargetsBatch(ref mut targets)) => {
+                    match (p.get(0).map(|s| *s), p.get(1), p.get(2)) {
+                        (Some("TARGETS"), Some(target), Some(time)) => {
+                            targets.push(HistoryTarget {
+                                target: target.to_string(),
+                                time: time.to_string(),
+                            });
+                        },
+                        _ => {},
+                    }
+                },
+            }
+            HistoryCollected
+        }
+    }
+
+    // responses which don't match anything we asked for were sent
+    // unsolicited, and get no request
+    fn take_pending (&mut self, target: Option<&str>) -> Option<ChatHistoryRequest> {
+        let idx = match target {
+            Some(target) => {
+                self.pending.iter().position(|req| {
+                    req.target().map(|t| t.to_ascii_lower() == target.to_ascii_lower()).unwrap_or(false)
+                })
+            },
+            None => self.pending.iter().position(|req| req.target().is_none()),
+        };
+        match idx {
+            Some(idx) => self.pending.remove(idx),
+            None => None,
+        }
+    }
+}
+
+#[test]
+fn test_chathistory_request () {
+    assert_eq!(
+        Latest("#chan".to_string(), Unbounded, 50).to_message().to_protocol_string().as_slice(),
+        "CHATHISTORY LATEST #chan * 50\r\n"
+    );
+    assert_eq!(
+        Between(
+            "#chan".to_string(),
+            Timestamp("2019-01-04T14:33:26.123Z".to_string()),
+            MsgId("abc".to_string()),
+            10
+        ).to_message().to_protocol_string().as_slice(),
+        "CHATHISTORY BETWEEN #chan timestamp=2019-01-04T14:33:26.123Z msgid=abc 10\r\n"
+    );
+}
+
+#[test]
+fn test_chathistory_collector () {
+    let mut collector = ChatHistoryCollector::new();
+    collector.expect(Latest("#chan".to_string(), Unbounded, 2));
+
+    let lines = [
+        ":irc.host BATCH +ref1 chathistory #chan\r\n",
+        "@batch=ref1;time=2019-01-04T14:33:26.123Z;msgid=a1 :nick!u@h PRIVMSG #chan :one\r\n",
+        "@batch=ref1;time=2019-01-04T14:34:26.123Z;msgid=a2 :nick!u@h PRIVMSG #chan :two\r\n",
+    ];
+    for line in lines.iter() {
+        assert_eq!(collector.process(&MessageRef::parse(*line).unwrap()), HistoryCollected);
+    }
+
+    match collector.process(&MessageRef::parse(":irc.host BATCH -ref1\r\n").unwrap()) {
+        HistoryComplete(HistoryMessages(batch)) => {
+            assert_eq!(batch.target(), "#chan");
+            assert_eq!(batch.messages().len(), 2);
+            assert_eq!(batch.messages()[0].msgid(), Some("a1"));
+            assert_eq!(batch.messages()[1].time(), Some("2019-01-04T14:34:26.123Z"));
+            assert!(batch.has_more());
+            assert_eq!(
+                batch.next_page_before(2),
+                Some(Before("#chan".to_string(), MsgId("a1".to_string()), 2))
+            );
+        },
+        other => panic!("unexpected {}", other),
+    }
+
+    assert_eq!(
+        collector.process(&MessageRef::parse(":nick!u@h PRIVMSG #chan :live\r\n").unwrap()),
+        HistoryUnrelated
+    );
+
+    collector.expect(Before("#nope".to_string(), Unbounded, 10));
+    match collector.process(&MessageRef::parse("FAIL CHATHISTORY INVALID_TARGET BEFORE #nope :Messages could not be retrieved\r\n").unwrap()) {
+        HistoryComplete(HistoryFailed(err)) => {
+            assert_eq!(err.code(), "INVALID_TARGET");
+            assert_eq!(err.context(), ["BEFORE".to_string(), "#nope".to_string()].as_slice());
+            assert_eq!(err.description(), "Messages could not be retrieved");
+            assert_eq!(err.request(), Some(&Before("#nope".to_string(), Unbounded, 10)));
+        },
+        other => panic!("unexpected {}", other),
+    }
+
+    // a response for some other target leaves the pending request alone
+    collector.expect(Latest("#chan".to_string(), Unbounded, 10));
+    match collector.process(&MessageRef::parse("FAIL CHATHISTORY INVALID_TARGET LATEST #other :No such channel\r\n").unwrap()) {
+        HistoryComplete(HistoryFailed(err)) => assert_eq!(err.request(), None),
+        other => panic!("unexpected {}", other),
+    }
+    assert_eq!(
+        collector.process(&MessageRef::parse(":irc.host BATCH +ref2 chathistory #other\r\n").unwrap()),
+        HistoryCollected
+    );
+    match collector.process(&MessageRef::parse(":irc.host BATCH -ref2\r\n").unwrap()) {
+        HistoryComplete(HistoryMessages(batch)) => assert_eq!(batch.request(), None),
+        other => panic!("unexpected {}", other),
+    }
+    assert_eq!(collector.pending, vec![Latest("#chan".to_string(), Unbounded, 10)]);
+}
@@ -1,13 +1,55 @@
+use std::cmp::min;
+use std::comm::{Full, RecvDisconnected};
+use std::error::FromError;
 use std::io;
+use std::str;
 
+use chathistory::{ChatHistoryCollector, ChatHistoryRequest, HistoryBatch, HistoryError, HistoryTarget};
+use chathistory::{HistoryProgress, HistoryUnrelated, HistoryCollected, HistoryComplete};
+use chathistory::{HistoryMessages, HistoryTargets, HistoryFailed};
+use codec::{LineCodec, FrameError, LineTooLong, ReadError};
 use constants::*;
-use message::Message;
+use ctcp::{Ctcp, CtcpResponder};
+use dcc::DccOffer;
+use encoding::{EncodingConfig, TextEncoding, raw_target};
+use events::Events;
+use error::{IrcError, IrcResult, ParseError, PingTimeout, ClientQuit, ServerError, RegistrationFailed};
+use message::{Message, MessageBuilder, MessageRef, RawMessage, ParseMode, Lenient, Tag};
+use names::{NameEntry, NamesCollector};
+use net::NetStream;
+use standard_reply::StandardReply;
+use sts::{StsDirective, StsPolicy, StsPolicyStore};
+#[cfg(feature = "ssl")]
+use sts::FileStsPolicyStore;
+use time;
 
-pub enum MessageError {
-    ParseError(&'static str),
-    IoError(io::IoError),
+pub type MessageResult = Result<Message, IrcError>;
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum TypingState {
+    TypingActive,
+    TypingPaused,
+    TypingDone,
+}
+
+impl TypingState {
+    pub fn as_str (&self) -> &'static str {
+        match self {
+            &TypingActive => "active",
+            &TypingPaused => "paused",
+            &TypingDone => "done",
+        }
+    }
+
+    pub fn from_tag_value (value: &str) -> Option<TypingState> {
+        match value {
+            "active" => Some(TypingActive),
+            "paused" => Some(TypingPaused),
+            "done" => Some(TypingDone),
+            _ => None,
+        }
+    }
 }
-pub type MessageResult = Result<Message, MessageError>;
 
 pub struct ClientBuilder {
     nick: String,
@@ -19,6 +61,18 @@ pub struct ClientBuilder {
 
     servername: String,
     port: u16,
+    ssl: bool,
+
+    caps: Vec<String>,
+    sts_store: Option<Box<StsPolicyStore + Send>>,
+    sts_upgrade: bool,
+
+    ctcp: CtcpResponder,
+    encoding: EncodingConfig,
+    parse_mode: ParseMode,
+    max_line_length: uint,
+    ping_timeout: Option<u64>,
+    send_queue_limit: Option<uint>,
 
     debug: bool,
 }
@@ -35,6 +89,18 @@ impl ClientBuilder {
 
             servername: servername.to_string(),
             port: 6667,
+            ssl: false,
+
+            caps: vec![],
+            sts_store: default_sts_store(),
+            sts_upgrade: true,
+
+            ctcp: CtcpResponder::new(),
+            encoding: EncodingConfig::new(),
+            parse_mode: Lenient,
+            max_line_length: MAX_TAGS_LENGTH + MAX_MESSAGE_LENGTH,
+            ping_timeout: None,
+            send_queue_limit: None,
 
             debug: false,
         }
@@ -65,38 +131,324 @@ impl ClientBuilder {
         self
     }
 
+    pub fn set_ssl (&mut self, ssl: bool) -> &mut ClientBuilder {
+        self.ssl = ssl;
+        self
+    }
+
+    // where sts policies are remembered between connections. with the ssl
+    // feature this defaults to a FileStsPolicyStore in the home directory,
+    // and without it there's no store, since there'd be no way to follow
+    // the policies anyway. setting one makes registration start cap
+    // negotiation, so that the server's policy can be seen.
+    pub fn set_sts_store (&mut self, store: Option<Box<StsPolicyStore + Send>>) -> &mut ClientBuilder {
+        self.sts_store = store;
+        self
+    }
+
+    // whether a plain connection is replaced by a tls one when the server's
+    // sts policy asks for it. stored policies still apply either way.
+    pub fn set_sts_upgrade (&mut self, upgrade: bool) -> &mut ClientBuilder {
+        self.sts_upgrade = upgrade;
+        self
+    }
+
+    // caps are only requested if they're asked for here, including ones
+    // which change what is parsed, like multi-prefix and userhost-in-names
+    // for NAMES replies
+    pub fn request_cap (&mut self, cap: &str) -> &mut ClientBuilder {
+        self.caps.push(cap.to_string());
+        self
+    }
+
+    pub fn set_ctcp_version (&mut self, version: Option<&str>) -> &mut ClientBuilder {
+        self.ctcp.set_version(version);
+        self
+    }
+
+    pub fn set_ctcp_source (&mut self, source: Option<&str>) -> &mut ClientBuilder {
+        self.ctcp.set_source(source);
+        self
+    }
+
+    pub fn set_ctcp_ping (&mut self, ping: bool) -> &mut ClientBuilder {
+        self.ctcp.set_ping(ping);
+        self
+    }
+
+    pub fn set_ctcp_time (&mut self, time: bool) -> &mut ClientBuilder {
+        self.ctcp.set_time(time);
+        self
+    }
+
+    pub fn set_encoding (&mut self, encoding: Box<TextEncoding + Send>) -> &mut ClientBuilder {
+        self.encoding.set_default(encoding);
+        self
+    }
+
+    // target is a channel or a nick. for a nick, this covers both what we
+    // send to them and what they send to us directly.
+    pub fn set_target_encoding (&mut self, target: &str, encoding: Box<TextEncoding + Send>) -> &mut ClientBuilder {
+        self.encoding.set_target(target, encoding);
+        self
+    }
+
+    pub fn set_try_utf8 (&mut self, try_utf8: bool) -> &mut ClientBuilder {
+        self.encoding.set_try_utf8(try_utf8);
+        self
+    }
+
+    pub fn set_parse_mode (&mut self, mode: ParseMode) -> &mut ClientBuilder {
+        self.parse_mode = mode;
+        self
+    }
+
+    // including the line ending and any tags. longer lines are skipped.
+    pub fn set_max_line_length (&mut self, max_line_length: uint) -> &mut ClientBuilder {
+        self.max_line_length = max_line_length;
+        self
+    }
+
+    // in milliseconds. if nothing is received for this long, the server is
+    // pinged, and if nothing arrives for another timeout period after that,
+    // the loop stops with PingTimeout.
+    pub fn set_ping_timeout (&mut self, ping_timeout: Option<u64>) -> &mut ClientBuilder {
+        self.ping_timeout = ping_timeout;
+        self
+    }
+
+    // how many messages a ClientSender can queue before writing blocks (or
+    // try_write fails). None means the queue can grow without limit.
+    pub fn set_send_queue_limit (&mut self, limit: Option<uint>) -> &mut ClientBuilder {
+        self.send_queue_limit = limit;
+        self
+    }
+
     pub fn set_debug (&mut self, debug: bool) -> &mut ClientBuilder {
         self.debug = debug;
         self
     }
 
+    pub fn max_line_length (&self) -> uint {
+        self.max_line_length
+    }
+
+    pub fn ping_timeout (&self) -> Option<u64> {
+        self.ping_timeout
+    }
+
     pub fn connect (self) -> Client {
-        let stream = io::TcpStream::connect(self.servername.as_slice(), self.port);
-        let mut stream = stream.unwrap();
+        self.try_connect().unwrap()
+    }
+
+    // whether connecting will use tls, either because it was asked for or
+    // because of a stored sts policy for the host
+    pub fn uses_tls (&mut self) -> bool {
+        // a stored sts policy means we must never connect to this host
+        // without tls, so it takes precedence over the configured port.
+        // builds without tls can't follow it, so they carry on as before.
+        if !self.ssl && cfg!(feature = "ssl") {
+            let policy = match self.sts_store {
+                Some(ref mut store) => store.get(self.servername.as_slice()),
+                None => None,
+            };
+            match policy {
+                Some(policy) => {
+                    self.ssl = true;
+                    self.port = policy.port();
+                },
+                None => {},
+            }
+        }
+        self.ssl
+    }
+
+    pub fn try_connect (mut self) -> io::IoResult<Client> {
+        self.uses_tls();
+
+        let mut stream = try!(NetStream::connect(self.servername.as_slice(), self.port, self.ssl));
+        stream.set_read_timeout(self.ping_timeout);
         let socket_name = match stream.socket_name() {
             Ok(addr) => Some(addr.ip.to_string()),
             Err(_) => None,
         };
-        Client::new(self, io::BufferedStream::new(stream), socket_name)
+        Ok(Client::new(self, io::BufferedStream::new(stream), socket_name))
+    }
+}
+
+// how often, in ms, the run loops check for messages from ClientSenders
+// when nothing is being read
+pub static SEND_POLL_INTERVAL: u64 = 100;
+
+enum SendQueue {
+    UnboundedQueue(Sender<Message>),
+    BoundedQueue(SyncSender<Message>),
+}
+
+impl Clone for SendQueue {
+    fn clone (&self) -> SendQueue {
+        match self {
+            &UnboundedQueue(ref tx) => UnboundedQueue(tx.clone()),
+            &BoundedQueue(ref tx) => BoundedQueue(tx.clone()),
+        }
+    }
+}
+
+// queues messages for a client's run loop to send, from any task. see
+// Client::sender.
+#[deriving(Clone)]
+pub struct ClientSender {
+    queue: SendQueue,
+}
+
+impl ClientSender {
+    // if the queue is full, this waits for the loop to make room. that
+    // never happens while a callback on the same task is running, so use
+    // try_write (or the Client itself) there.
+    pub fn write (&self, m: Message) -> io::IoResult<()> {
+        try!(validate(&m));
+        let sent = match self.queue {
+            UnboundedQueue(ref tx) => tx.send_opt(m),
+            BoundedQueue(ref tx) => tx.send_opt(m),
+        };
+        sent.map_err(|_| closed())
+    }
+
+    // fails with ResourceUnavailable rather than waiting if the queue is
+    // full
+    pub fn try_write (&self, m: Message) -> io::IoResult<()> {
+        try!(validate(&m));
+        match self.queue {
+            UnboundedQueue(ref tx) => tx.send_opt(m).map_err(|_| closed()),
+            BoundedQueue(ref tx) => match tx.try_send(m) {
+                Ok(()) => Ok(()),
+                Err(Full(_)) => Err(io::IoError {
+                    kind: io::ResourceUnavailable,
+                    desc: "send queue is full",
+                    detail: None,
+                }),
+                Err(RecvDisconnected(_)) => Err(closed()),
+            },
+        }
+    }
+
+    pub fn send (&self, builder: MessageBuilder) -> io::IoResult<()> {
+        match builder.build() {
+            Ok(m) => self.write(m),
+            Err(e) => Err(io::IoError { kind: io::InvalidInput, desc: e, detail: None }),
+        }
+    }
+
+    pub fn privmsg (&self, receivers: &[&str], text: &str) -> io::IoResult<()> {
+        self.write(Message::new(None, Privmsg, vec![receivers.connect(","), text.to_string()]))
+    }
+
+    pub fn notice (&self, nickname: &str, text: &str) -> io::IoResult<()> {
+        self.write(Message::new(None, Notice, vec![nickname.to_string(), text.to_string()]))
+    }
+}
+
+// invalid messages are rejected when they're queued, rather than stopping
+// the loop when it tries to write them
+fn validate (m: &Message) -> io::IoResult<()> {
+    match m.validate() {
+        Ok(()) => Ok(()),
+        Err(e) => Err(io::IoError { kind: io::InvalidInput, desc: e, detail: None }),
     }
 }
 
+fn closed () -> io::IoError {
+    io::IoError { kind: io::BrokenPipe, desc: "connection closed", detail: None }
+}
+
+#[cfg(feature = "ssl")]
+fn default_sts_store () -> Option<Box<StsPolicyStore + Send>> {
+    FileStsPolicyStore::default_path().map(|path| {
+        box FileStsPolicyStore::new(path) as Box<StsPolicyStore + Send>
+    })
+}
+
+#[cfg(not(feature = "ssl"))]
+fn default_sts_store () -> Option<Box<StsPolicyStore + Send>> {
+    None
+}
+
 pub struct Client {
     builder: ClientBuilder,
-    conn: io::BufferedStream<io::TcpStream>,
+    conn: io::BufferedStream<NetStream>,
+    codec: LineCodec,
     socket_name: Option<String>,
+
+    server_caps: Vec<(String, Option<String>)>,
+    enabled_caps: Vec<String>,
+    negotiating_caps: bool,
+    // set while a multiline CAP LS reply is being read
+    listing_caps: bool,
+    // set while PASS, NICK and USER wait for the reply to CAP LS
+    registration_held: bool,
+
+    registered: bool,
+    quitting: bool,
+    // how many times NICK has been sent, so that we can tell whether
+    // anything responded to a nick error
+    nicks_sent: uint,
+    // the last line which couldn't be parsed, until take_parse_error
+    parse_error: Option<IrcError>,
+    // set when a read times out and the server gets pinged
+    pinged: bool,
+    // when the last line was read (or the server was pinged), in ns
+    last_read: u64,
+
+    // messages queued by ClientSenders, which are written between reads.
+    // the receiver is dropped when the loop stops, so that senders can tell.
+    sender: Option<ClientSender>,
+    outgoing: Option<Receiver<Message>>,
+
+    chathistory: ChatHistoryCollector,
+    names: NamesCollector,
 }
 
 impl Client {
-    pub fn new (builder: ClientBuilder, conn: io::BufferedStream<io::TcpStream>, socket_name: Option<String>) -> Client {
-        Client { builder: builder, conn: conn, socket_name: socket_name }
+    pub fn new (builder: ClientBuilder, conn: io::BufferedStream<NetStream>, socket_name: Option<String>) -> Client {
+        let codec = LineCodec::new(builder.max_line_length);
+        Client {
+            builder: builder,
+            conn: conn,
+            codec: codec,
+            socket_name: socket_name,
+
+            server_caps: vec![],
+            enabled_caps: vec![],
+            negotiating_caps: false,
+            listing_caps: false,
+            registration_held: false,
+
+            registered: false,
+            quitting: false,
+            nicks_sent: 0,
+            parse_error: None,
+            pinged: false,
+            last_read: time::precise_time_ns(),
+
+            sender: None,
+            outgoing: None,
+
+            chathistory: ChatHistoryCollector::new(),
+            names: NamesCollector::new(),
+        }
     }
     pub fn builder (&self) -> &ClientBuilder {
         &self.builder
     }
-    pub fn conn (&mut self) -> &mut io::BufferedStream<io::TcpStream> {
+    pub fn conn (&mut self) -> &mut io::BufferedStream<NetStream> {
         &mut self.conn
     }
+    pub fn is_secure (&self) -> bool {
+        self.conn.get_ref().is_secure()
+    }
+    pub fn encoding (&mut self) -> &mut EncodingConfig {
+        &mut self.builder.encoding
+    }
     pub fn socket_name (&self) -> Option<&str> {
         match self.socket_name {
             Some(ref name) => Some(name.as_slice()),
@@ -104,116 +456,684 @@ impl Client {
         }
     }
 
-    pub fn read (&mut self) -> MessageResult {
-        // \n isn't valid inside a message, so this should be fine. if the \n
-        // we find isn't preceded by a \r, this will be caught by the message
-        // parser.
-        let mut buf = [0, ..MAX_MESSAGE_LENGTH];
-        let mut len = 0;
-        for (res, i) in self.conn().bytes().zip(range(0, MAX_MESSAGE_LENGTH - 1)) {
-            match res {
-                Ok(b) => {
-                    buf[i] = b;
-                    if b == b'\n' {
-                        len = i + 1;
-                        break;
-                    }
-                },
-                Err(e) => return Err(IoError(e)),
+    pub fn has_cap (&self, cap: &str) -> bool {
+        self.enabled_caps.iter().any(|c| c.as_slice() == cap)
+    }
+    pub fn server_cap_value (&self, cap: &str) -> Option<&str> {
+        for &(ref name, ref value) in self.server_caps.iter() {
+            if name.as_slice() == cap {
+                return value.as_ref().map(|s| s.as_slice());
             }
         }
+        None
+    }
 
-        // XXX handle different encodings
-        match Message::parse(String::from_utf8_lossy(buf.slice(0, len)).as_slice()) {
+    pub fn read (&mut self) -> MessageResult {
+        let mut raw = vec![];
+        try!(self.read_line(&mut raw));
+
+        let line = self.decode_line(raw.as_slice());
+        let parsed = Message::parse_with_mode(line.as_slice(), self.builder.parse_mode);
+        match parsed {
             Ok(m) => {
                 if self.builder.debug {
                     print!("R {}", m.to_protocol_string());
                 }
                 Ok(m)
             },
-            Err(s) => Err(ParseError(s)),
+            Err(e) => Err(ParseError(line, e)),
+        }
+    }
+
+    // like read, but keeps the line's original bytes rather than decoding
+    // it, so that it can be passed on unchanged
+    pub fn read_raw (&mut self) -> Result<RawMessage, IrcError> {
+        let mut raw = vec![];
+        try!(self.read_line(&mut raw));
+
+        match RawMessage::parse_with_mode(raw.as_slice(), self.builder.parse_mode) {
+            Ok(m) => {
+                if self.builder.debug {
+                    print!("R {}", String::from_utf8_lossy(m.as_bytes()));
+                }
+                Ok(m)
+            },
+            Err(e) => Err(ParseError(self.decode_line(raw.as_slice()), e)),
         }
     }
 
+    // reads the next line, including its line ending, into out
+    pub fn read_line (&mut self, out: &mut Vec<u8>) -> Result<(), FrameError> {
+        self.codec.read_line_into(&mut self.conn, out)
+    }
+
+    pub fn decode_line (&self, raw: &[u8]) -> String {
+        let target = raw_target(raw);
+        self.builder.encoding.decode(target.as_ref().map(|s| s.as_slice()), raw)
+    }
+
     pub fn write (&mut self, m: Message) -> io::IoResult<()> {
-        try!(m.write_protocol_string(self.conn()));
+        match m.validate() {
+            Ok(()) => {},
+            Err(e) => return Err(io::IoError { kind: io::InvalidInput, desc: e, detail: None }),
+        }
+        let line = m.to_protocol_string();
+        let bytes = self.builder.encoding.encode(message_target(&m), line.as_slice());
+        try!(self.conn.write(bytes.as_slice()));
+        try!(self.conn.flush());
         if self.builder.debug {
-            print!("W {}", m.to_protocol_string());
+            print!("W {}", line);
         }
         Ok(())
     }
 
-    pub fn run_loop (&mut self, handler: |&mut Client, &Message| -> io::IoResult<()>) -> io::IoError {
+    // sends the message's bytes exactly as they were read or built
+    pub fn write_raw (&mut self, m: &RawMessage) -> io::IoResult<()> {
+        try!(m.write_to(&mut self.conn));
+        try!(self.conn.flush());
+        if self.builder.debug {
+            print!("W {}", String::from_utf8_lossy(m.as_bytes()));
+        }
+        Ok(())
+    }
+
+    // builds the message and sends it, failing without sending anything if
+    // it isn't valid
+    pub fn send (&mut self, builder: MessageBuilder) -> io::IoResult<()> {
+        match builder.build() {
+            Ok(m) => self.write(m),
+            Err(e) => Err(io::IoError { kind: io::InvalidInput, desc: e, detail: None }),
+        }
+    }
+
+    // runs until something stops the loop, and returns the reason why.
+    // lines which are skipped aren't reported to the handler here or in
+    // the other closure loops, only through ClientCallbacks and Events. the
+    // handler can call take_parse_error to check whether a line was skipped
+    // before the current one, but only the last one is kept.
+    pub fn run_loop (&mut self, mut handler: |&mut Client, &Message| -> IrcResult<()>) -> IrcError {
+        self.run_loop_borrowed(|client, m| handler(client, &m.to_message()))
+    }
+
+    // like run_loop, but messages borrow from the read buffer rather than
+    // being copied out of it
+    pub fn run_loop_borrowed (&mut self, mut handler: |&mut Client, &MessageRef| -> IrcResult<()>) -> IrcError {
+        let mut buf = Vec::with_capacity(self.builder.max_line_length);
+        loop {
+            match self.handle_next(&mut buf, |client, m| handler(client, m)) {
+                Ok(()) => {},
+                Err(e) => return e,
+            }
+        }
+    }
+
+    // a single turn of run_loop_borrowed, for callers which run the loop
+    // themselves. buf is only used to hold the line being handled, and an
+    // error means the loop has to stop. nothing is handled if the line
+    // was too long or couldn't be parsed, or if the read timed out, in which
+    // case the server gets pinged.
+    pub fn handle_next (&mut self, buf: &mut Vec<u8>, handler: |&mut Client, &MessageRef| -> IrcResult<()>) -> IrcResult<()> {
+        let read = self.read_line(buf);
+        self.handle_read(read.map(|()| buf.as_slice()), handler)
+    }
+
+    // like handle_next, for lines which were read somewhere other than
+    // from this client's connection
+    pub fn handle_read (&mut self, read: Result<&[u8], FrameError>, handler: |&mut Client, &MessageRef| -> IrcResult<()>) -> IrcResult<()> {
+        let res = self.handle_line(read, handler);
+        if res.is_err() {
+            self.close_senders();
+        }
+        res
+    }
+
+    fn handle_line (&mut self, read: Result<&[u8], FrameError>, handler: |&mut Client, &MessageRef| -> IrcResult<()>) -> IrcResult<()> {
+        let raw = match read {
+            Ok(raw) => raw,
+            Err(e) => return self.check_read(Err(e)).map(|_| ()),
+        };
+        self.pinged = false;
+        self.last_read = time::precise_time_ns();
+
+        // lines only need to be copied when they have to be converted
+        // from some other encoding
+        let decoded;
+        let line = match str::from_utf8(raw) {
+            Some(line) if self.builder.encoding.try_utf8() => line,
+            _ => {
+                decoded = self.decode_line(raw);
+                decoded.as_slice()
+            },
+        };
+
+        let m = match MessageRef::parse_with_mode(line, self.builder.parse_mode) {
+            Ok(m) => m,
+            Err(e) => {
+                self.parse_error = Some(ParseError(line.to_string(), e));
+                return Ok(try!(self.drain_senders()));
+            },
+        };
+        if self.builder.debug {
+            print!("R {}", line);
+        }
+        let nicks_sent = self.nicks_sent;
+        try!(handler(self, &m));
+        match self.loop_end(m.message_type(), m.params(), nicks_sent) {
+            Some(e) => Err(e),
+            None => Ok(try!(self.drain_senders())),
+        }
+    }
+
+    // like run_loop, but messages keep the bytes they were read as
+    pub fn run_loop_raw (&mut self, handler: |&mut Client, &RawMessage| -> IrcResult<()>) -> IrcError {
+        let mut buf = Vec::with_capacity(self.builder.max_line_length);
         loop {
-            let m = match self.read() {
+            match self.loop_read(&mut buf) {
+                Ok(true) => {},
+                Ok(false) => continue,
+                Err(e) => {
+                    self.close_senders();
+                    return e;
+                },
+            }
+
+            let m = match RawMessage::parse_with_mode(buf.as_slice(), self.builder.parse_mode) {
                 Ok(m) => m,
-                Err(ParseError(_e)) => {
-                    // XXX this shouldn't stop the loop, but it's not clear
-                    // what it should do - warn maybe?
-                    continue
+                Err(e) => {
+                    let line = String::from_utf8_lossy(buf.as_slice()).into_string();
+                    self.parse_error = Some(ParseError(line, e));
+                    continue;
                 },
-                Err(IoError(e)) => return e,
             };
-            match handler(self, &m) {
-                Err(e) => return e,
-                _ => {},
+            if self.builder.debug {
+                print!("R {}", String::from_utf8_lossy(m.as_bytes()));
+            }
+            let nicks_sent = self.nicks_sent;
+            let res = handler(self, &m).and_then(|()| {
+                let decoded = m.decode(&self.builder.encoding);
+                let params: Vec<&str> = decoded.params().iter().map(|p| p.as_slice()).collect();
+                match self.loop_end(m.message_type(), params.as_slice(), nicks_sent) {
+                    Some(e) => Err(e),
+                    None => Ok(try!(self.drain_senders())),
+                }
+            });
+            match res {
+                Err(e) => {
+                    self.close_senders();
+                    return e;
+                },
+                Ok(()) => {},
             }
         }
     }
 
-    pub fn run_loop_with_callbacks<T: ClientCallbacks> (mut self, cbs: T) -> io::IoError {
+    pub fn run_loop_with_callbacks<T: ClientCallbacks> (mut self, cbs: T) -> IrcError {
         cbs.run_loop(&mut self)
     }
 
-    pub fn pass (&mut self, pass: &str) -> io::IoResult<()> {
-        self.write(Message::new(None, Pass, vec![pass.to_string()]))
+    // a second handle on the connection for reading, with the same read
+    // timeout. only works for plain connections.
+    pub fn try_clone_stream (&self) -> io::IoResult<NetStream> {
+        let mut stream = try!(self.conn.get_ref().try_clone());
+        stream.set_read_timeout(self.read_timeout());
+        Ok(stream)
     }
-    pub fn nick (&mut self, nick: &str) -> io::IoResult<()> {
-        self.write(Message::new(None, Nick, vec![nick.to_string()]))
+
+    // a handle which other tasks can use to send messages on this
+    // connection. they're written by whichever loop is running, between
+    // reading lines, so while there are senders reads time out every
+    // SEND_POLL_INTERVAL ms to check for them. once the loop stops (or the
+    // client is dropped), writing to a sender fails with BrokenPipe.
+    pub fn sender (&mut self) -> ClientSender {
+        match self.sender {
+            Some(ref sender) => return sender.clone(),
+            None => {},
+        }
+        let (queue, outgoing) = match self.builder.send_queue_limit {
+            Some(limit) => {
+                let (tx, rx) = sync_channel(limit);
+                (BoundedQueue(tx), rx)
+            },
+            None => {
+                let (tx, rx) = channel();
+                (UnboundedQueue(tx), rx)
+            },
+        };
+        let sender = ClientSender { queue: queue };
+        self.sender = Some(sender.clone());
+        self.outgoing = Some(outgoing);
+        let timeout = self.read_timeout();
+        self.conn.get_mut().set_read_timeout(timeout);
+        sender
     }
-    pub fn user (&mut self, username: &str, hostname: &str, servername: &str, realname: &str) -> io::IoResult<()> {
+
+    // lines which can't be parsed or are too long are skipped rather than
+    // stopping the loop, and the ParseError for the last one is kept here
+    // until it's taken (with an empty line, for lines which were too long).
+    // ClientCallbacks get it through on_parse_error, and Events as a
+    // ParseErrorEvent.
+    pub fn take_parse_error (&mut self) -> Option<IrcError> {
+        self.parse_error.take()
+    }
+
+    // an alternative to the run loops which leaves the caller in control
+    pub fn events (&mut self) -> Events {
+        Events::new(self)
+    }
+
+    // reads the next line into buf for one of the run loops. returns false
+    // if there's nothing to handle this time around.
+    fn loop_read (&mut self, buf: &mut Vec<u8>) -> Result<bool, IrcError> {
+        let read = self.read_line(buf);
+        self.check_read(read)
+    }
+
+    fn check_read (&mut self, read: Result<(), FrameError>) -> Result<bool, IrcError> {
+        match read {
+            Ok(()) => {
+                self.pinged = false;
+                self.last_read = time::precise_time_ns();
+                Ok(true)
+            },
+            // the rest of the line has been skipped, so just move on, but
+            // keep the error around like any other line that was skipped
+            Err(LineTooLong(len)) => {
+                self.parse_error = Some(FromError::from_error(LineTooLong(len)));
+                Ok(false)
+            },
+            // the read timeout is the ping timeout. the first time it runs
+            // out the server gets pinged, and if it's still quiet the next
+            // time, the connection is dead.
+            Err(ReadError(ref e)) if e.kind == io::TimedOut => {
+                try!(self.drain_senders());
+                let ping_timeout = match self.builder.ping_timeout {
+                    Some(ping_timeout) => ping_timeout,
+                    None => return Ok(false),
+                };
+                // reads time out more often while there are senders to
+                // check on, so go by how long it's actually been
+                let now = time::precise_time_ns();
+                if self.outgoing.is_some() && (now - self.last_read) / 1_000_000 < ping_timeout {
+                    return Ok(false);
+                }
+                if self.pinged {
+                    return Err(PingTimeout);
+                }
+                self.pinged = true;
+                self.last_read = now;
+                let servername = self.builder.servername.clone();
+                try!(self.ping(servername.as_slice()));
+                Ok(false)
+            },
+            Err(ReadError(ref e)) if e.kind == io::EndOfFile && self.quitting => {
+                Err(ClientQuit)
+            },
+            Err(e) => Err(FromError::from_error(e)),
+        }
+    }
+
+    // writes everything the senders have queued so far
+    fn drain_senders (&mut self) -> io::IoResult<()> {
+        loop {
+            let m = match self.outgoing {
+                Some(ref outgoing) => match outgoing.try_recv() {
+                    Ok(m) => m,
+                    Err(_) => return Ok(()),
+                },
+                None => return Ok(()),
+            };
+            try!(self.write(m));
+        }
+    }
+
+    // anything still queued is dropped, and the senders all start failing
+    fn close_senders (&mut self) {
+        self.outgoing = None;
+        let timeout = self.read_timeout();
+        self.conn.get_mut().set_read_timeout(timeout);
+    }
+
+    fn read_timeout (&self) -> Option<u64> {
+        match (self.outgoing.is_some(), self.builder.ping_timeout) {
+            (true, Some(ping_timeout)) => Some(min(ping_timeout, SEND_POLL_INTERVAL)),
+            (true, None) => Some(SEND_POLL_INTERVAL),
+            (false, ping_timeout) => ping_timeout,
+        }
+    }
+
+    // checks whether a message which has already been handled means the
+    // loop has to stop. nick errors during registration only count as a
+    // failure if the handler didn't respond by trying another nick.
+    fn loop_end (&mut self, message_type: &MessageType, params: &[&str], nicks_sent: uint) -> Option<IrcError> {
+        match *message_type {
+            Error if self.quitting => Some(ClientQuit),
+            Error => {
+                let text = params.get(0).map(|s| s.to_string());
+                Some(ServerError(text.unwrap_or(String::new())))
+            },
+            Reply(RPL_WELCOME) => {
+                self.registered = true;
+                None
+            },
+            // servers which don't know about CAP won't ever reply to CAP LS
+            Reply(ERR_UNKNOWNCOMMAND) if self.registration_held => {
+                self.send_registration().err().map(|e| FromError::from_error(e))
+            },
+            Reply(code) if !self.registered => {
+                let text = params.last().map(|s| s.to_string()).unwrap_or(String::new());
+                match code {
+                    ERR_PASSWDMISMATCH | ERR_YOUREBANNEDCREEP => {
+                        Some(RegistrationFailed(code, text))
+                    },
+                    ERR_NONICKNAMEGIVEN | ERR_ERRONEUSNICKNAME | ERR_NICKNAMEINUSE
+                        | ERR_NICKCOLLISION | ERR_UNAVAILRESOURCE
+                        if self.nicks_sent == nicks_sent => {
+                        Some(RegistrationFailed(code, text))
+                    },
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
+
+    pub fn is_registered (&self) -> bool {
+        self.registered
+    }
+
+    pub fn cap_ls (&mut self) -> io::IoResult<()> {
+        self.negotiating_caps = true;
         self.write(Message::new(
             None,
-            User,
-            vec![
-                username.to_string(),
-                hostname.to_string(),
-                servername.to_string(),
-                realname.to_string(),
-            ]
+            Cap,
+            vec!["LS".to_string(), "302".to_string()]
         ))
     }
-    pub fn oper (&mut self, user: &str, pass: &str) -> io::IoResult<()> {
+    pub fn cap_req (&mut self, caps: &[&str]) -> io::IoResult<()> {
         self.write(Message::new(
             None,
-            Oper,
-            vec![
-                user.to_string(),
-                pass.to_string(),
-            ]
+            Cap,
+            vec!["REQ".to_string(), caps.connect(" ")]
         ))
     }
-    pub fn quit (&mut self, msg: Option<&str>) -> io::IoResult<()> {
+    pub fn cap_end (&mut self) -> io::IoResult<()> {
+        self.negotiating_caps = false;
         self.write(Message::new(
             None,
-            Quit,
-            vec![].append(msg.map(|s| s.to_string()).as_slice())
+            Cap,
+            vec!["END".to_string()]
         ))
     }
 
-    pub fn join (&mut self, channels: &[&str], keys: &[&str]) -> io::IoResult<()> {
-        let mut params = vec![channels.connect(",")];
-        if keys.len() > 0 {
-            params.push(keys.connect(","));
-        }
-        self.write(Message::new(None, Join, params))
-    }
-    pub fn part (&mut self, channels: &[&str]) -> io::IoResult<()> {
-        self.write(Message::new(None, Part, vec![channels.connect(",")]))
-    }
-    pub fn channel_mode (&mut self, channel: &str, modes: &str, params: &[&str]) -> io::IoResult<()> {
-        let p: Vec<String> = params.iter().map(|s| s.to_string()).collect();
-        self.write(Message::new(
+    // handles the replies to CAP LS and CAP REQ sent during registration,
+    // requesting whichever of the caps given to the builder the server
+    // supports
+    pub fn negotiate_caps (&mut self, m: &MessageRef) -> io::IoResult<()> {
+        let p = m.params();
+        let (subcommand, more, caps) = match (p.get(1), p.get(2), p.get(3)) {
+            (Some(sub), Some(star), Some(caps)) if star.as_slice() == "*" => {
+                (sub.as_slice(), true, caps.as_slice())
+            },
+            (Some(sub), Some(caps), None) => (sub.as_slice(), false, caps.as_slice()),
+            _ => return Ok(()),
+        };
+
+        match subcommand {
+            "LS" | "NEW" => {
+                // a new LS reply lists everything the server supports, so
+                // it replaces what an earlier one said
+                if subcommand == "LS" {
+                    if !self.listing_caps {
+                        self.server_caps = vec![];
+                    }
+                    self.listing_caps = more;
+                }
+                for cap in caps.split(' ').filter(|c| c.len() > 0) {
+                    let (name, value) = match cap.find('=') {
+                        Some(idx) => (cap.slice_to(idx), Some(cap.slice_from(idx + 1).to_string())),
+                        None => (cap, None),
+                    };
+                    match self.server_caps.iter().position(|&(ref c, _)| c.as_slice() == name) {
+                        Some(idx) => self.server_caps.as_mut_slice()[idx] = (name.to_string(), value),
+                        None => self.server_caps.push((name.to_string(), value)),
+                    }
+                }
+                if more {
+                    return Ok(());
+                }
+
+                if subcommand == "LS" && try!(self.apply_sts_policy()) {
+                    return Ok(());
+                }
+                if self.registration_held {
+                    try!(self.send_registration());
+                }
+
+                let wanted: Vec<String> = self.builder.caps.iter().filter(|c| {
+                    !self.has_cap(c.as_slice())
+                        && self.server_caps.iter().any(|&(ref name, _)| name == *c)
+                }).map(|c| c.clone()).collect();
+                if wanted.len() > 0 {
+                    let wanted: Vec<&str> = wanted.iter().map(|c| c.as_slice()).collect();
+                    self.cap_req(wanted.as_slice())
+                }
+                else if self.negotiating_caps {
+                    self.cap_end()
+                }
+                else {
+                    Ok(())
+                }
+            },
+            "ACK" => {
+                for cap in caps.split(' ').filter(|c| c.len() > 0) {
+                    if cap.starts_with("-") {
+                        let cap = cap.slice_from(1);
+                        self.enabled_caps.retain(|c| c.as_slice() != cap);
+                    }
+                    else if !self.has_cap(cap) {
+                        self.enabled_caps.push(cap.to_string());
+                    }
+                }
+                if self.negotiating_caps && !more { self.cap_end() } else { Ok(()) }
+            },
+            "NAK" => {
+                if self.negotiating_caps && !more { self.cap_end() } else { Ok(()) }
+            },
+            "DEL" => {
+                for cap in caps.split(' ').filter(|c| c.len() > 0) {
+                    self.enabled_caps.retain(|c| c.as_slice() != cap);
+                    self.server_caps.retain(|&(ref c, _)| c.as_slice() != cap);
+                }
+                Ok(())
+            },
+            _ => Ok(()),
+        }
+    }
+
+    // returns true if the connection was replaced by a secure one, in which
+    // case registration has already been restarted
+    fn apply_sts_policy (&mut self) -> io::IoResult<bool> {
+        // without tls there's no way to follow the policy, so it's ignored
+        if !cfg!(feature = "ssl") {
+            return Ok(false);
+        }
+        let directive = match self.server_cap_value("sts") {
+            Some(value) => StsDirective::parse(value),
+            None => return Ok(false),
+        };
+
+        // secure connections have had their certificate checked against the
+        // host, so the policy can be trusted. a policy seen over a plain
+        // connection is only used to upgrade it, never stored.
+        if self.is_secure() {
+            let host = self.builder.servername.clone();
+            let port = self.builder.port;
+            match (self.builder.sts_store.as_mut(), directive.duration()) {
+                (Some(store), Some(0)) => try!(store.remove(host.as_slice())),
+                (Some(store), Some(duration)) => {
+                    try!(store.put(StsPolicy::new(
+                        host.as_slice(), port, duration, directive.preload()
+                    )));
+                },
+                _ => {},
+            }
+            Ok(false)
+        }
+        else if self.builder.sts_upgrade {
+            match directive.port() {
+                Some(port) => {
+                    try!(self.reconnect_secure(port));
+                    Ok(true)
+                },
+                None => Ok(false),
+            }
+        }
+        else {
+            Ok(false)
+        }
+    }
+
+    pub fn reconnect_secure (&mut self, port: u16) -> io::IoResult<()> {
+        let mut stream = try!(NetStream::connect(self.builder.servername.as_slice(), port, true));
+        stream.set_read_timeout(self.read_timeout());
+        self.conn = io::BufferedStream::new(stream);
+        self.codec = LineCodec::new(self.builder.max_line_length);
+        self.builder.ssl = true;
+        self.builder.port = port;
+
+        self.server_caps = vec![];
+        self.enabled_caps = vec![];
+        self.negotiating_caps = false;
+        self.listing_caps = false;
+        self.registration_held = false;
+        self.registered = false;
+        self.pinged = false;
+        self.last_read = time::precise_time_ns();
+
+        self.register()
+    }
+
+    // on a plain connection which might be upgraded to tls, PASS, NICK and
+    // USER wait until the reply to CAP LS has been seen, so that nothing
+    // secret is sent before we know whether the server has an sts policy.
+    // that reply has to reach negotiate_caps (the default on_cap does this).
+    pub fn register (&mut self) -> io::IoResult<()> {
+        // always start cap negotiation when an sts policy could be followed,
+        // since that's the only way to find out about the server's policy
+        if self.builder.caps.len() > 0 || self.builder.sts_store.is_some() || cfg!(feature = "ssl") {
+            try!(self.cap_ls());
+        }
+
+        if cfg!(feature = "ssl") && self.builder.sts_upgrade && !self.is_secure() {
+            self.registration_held = true;
+            return Ok(());
+        }
+        self.send_registration()
+    }
+
+    fn send_registration (&mut self) -> io::IoResult<()> {
+        self.registration_held = false;
+        let nick = self.builder.nick.clone();
+        let pass = self.builder.pass.clone();
+        let username = self.builder.username.clone();
+        let servername = self.builder.servername.clone();
+        let realname = self.builder.realname.clone();
+
+        match pass {
+            Some(pass) => try!(self.pass(pass.as_slice())),
+            None => {},
+        }
+
+        try!(self.nick(nick.as_slice()));
+
+        let hostname = match self.builder.hostname {
+            Some(ref host) => host.clone(),
+            None => {
+                match self.socket_name() {
+                    Some(ref host) => host.to_string(),
+                    // XXX something better here?
+                    None => "localhost".to_string(),
+                }
+            },
+        };
+
+        try!(self.user(
+            username.as_slice(),
+            hostname.as_slice(),
+            servername.as_slice(),
+            realname.as_slice(),
+        ));
+
+        Ok(())
+    }
+
+    pub fn chathistory (&mut self, req: ChatHistoryRequest) -> io::IoResult<()> {
+        try!(self.write(req.to_message()));
+        self.chathistory.expect(req);
+        Ok(())
+    }
+    pub fn process_chathistory (&mut self, m: &MessageRef) -> HistoryProgress {
+        self.chathistory.process(m)
+    }
+
+    pub fn process_names (&mut self, m: &MessageRef) -> Option<(String, Vec<NameEntry>)> {
+        self.names.process(m)
+    }
+    pub fn prefixes (&self) -> &[(char, char)] {
+        self.names.prefixes()
+    }
+
+    pub fn pass (&mut self, pass: &str) -> io::IoResult<()> {
+        self.write(Message::new(None, Pass, vec![pass.to_string()]))
+    }
+    pub fn nick (&mut self, nick: &str) -> io::IoResult<()> {
+        self.nicks_sent += 1;
+        self.write(Message::new(None, Nick, vec![nick.to_string()]))
+    }
+    pub fn user (&mut self, username: &str, hostname: &str, servername: &str, realname: &str) -> io::IoResult<()> {
+        self.write(Message::new(
+            None,
+            User,
+            vec![
+                username.to_string(),
+                hostname.to_string(),
+                servername.to_string(),
+                realname.to_string(),
+            ]
+        ))
+    }
+    pub fn oper (&mut self, user: &str, pass: &str) -> io::IoResult<()> {
+        self.write(Message::new(
+            None,
+            Oper,
+            vec![
+                user.to_string(),
+                pass.to_string(),
+            ]
+        ))
+    }
+    pub fn quit (&mut self, msg: Option<&str>) -> io::IoResult<()> {
+        self.quitting = true;
+        self.write(Message::new(
+            None,
+            Quit,
+            vec![].append(msg.map(|s| s.to_string()).as_slice())
+        ))
+    }
+
+    pub fn join (&mut self, channels: &[&str], keys: &[&str]) -> io::IoResult<()> {
+        let mut params = vec![channels.connect(",")];
+        if keys.len() > 0 {
+            params.push(keys.connect(","));
+        }
+        self.write(Message::new(None, Join, params))
+    }
+    pub fn part (&mut self, channels: &[&str]) -> io::IoResult<()> {
+        self.write(Message::new(None, Part, vec![channels.connect(",")]))
+    }
+    pub fn channel_mode (&mut self, channel: &str, modes: &str, params: &[&str]) -> io::IoResult<()> {
+        let p: Vec<String> = params.iter().map(|s| s.to_string()).collect();
+        self.write(Message::new(
             None,
             Mode,
             vec![channel.to_string(), modes.to_string()].append(p.as_slice())
@@ -358,6 +1278,82 @@ impl Client {
             ]
         ))
     }
+    pub fn ctcp (&mut self, target: &str, command: &str, params: Option<&str>) -> io::IoResult<()> {
+        self.privmsg([target], Ctcp::new(command, params).to_text().as_slice())
+    }
+    pub fn ctcp_reply (&mut self, target: &str, command: &str, params: Option<&str>) -> io::IoResult<()> {
+        self.notice(target, Ctcp::new(command, params).to_text().as_slice())
+    }
+    pub fn action (&mut self, target: &str, text: &str) -> io::IoResult<()> {
+        self.ctcp(target, "ACTION", Some(text))
+    }
+    pub fn dcc (&mut self, target: &str, offer: &DccOffer) -> io::IoResult<()> {
+        self.privmsg([target], offer.to_ctcp().to_text().as_slice())
+    }
+    pub fn ctcp_default_response (&self, request: &Ctcp) -> Option<Ctcp> {
+        self.builder.ctcp.respond(request)
+    }
+    // splits text that is too long to fit in a single message, measuring it
+    // in the encoding used for the target
+    pub fn say (&mut self, target: &str, text: &str) -> io::IoResult<()> {
+        // leave room for the prefix the server adds when relaying this
+        let overhead = ":!@ PRIVMSG  :\r\n".len()
+            + self.builder.nick.len() + self.builder.username.len() + 63
+            + target.len();
+        let max = if overhead < MAX_MESSAGE_LENGTH { MAX_MESSAGE_LENGTH - overhead } else { 1 };
+        let pieces = self.builder.encoding.split(Some(target), text, max);
+        for piece in pieces.iter() {
+            try!(self.privmsg([target], piece.as_slice()));
+        }
+        Ok(())
+    }
+    pub fn privmsg_with_tags (&mut self, receivers: &[&str], text: &str, tags: Vec<Tag>) -> io::IoResult<()> {
+        self.write(Message::new_with_tags(
+            tags,
+            None,
+            Privmsg,
+            vec![
+                receivers.connect(","),
+                text.to_string(),
+            ]
+        ))
+    }
+    pub fn notice_with_tags (&mut self, nickname: &str, text: &str, tags: Vec<Tag>) -> io::IoResult<()> {
+        self.write(Message::new_with_tags(
+            tags,
+            None,
+            Notice,
+            vec![
+                nickname.to_string(),
+                text.to_string(),
+            ]
+        ))
+    }
+    pub fn tagmsg (&mut self, target: &str, tags: Vec<Tag>) -> io::IoResult<()> {
+        self.write(Message::new_with_tags(tags, None, Tagmsg, vec![target.to_string()]))
+    }
+    pub fn reply (&mut self, target: &str, msgid: &str, text: &str) -> io::IoResult<()> {
+        self.privmsg_with_tags(
+            [target],
+            text,
+            vec![(TAG_REPLY.to_string(), Some(msgid.to_string()))]
+        )
+    }
+    pub fn react (&mut self, target: &str, msgid: &str, reaction: &str) -> io::IoResult<()> {
+        self.tagmsg(
+            target,
+            vec![
+                (TAG_REPLY.to_string(), Some(msgid.to_string())),
+                (TAG_REACT.to_string(), Some(reaction.to_string())),
+            ]
+        )
+    }
+    pub fn typing (&mut self, target: &str, state: TypingState) -> io::IoResult<()> {
+        self.tagmsg(
+            target,
+            vec![(TAG_TYPING.to_string(), Some(state.as_str().to_string()))]
+        )
+    }
     pub fn who (&mut self, name: &str, o: bool) -> io::IoResult<()> {
         let mut params = vec![name.to_string()];
         if o {
@@ -391,6 +1387,9 @@ impl Client {
             vec![nickname.to_string(), comment.to_string()]
         ))
     }
+    pub fn ping (&mut self, server: &str) -> io::IoResult<()> {
+        self.write(Message::new(None, Ping, vec![server.to_string()]))
+    }
     pub fn pong (&mut self, daemon1: &str) -> io::IoResult<()> {
         self.write(Message::new(None, Pong, vec![daemon1.to_string()]))
     }
@@ -441,1057 +1440,1775 @@ impl Client {
             nicknames.iter().map(|s| s.to_string()).collect()
         ))
     }
+
+    // data is the base64 encoded payload (or a mechanism name), which is
+    // split into 400 byte chunks as the sasl spec requires
+    pub fn authenticate (&mut self, data: &str) -> io::IoResult<()> {
+        let mut start = 0;
+        while start < data.len() {
+            let end = min(start + 400, data.len());
+            try!(self.write(Message::new(None, Authenticate, vec![data.slice(start, end).to_string()])));
+            start = end;
+        }
+        if data.len() % 400 == 0 {
+            try!(self.write(Message::new(None, Authenticate, vec!["+".to_string()])));
+        }
+        Ok(())
+    }
+    pub fn batch_start (&mut self, reference: &str, batch_type: &str, params: &[&str]) -> io::IoResult<()> {
+        let mut batch_params = vec![format!("+{}", reference), batch_type.to_string()];
+        batch_params.extend(params.iter().map(|s| s.to_string()));
+        self.write(Message::new(None, Batch, batch_params))
+    }
+    pub fn batch_end (&mut self, reference: &str) -> io::IoResult<()> {
+        self.write(Message::new(None, Batch, vec![format!("-{}", reference)]))
+    }
+    pub fn chghost (&mut self, nickname: &str, host: &str) -> io::IoResult<()> {
+        self.write(Message::new(
+            None,
+            Chghost,
+            vec![nickname.to_string(), host.to_string()]
+        ))
+    }
+    pub fn knock (&mut self, channel: &str, message: Option<&str>) -> io::IoResult<()> {
+        self.write(Message::new(
+            None,
+            Knock,
+            vec![
+                channel.to_string(),
+            ].append(message.map(|s| s.to_string()).as_slice())
+        ))
+    }
+    pub fn monitor_add (&mut self, targets: &[&str]) -> io::IoResult<()> {
+        self.write(Message::new(None, Monitor, vec!["+".to_string(), targets.connect(",")]))
+    }
+    pub fn monitor_remove (&mut self, targets: &[&str]) -> io::IoResult<()> {
+        self.write(Message::new(None, Monitor, vec!["-".to_string(), targets.connect(",")]))
+    }
+    pub fn monitor_clear (&mut self) -> io::IoResult<()> {
+        self.write(Message::new(None, Monitor, vec!["C".to_string()]))
+    }
+    pub fn monitor_list (&mut self) -> io::IoResult<()> {
+        self.write(Message::new(None, Monitor, vec!["L".to_string()]))
+    }
+    pub fn monitor_status (&mut self) -> io::IoResult<()> {
+        self.write(Message::new(None, Monitor, vec!["S".to_string()]))
+    }
+    pub fn setname (&mut self, realname: &str) -> io::IoResult<()> {
+        self.write(Message::new(None, Setname, vec![realname.to_string()]))
+    }
+    // entries look like "+nick" or "-nick", or are one of "C", "L", or "S"
+    pub fn watch (&mut self, entries: &[&str]) -> io::IoResult<()> {
+        self.write(Message::new(
+            None,
+            Watch,
+            entries.iter().map(|s| s.to_string()).collect()
+        ))
+    }
+    // masks look like "+nick!user@host" or "-nick!user@host"
+    pub fn silence (&mut self, masks: &[&str]) -> io::IoResult<()> {
+        self.write(Message::new(
+            None,
+            Silence,
+            masks.iter().map(|s| s.to_string()).collect()
+        ))
+    }
+    pub fn cprivmsg (&mut self, nickname: &str, channel: &str, text: &str) -> io::IoResult<()> {
+        self.write(Message::new(
+            None,
+            Cprivmsg,
+            vec![nickname.to_string(), channel.to_string(), text.to_string()]
+        ))
+    }
+    pub fn cnotice (&mut self, nickname: &str, channel: &str, text: &str) -> io::IoResult<()> {
+        self.write(Message::new(
+            None,
+            Cnotice,
+            vec![nickname.to_string(), channel.to_string(), text.to_string()]
+        ))
+    }
+    // has to be sent before anything else during registration
+    pub fn webirc (&mut self, password: &str, gateway: &str, hostname: &str, ip: &str) -> io::IoResult<()> {
+        self.write(Message::new(
+            None,
+            Webirc,
+            vec![
+                password.to_string(),
+                gateway.to_string(),
+                hostname.to_string(),
+                ip.to_string(),
+            ]
+        ))
+    }
+    pub fn standard_reply (&mut self, reply: &StandardReply) -> io::IoResult<()> {
+        self.write(reply.to_message())
+    }
 }
 
 pub trait ClientCallbacks {
-    // XXX once storing closures in structs works, we'll also want to provide
-    // a default CallbackClient impl of Client to allow users to not have to
-    // worry about the struct layout for simple cases.
-    fn run_loop (mut self, client: &mut Client) -> io::IoError {
+    // for simple cases, handlers::Handlers implements this in terms of
+    // closures registered at runtime
+    fn run_loop (mut self, client: &mut Client) -> IrcError {
         match self.on_client_connect(client) {
             Err(e) => return e,
             _ => { },
         }
 
-        let err = client.run_loop(|client, m| {
-            try!(self.on_any_message(client, m));
-
-            if m.is_reply() {
-                try!(self.on_reply(client, m));
+        let mut buf = Vec::with_capacity(client.builder().max_line_length());
+        let mut res = Ok(());
+        while res.is_ok() {
+            res = client.handle_next(&mut buf, |client, m| self.handle_message(client, m));
+            if res.is_ok() {
+                res = self.check_parse_error(client);
             }
-            else {
-                try!(self.on_command(client, m));
+        }
+
+        let _ = self.on_client_disconnect(client);
+
+        res.unwrap_err()
+    }
+
+    // calls on_parse_error if the client skipped a line since the last time
+    // this was called. loops run elsewhere call this after each line.
+    fn check_parse_error (&mut self, client: &mut Client) -> IrcResult<()> {
+        let res = match client.take_parse_error() {
+            Some(e) => self.on_parse_error(client, &e),
+            None => Ok(()),
+        };
+        if res.is_err() {
+            client.close_senders();
+        }
+        res
+    }
+
+    // decodes a message and calls the callbacks for it. run_loop calls this
+    // for each message, and it can be used to drive a loop run elsewhere.
+    fn handle_message (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> {
+        try!(self.on_any_message(client, m));
+
+        // messages belonging to a history batch are only delivered as
+        // part of the batch, not as if they were happening live. failures
+        // go to on_fail as well as on_chathistory_fail.
+        match client.process_chathistory(m) {
+            HistoryUnrelated => {},
+            HistoryCollected => return Ok(()),
+            HistoryComplete(HistoryMessages(batch)) => {
+                return self.on_chathistory(client, &batch);
+            },
+            HistoryComplete(HistoryTargets(targets)) => {
+                return self.on_chathistory_targets(client, targets.as_slice());
+            },
+            HistoryComplete(HistoryFailed(err)) => {
+                try!(self.on_fail(client, m.from(), err.reply()));
+                return self.on_chathistory_fail(client, &err);
+            },
+        }
+
+        match client.process_names(m) {
+            Some((channel, members)) => {
+                try!(self.on_channel_names(client, channel.as_slice(), members.as_slice()));
+            },
+            None => {},
+        }
+
+        if m.is_reply() {
+            try!(self.on_reply(client, m));
+            if m.message_type().is_error_reply() {
+                try!(self.on_error_numeric(client, m));
             }
+        }
+        else {
+            try!(self.on_command(client, m));
+        }
 
-            let from = m.from().as_ref().map(|s| s.as_slice());
-            let p = m.params().as_slice();
-            match *m.message_type() {
-                Pass => {
-                    match (p.get(0),) {
-                        (Some(ref pass),) => {
-                            self.on_pass(
-                                client, from,
-                                pass.as_slice()
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Nick => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref nick), Some(ref hopcount)) => {
-                            match from_str(hopcount.as_slice()) {
-                                Some(i) => {
-                                    self.on_nick(
-                                        client, from,
-                                        nick.as_slice(), Some(i)
-                                    )
-                                },
-                                _ => self.on_invalid_message(client, m),
-                            }
-                        },
-                        (Some(ref nick), None) => {
-                            self.on_nick(
-                                client, from,
-                                nick.as_slice(), None
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                User => {
-                    match (p.get(0), p.get(1), p.get(2), p.get(3)) {
-                        (Some(user), Some(host), Some(server), Some(real)) => {
-                            self.on_user(
-                                client, from,
-                                user.as_slice(), host.as_slice(),
-                                server.as_slice(), real.as_slice()
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Server => {
-                    match (p.get(0), p.get(1), p.get(2)) {
-                        (Some(ref name), Some(ref hopcount), Some(ref info)) => {
-                            match from_str(hopcount.as_slice()) {
-                                Some(i) => {
-                                    self.on_server(
-                                        client, from,
-                                        name.as_slice(), i, info.as_slice()
-                                    )
-                                },
-                                _ => self.on_invalid_message(client, m),
-                            }
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Oper => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref user), Some(ref pass)) => {
-                            self.on_oper(
-                                client, from,
-                                user.as_slice(), pass.as_slice()
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Quit => {
-                    self.on_quit(
-                        client, from,
-                        p.get(0).map(|s| s.as_slice())
-                    )
-                },
-                Squit => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref server), Some(ref comment)) => {
-                            self.on_squit(
-                                client, from,
-                                server.as_slice(), comment.as_slice()
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Join => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref channels), Some(ref keys)) => {
-                            let channels: Vec<&str> = channels.as_slice().split(',').collect();
-                            let keys: Vec<&str> = keys.as_slice().split(',').collect();
-                            self.on_join(
-                                client, from,
-                                channels.as_slice(),
-                                keys.as_slice()
-                            )
-                        },
-                        (Some(ref channels), None) => {
-                            let channels: Vec<&str> = channels.as_slice().split(',').collect();
-                            self.on_join(
-                                client, from,
-                                channels.as_slice(),
-                                []
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Part => {
-                    match (p.get(0),) {
-                        (Some(ref channels),) => {
-                            let channels: Vec<&str> = channels.as_slice().split(',').collect();
-                            self.on_part(
-                                client, from,
-                                channels.as_slice()
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Mode => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(name), Some(modes))
-                            if is_channel(name.as_slice()) => {
-                            let params: Vec<&str> = p.slice_from(2).iter().map(|s| s.as_slice()).collect();
-                            self.on_channel_mode(
-                                client, from,
-                                name.as_slice(), modes.as_slice(),
-                                params.as_slice()
-                            )
-                        },
-                        (Some(name), Some(modes)) => {
-                            self.on_user_mode(
-                                client, from,
-                                name.as_slice(), modes.as_slice()
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Topic => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref channel), Some(ref topic)) => {
-                            self.on_topic(
-                                client, from,
-                                channel.as_slice(), Some(topic.as_slice())
-                            )
-                        },
-                        (Some(ref channel), None) => {
-                            self.on_topic(
-                                client, from,
-                                channel.as_slice(), None
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Names => {
-                    match (p.get(0),) {
-                        (Some(ref channels),) => {
-                            let channels: Vec<&str> = channels.as_slice().split(',').collect();
-                            self.on_names(
-                                client, from,
-                                channels.as_slice()
-                            )
-                        },
-                        _ => {
-                            self.on_names(
-                                client, from,
-                                []
-                            )
-                        },
-                    }
-                },
-                List => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref channels), Some(ref server)) => {
-                            let channels: Vec<&str> = channels.as_slice().split(',').collect();
-                            self.on_list(
-                                client, from,
-                                channels.as_slice(),
-                                Some(server.as_slice())
-                            )
-                        },
-                        (Some(ref channels), None) => {
-                            let channels: Vec<&str> = channels.as_slice().split(',').collect();
-                            self.on_list(
-                                client, from,
-                                channels.as_slice(),
-                                None
-                            )
-                        },
-                        _ => {
-                            self.on_list(
-                                client, from,
-                                [], None
-                            )
-                        },
-                    }
-                },
-                Invite => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref nickname), Some(ref channel)) => {
-                            self.on_invite(
-                                client, from,
-                                nickname.as_slice(), channel.as_slice()
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Kick => {
-                    match (p.get(0), p.get(1), p.get(2)) {
-                        (Some(ref channel), Some(ref user), Some(ref comment)) => {
-                            self.on_kick(
-                                client, from,
-                                channel.as_slice(), user.as_slice(),
-                                Some(comment.as_slice())
-                            )
-                        },
-                        (Some(ref channel), Some(ref user), None) => {
-                            self.on_kick(
-                                client, from,
-                                channel.as_slice(), user.as_slice(),
-                                None
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Version => {
-                    self.on_version(
-                        client, from,
-                        p.get(0).map(|s| s.as_slice())
-                    )
-                },
-                Stats => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref query), Some(ref server)) => {
-                            self.on_stats(
-                                client, from,
-                                Some(query.as_slice()), Some(server.as_slice())
-                            )
-                        },
-                        (Some(ref query), None) => {
-                            self.on_stats(
-                                client, from,
-                                Some(query.as_slice()), None
-                            )
-                        },
-                        _ => {
-                            self.on_stats(
-                                client, from,
-                                None, None
-                            )
-                        },
-                    }
-                },
-                Links => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref server), Some(ref mask)) => {
-                            self.on_stats(
-                                client, from,
-                                Some(server.as_slice()), Some(mask.as_slice())
-                            )
-                        },
-                        (Some(ref mask), None) => {
-                            self.on_stats(
-                                client, from,
-                                None, Some(mask.as_slice())
-                            )
-                        },
-                        _ => {
-                            self.on_stats(
-                                client, from,
-                                None, None
-                            )
-                        },
-                    }
-                },
-                Time => {
-                    self.on_time(
-                        client, from,
-                        p.get(0).map(|s| s.as_slice())
-                    )
-                },
-                Connect => {
-                    match (p.get(0), p.get(1), p.get(2)) {
-                        (Some(ref server), Some(ref port), Some(ref remote)) => {
-                            match from_str(port.as_slice()) {
-                                Some(port) => {
-                                    self.on_connect(
-                                        client, from,
-                                        server.as_slice(),
-                                        Some(port),
-                                        Some(remote.as_slice())
-                                    )
-                                },
-                                _ => self.on_invalid_message(client, m),
-                            }
-                        },
-                        (Some(ref server), Some(ref port), None) => {
-                            match from_str(port.as_slice()) {
-                                Some(port) => {
-                                    self.on_connect(
-                                        client, from,
-                                        server.as_slice(),
-                                        Some(port),
-                                        None
-                                    )
-                                },
-                                _ => self.on_invalid_message(client, m),
-                            }
-                        },
-                        (Some(ref server), None, None) => {
-                            self.on_connect(
-                                client, from,
-                                server.as_slice(),
-                                None,
-                                None
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Trace => {
-                    self.on_trace(
-                        client, from,
-                        p.get(0).map(|s| s.as_slice())
-                    )
-                },
-                Admin => {
-                    self.on_admin(
-                        client, from,
-                        p.get(0).map(|s| s.as_slice())
-                    )
-                },
-                Info => {
-                    self.on_info(
-                        client, from,
-                        p.get(0).map(|s| s.as_slice())
-                    )
-                },
-                Privmsg => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref receivers), Some(ref text)) => {
-                            let receivers: Vec<&str> = receivers.as_slice().split(',').collect();
-                            self.on_privmsg(
-                                client, from,
-                                receivers.as_slice(),
-                                text.as_slice()
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Notice => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref nickname), Some(ref text)) => {
-                            self.on_notice(
-                                client, from,
-                                nickname.as_slice(),
-                                text.as_slice()
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Who => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref name), Some(ref o)) => {
-                            match o.as_slice() {
-                                "o" => {
-                                    self.on_who(
-                                        client, from,
-                                        name.as_slice(),
-                                        true
-                                    )
-                                },
-                                _ => self.on_invalid_message(client, m),
-                            }
-                        },
-                        (Some(ref name), None) => {
-                            self.on_who(
-                                client, from,
-                                name.as_slice(),
-                                false
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Whois => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref server), Some(ref nickmasks)) => {
-                            let nickmasks: Vec<&str> = nickmasks.as_slice().split(',').collect();
-                            self.on_whois(
-                                client, from,
-                                Some(server.as_slice()),
-                                nickmasks.as_slice()
-                            )
-                        },
-                        (Some(ref nickmasks), None) => {
-                            let nickmasks: Vec<&str> = nickmasks.as_slice().split(',').collect();
-                            self.on_whois(
-                                client, from,
-                                None,
-                                nickmasks.as_slice()
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Whowas => {
-                    match (p.get(0), p.get(1), p.get(2)) {
-                        (Some(ref nickname), Some(count), Some(ref server)) => {
-                            match from_str(count.as_slice()) {
-                                Some(i) => {
-                                    self.on_whowas(
+        let from = m.from();
+        let p = m.params();
+        match *m.message_type() {
+            Pass => {
+                match (p.get(0),) {
+                    (Some(ref pass),) => {
+                        self.on_pass(
+                            client, from,
+                            pass.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Nick => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref nick), Some(ref hopcount)) => {
+                        match from_str(hopcount.as_slice()) {
+                            Some(i) => {
+                                self.on_nick(
+                                    client, from,
+                                    nick.as_slice(), Some(i)
+                                )
+                            },
+                            _ => self.on_invalid_message(client, m),
+                        }
+                    },
+                    (Some(ref nick), None) => {
+                        self.on_nick(
+                            client, from,
+                            nick.as_slice(), None
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            User => {
+                match (p.get(0), p.get(1), p.get(2), p.get(3)) {
+                    (Some(user), Some(host), Some(server), Some(real)) => {
+                        self.on_user(
+                            client, from,
+                            user.as_slice(), host.as_slice(),
+                            server.as_slice(), real.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Server => {
+                match (p.get(0), p.get(1), p.get(2)) {
+                    (Some(ref name), Some(ref hopcount), Some(ref info)) => {
+                        match from_str(hopcount.as_slice()) {
+                            Some(i) => {
+                                self.on_server(
+                                    client, from,
+                                    name.as_slice(), i, info.as_slice()
+                                )
+                            },
+                            _ => self.on_invalid_message(client, m),
+                        }
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Oper => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref user), Some(ref pass)) => {
+                        self.on_oper(
+                            client, from,
+                            user.as_slice(), pass.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Quit => {
+                self.on_quit(
+                    client, from,
+                    p.get(0).map(|s| s.as_slice())
+                )
+            },
+            Squit => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref server), Some(ref comment)) => {
+                        self.on_squit(
+                            client, from,
+                            server.as_slice(), comment.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Join => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref channels), Some(ref keys)) => {
+                        let channels: Vec<&str> = channels.as_slice().split(',').collect();
+                        let keys: Vec<&str> = keys.as_slice().split(',').collect();
+                        self.on_join(
+                            client, from,
+                            channels.as_slice(),
+                            keys.as_slice()
+                        )
+                    },
+                    (Some(ref channels), None) => {
+                        let channels: Vec<&str> = channels.as_slice().split(',').collect();
+                        self.on_join(
+                            client, from,
+                            channels.as_slice(),
+                            []
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Part => {
+                match (p.get(0),) {
+                    (Some(ref channels),) => {
+                        let channels: Vec<&str> = channels.as_slice().split(',').collect();
+                        self.on_part(
+                            client, from,
+                            channels.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Mode => {
+                match (p.get(0), p.get(1)) {
+                    (Some(name), Some(modes))
+                        if is_channel(name.as_slice()) => {
+                        let params: Vec<&str> = p.slice_from(2).iter().map(|s| s.as_slice()).collect();
+                        self.on_channel_mode(
+                            client, from,
+                            name.as_slice(), modes.as_slice(),
+                            params.as_slice()
+                        )
+                    },
+                    (Some(name), Some(modes)) => {
+                        self.on_user_mode(
+                            client, from,
+                            name.as_slice(), modes.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Topic => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref channel), Some(ref topic)) => {
+                        self.on_topic(
+                            client, from,
+                            channel.as_slice(), Some(topic.as_slice())
+                        )
+                    },
+                    (Some(ref channel), None) => {
+                        self.on_topic(
+                            client, from,
+                            channel.as_slice(), None
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Names => {
+                match (p.get(0),) {
+                    (Some(ref channels),) => {
+                        let channels: Vec<&str> = channels.as_slice().split(',').collect();
+                        self.on_names(
+                            client, from,
+                            channels.as_slice()
+                        )
+                    },
+                    _ => {
+                        self.on_names(
+                            client, from,
+                            []
+                        )
+                    },
+                }
+            },
+            List => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref channels), Some(ref server)) => {
+                        let channels: Vec<&str> = channels.as_slice().split(',').collect();
+                        self.on_list(
+                            client, from,
+                            channels.as_slice(),
+                            Some(server.as_slice())
+                        )
+                    },
+                    (Some(ref channels), None) => {
+                        let channels: Vec<&str> = channels.as_slice().split(',').collect();
+                        self.on_list(
+                            client, from,
+                            channels.as_slice(),
+                            None
+                        )
+                    },
+                    _ => {
+                        self.on_list(
+                            client, from,
+                            [], None
+                        )
+                    },
+                }
+            },
+            Invite => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref nickname), Some(ref channel)) => {
+                        self.on_invite(
+                            client, from,
+                            nickname.as_slice(), channel.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Kick => {
+                match (p.get(0), p.get(1), p.get(2)) {
+                    (Some(ref channel), Some(ref user), Some(ref comment)) => {
+                        self.on_kick(
+                            client, from,
+                            channel.as_slice(), user.as_slice(),
+                            Some(comment.as_slice())
+                        )
+                    },
+                    (Some(ref channel), Some(ref user), None) => {
+                        self.on_kick(
+                            client, from,
+                            channel.as_slice(), user.as_slice(),
+                            None
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Version => {
+                self.on_version(
+                    client, from,
+                    p.get(0).map(|s| s.as_slice())
+                )
+            },
+            Stats => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref query), Some(ref server)) => {
+                        self.on_stats(
+                            client, from,
+                            Some(query.as_slice()), Some(server.as_slice())
+                        )
+                    },
+                    (Some(ref query), None) => {
+                        self.on_stats(
+                            client, from,
+                            Some(query.as_slice()), None
+                        )
+                    },
+                    _ => {
+                        self.on_stats(
+                            client, from,
+                            None, None
+                        )
+                    },
+                }
+            },
+            Links => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref server), Some(ref mask)) => {
+                        self.on_stats(
+                            client, from,
+                            Some(server.as_slice()), Some(mask.as_slice())
+                        )
+                    },
+                    (Some(ref mask), None) => {
+                        self.on_stats(
+                            client, from,
+                            None, Some(mask.as_slice())
+                        )
+                    },
+                    _ => {
+                        self.on_stats(
+                            client, from,
+                            None, None
+                        )
+                    },
+                }
+            },
+            Time => {
+                self.on_time(
+                    client, from,
+                    p.get(0).map(|s| s.as_slice())
+                )
+            },
+            Connect => {
+                match (p.get(0), p.get(1), p.get(2)) {
+                    (Some(ref server), Some(ref port), Some(ref remote)) => {
+                        match from_str(port.as_slice()) {
+                            Some(port) => {
+                                self.on_connect(
+                                    client, from,
+                                    server.as_slice(),
+                                    Some(port),
+                                    Some(remote.as_slice())
+                                )
+                            },
+                            _ => self.on_invalid_message(client, m),
+                        }
+                    },
+                    (Some(ref server), Some(ref port), None) => {
+                        match from_str(port.as_slice()) {
+                            Some(port) => {
+                                self.on_connect(
+                                    client, from,
+                                    server.as_slice(),
+                                    Some(port),
+                                    None
+                                )
+                            },
+                            _ => self.on_invalid_message(client, m),
+                        }
+                    },
+                    (Some(ref server), None, None) => {
+                        self.on_connect(
+                            client, from,
+                            server.as_slice(),
+                            None,
+                            None
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Trace => {
+                self.on_trace(
+                    client, from,
+                    p.get(0).map(|s| s.as_slice())
+                )
+            },
+            Admin => {
+                self.on_admin(
+                    client, from,
+                    p.get(0).map(|s| s.as_slice())
+                )
+            },
+            Info => {
+                self.on_info(
+                    client, from,
+                    p.get(0).map(|s| s.as_slice())
+                )
+            },
+            Privmsg => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref receivers), Some(ref text)) => {
+                        let receivers: Vec<&str> = receivers.as_slice().split(',').collect();
+                        let ctcp = Ctcp::parse(text.as_slice());
+                        match (ctcp, m.tag_value(TAG_REPLY)) {
+                            (Some(ref ctcp), _) if ctcp.command() == "ACTION" => {
+                                self.on_action(
+                                    client, from,
+                                    receivers.as_slice(),
+                                    ctcp.params().unwrap_or("")
+                                )
+                            },
+                            (Some(ref ctcp), _) if ctcp.command() == "DCC" => {
+                                match DccOffer::from_ctcp(ctcp) {
+                                    Some(ref offer) => {
+                                        self.on_dcc_offer(
+                                            client, from,
+                                            receivers.as_slice(),
+                                            offer
+                                        )
+                                    },
+                                    None => self.on_invalid_message(client, m),
+                                }
+                            },
+                            (Some(ref ctcp), _) => {
+                                self.on_ctcp_request(
+                                    client, from,
+                                    receivers.as_slice(),
+                                    ctcp
+                                )
+                            },
+                            (None, Some(parent)) => {
+                                self.on_privmsg_reply(
+                                    client, from,
+                                    receivers.as_slice(),
+                                    parent.as_slice(),
+                                    text.as_slice()
+                                )
+                            },
+                            (None, None) => {
+                                self.on_privmsg(
+                                    client, from,
+                                    receivers.as_slice(),
+                                    text.as_slice()
+                                )
+                            },
+                        }
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Notice => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref nickname), Some(ref text)) => {
+                        match Ctcp::parse(text.as_slice()) {
+                            Some(ref ctcp) => {
+                                self.on_ctcp_reply(
+                                    client, from,
+                                    nickname.as_slice(),
+                                    ctcp
+                                )
+                            },
+                            None => match m.tag_value(TAG_REPLY) {
+                                Some(parent) => {
+                                    self.on_notice_reply(
                                         client, from,
                                         nickname.as_slice(),
-                                        Some(i),
-                                        Some(server.as_slice()),
+                                        parent.as_slice(),
+                                        text.as_slice()
                                     )
                                 },
-                                _ => self.on_invalid_message(client, m),
-                            }
-                        },
-                        (Some(ref nickname), Some(count), None) => {
-                            match from_str(count.as_slice()) {
-                                Some(i) => {
-                                    self.on_whowas(
+                                None => {
+                                    self.on_notice(
                                         client, from,
                                         nickname.as_slice(),
-                                        Some(i),
-                                        None
+                                        text.as_slice()
                                     )
                                 },
-                                _ => self.on_invalid_message(client, m),
-                            }
-                        },
-                        (Some(ref nickname), None, None) => {
-                            self.on_whowas(
-                                client, from,
-                                nickname.as_slice(),
-                                None,
-                                None
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Kill => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref nickname), Some(ref comment)) => {
-                            self.on_kill(
-                                client, from,
-                                nickname.as_slice(),
-                                comment.as_slice()
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Ping => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref server1), Some(ref server2)) => {
-                            self.on_ping(
-                                client, from,
-                                server1.as_slice(),
-                                Some(server2.as_slice())
-                            )
-                        },
-                        (Some(ref server1), None) => {
-                            self.on_ping(
-                                client, from,
-                                server1.as_slice(),
-                                None
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Pong => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref daemon1), Some(ref daemon2)) => {
-                            self.on_pong(
-                                client, from,
-                                daemon1.as_slice(),
-                                Some(daemon2.as_slice())
-                            )
-                        },
-                        (Some(ref daemon1), None) => {
-                            self.on_ping(
-                                client, from,
-                                daemon1.as_slice(),
-                                None
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Error => {
-                    match (p.get(0),) {
-                        (Some(ref message),) => {
-                            self.on_error(
-                                client, from,
-                                message.as_slice()
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Away => {
-                    self.on_away(
-                        client, from,
-                        p.get(0).map(|s| s.as_slice())
-                    )
-                },
-                Rehash => {
-                    self.on_rehash(
-                        client, from
-                    )
-                },
-                Restart => {
-                    self.on_restart(
-                        client, from
-                    )
-                },
-                Summon => {
-                    match (p.get(0), p.get(1)) {
-                        (Some(ref user), Some(ref server)) => {
-                            self.on_summon(
-                                client, from,
-                                user.as_slice(),
-                                Some(server.as_slice())
-                            )
-                        },
-                        (Some(ref user), None) => {
-                            self.on_summon(
-                                client, from,
-                                user.as_slice(),
-                                None
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Users => {
-                    self.on_users(
-                        client, from,
-                        p.get(0).map(|s| s.as_slice())
-                    )
-                },
-                Wallops => {
-                    match (p.get(0),) {
-                        (Some(ref text),) => {
-                            self.on_wallops(
-                                client, from,
-                                text.as_slice()
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Userhost => {
-                    match (p.get(0),) {
-                        (Some(_),) => {
-                            self.on_userhost(
-                                client, from,
-                                m.params().iter().map(|s| s.as_slice()).collect::<Vec<&str>>().as_slice()
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                Ison => {
-                    match (p.get(0),) {
-                        (Some(_),) => {
-                            self.on_userhost(
-                                client, from,
-                                m.params().iter().map(|s| s.as_slice()).collect::<Vec<&str>>().as_slice()
-                            )
-                        },
-                        _ => self.on_invalid_message(client, m),
-                    }
-                },
-                RawCommand(_) => {
-                    self.on_unknown_command(client, m)
-                },
-                Reply(i) => {
-                    match i {
-                        RPL_WELCOME => self.on_rpl_welcome(client, m),
-                        RPL_YOURHOST => self.on_rpl_yourhost(client, m),
-                        RPL_CREATED => self.on_rpl_created(client, m),
-                        RPL_MYINFO => self.on_rpl_myinfo(client, m),
-                        RPL_BOUNCE => self.on_rpl_bounce(client, m),
-                        RPL_USERHOST => self.on_rpl_userhost(client, m),
-                        RPL_ISON => self.on_rpl_ison(client, m),
-                        RPL_AWAY => self.on_rpl_away(client, m),
-                        RPL_UNAWAY => self.on_rpl_unaway(client, m),
-                        RPL_NOWAWAY => self.on_rpl_noaway(client, m),
-                        RPL_WHOISUSER => self.on_rpl_whoisuser(client, m),
-                        RPL_WHOISSERVER => self.on_rpl_whoisserver(client, m),
-                        RPL_WHOISOPERATOR => self.on_rpl_whoisoperator(client, m),
-                        RPL_WHOISIDLE => self.on_rpl_whoisidle(client, m),
-                        RPL_ENDOFWHOIS => self.on_rpl_endofwhois(client, m),
-                        RPL_WHOISCHANNELS => self.on_rpl_whoischannels(client, m),
-                        RPL_WHOWASUSER => self.on_rpl_whowasuser(client, m),
-                        RPL_ENDOFWHOWAS => self.on_rpl_endofwhowas(client, m),
-                        RPL_LISTSTART => self.on_rpl_liststart(client, m),
-                        RPL_LIST => self.on_rpl_list(client, m),
-                        RPL_LISTEND => self.on_rpl_listend(client, m),
-                        RPL_UNIQOPIS => self.on_rpl_uniqopis(client, m),
-                        RPL_CHANNELMODEIS => self.on_rpl_channelmodeis(client, m),
-                        RPL_NOTOPIC => self.on_rpl_notopic(client, m),
-                        RPL_TOPIC => self.on_rpl_topic(client, m),
-                        RPL_INVITING => self.on_rpl_inviting(client, m),
-                        RPL_SUMMONING => self.on_rpl_summoning(client, m),
-                        RPL_INVITELIST => self.on_rpl_invitelist(client, m),
-                        RPL_ENDOFINVITELIST => self.on_rpl_endofinvitelist(client, m),
-                        RPL_EXCEPTLIST => self.on_rpl_exceptlist(client, m),
-                        RPL_ENDOFEXCEPTLIST => self.on_rpl_endofexceptlist(client, m),
-                        RPL_VERSION => self.on_rpl_version(client, m),
-                        RPL_WHOREPLY => self.on_rpl_whoreply(client, m),
-                        RPL_ENDOFWHO => self.on_rpl_endofwho(client, m),
-                        RPL_NAMREPLY => self.on_rpl_namreply(client, m),
-                        RPL_ENDOFNAMES => self.on_rpl_endofnames(client, m),
-                        RPL_LINKS => self.on_rpl_links(client, m),
-                        RPL_ENDOFLINKS => self.on_rpl_endoflinks(client, m),
-                        RPL_BANLIST => self.on_rpl_banlist(client, m),
-                        RPL_ENDOFBANLIST => self.on_rpl_endofbanlist(client, m),
-                        RPL_INFO => self.on_rpl_info(client, m),
-                        RPL_ENDOFINFO => self.on_rpl_endofinfo(client, m),
-                        RPL_MOTDSTART => self.on_rpl_motdstart(client, m),
-                        RPL_MOTD => self.on_rpl_motd(client, m),
-                        RPL_ENDOFMOTD => self.on_rpl_endofmotd(client, m),
-                        RPL_YOUREOPER => self.on_rpl_youreoper(client, m),
-                        RPL_REHASHING => self.on_rpl_rehashing(client, m),
-                        RPL_YOURESERVICE => self.on_rpl_youreservice(client, m),
-                        RPL_TIME => self.on_rpl_time(client, m),
-                        RPL_USERSSTART => self.on_rpl_usersstart(client, m),
-                        RPL_USERS => self.on_rpl_users(client, m),
-                        RPL_ENDOFUSERS => self.on_rpl_endofusers(client, m),
-                        RPL_NOUSERS => self.on_rpl_nousers(client, m),
-                        RPL_TRACELINK => self.on_rpl_tracelink(client, m),
-                        RPL_TRACECONNECTING => self.on_rpl_traceconnecting(client, m),
-                        RPL_TRACEHANDSHAKE => self.on_rpl_tracehandshake(client, m),
-                        RPL_TRACEUNKNOWN => self.on_rpl_traceunknown(client, m),
-                        RPL_TRACEOPERATOR => self.on_rpl_traceoperator(client, m),
-                        RPL_TRACEUSER => self.on_rpl_traceuser(client, m),
-                        RPL_TRACESERVER => self.on_rpl_traceserver(client, m),
-                        RPL_TRACESERVICE => self.on_rpl_traceservice(client, m),
-                        RPL_TRACENEWTYPE => self.on_rpl_tracenewtype(client, m),
-                        RPL_TRACECLASS => self.on_rpl_traceclass(client, m),
-                        RPL_TRACERECONNECT => self.on_rpl_tracereconnect(client, m),
-                        RPL_TRACELOG => self.on_rpl_tracelog(client, m),
-                        RPL_TRACEEND => self.on_rpl_traceend(client, m),
-                        RPL_STATSLINKINFO => self.on_rpl_statslinkinfo(client, m),
-                        RPL_STATSCOMMANDS => self.on_rpl_statscommands(client, m),
-                        RPL_ENDOFSTATS => self.on_rpl_endofstats(client, m),
-                        RPL_STATSUPTIME => self.on_rpl_statsuptime(client, m),
-                        RPL_STATSOLINE => self.on_rpl_statsoline(client, m),
-                        RPL_UMODEIS => self.on_rpl_umodeis(client, m),
-                        RPL_SERVLIST => self.on_rpl_servlist(client, m),
-                        RPL_SERVLISTEND => self.on_rpl_servlistend(client, m),
-                        RPL_LUSERCLIENT => self.on_rpl_luserclient(client, m),
-                        RPL_LUSEROP => self.on_rpl_luserop(client, m),
-                        RPL_LUSERUNKNOWN => self.on_rpl_luserunknown(client, m),
-                        RPL_LUSERCHANNELS => self.on_rpl_luserchannels(client, m),
-                        RPL_LUSERME => self.on_rpl_luserme(client, m),
-                        RPL_ADMINME => self.on_rpl_adminme(client, m),
-                        RPL_ADMINLOC1 => self.on_rpl_adminloc1(client, m),
-                        RPL_ADMINLOC2 => self.on_rpl_adminloc2(client, m),
-                        RPL_ADMINEMAIL => self.on_rpl_adminemail(client, m),
-                        RPL_TRYAGAIN => self.on_rpl_tryagain(client, m),
-                        ERR_NOSUCHNICK => self.on_err_nosuchnick(client, m),
-                        ERR_NOSUCHSERVER => self.on_err_nosuchserver(client, m),
-                        ERR_NOSUCHCHANNEL => self.on_err_nosuchchannel(client, m),
-                        ERR_CANNOTSENDTOCHAN => self.on_err_cannotsendtochan(client, m),
-                        ERR_TOOMANYCHANNELS => self.on_err_toomanychannels(client, m),
-                        ERR_WASNOSUCHNICK => self.on_err_wasnosuchnick(client, m),
-                        ERR_TOOMANYTARGETS => self.on_err_toomanytargets(client, m),
-                        ERR_NOSUCHSERVICE => self.on_err_nosuchservice(client, m),
-                        ERR_NOORIGIN => self.on_err_noorigin(client, m),
-                        ERR_NORECIPIENT => self.on_err_norecipient(client, m),
-                        ERR_NOTEXTTOSEND => self.on_err_notexttosend(client, m),
-                        ERR_NOTOPLEVEL => self.on_err_notoplevel(client, m),
-                        ERR_WILDTOPLEVEL => self.on_err_wildtoplevel(client, m),
-                        ERR_BADMASK => self.on_err_badmask(client, m),
-                        ERR_UNKNOWNCOMMAND => self.on_err_unknowncommand(client, m),
-                        ERR_NOMOTD => self.on_err_nomotd(client, m),
-                        ERR_NOADMININFO => self.on_err_noadmininfo(client, m),
-                        ERR_FILEERROR => self.on_err_fileerror(client, m),
-                        ERR_NONICKNAMEGIVEN => self.on_err_nonicknamegiven(client, m),
-                        ERR_ERRONEUSNICKNAME => self.on_err_erroneusnickname(client, m),
-                        ERR_NICKNAMEINUSE => self.on_err_nicknameinuse(client, m),
-                        ERR_NICKCOLLISION => self.on_err_nickcollision(client, m),
-                        ERR_UNAVAILRESOURCE => self.on_err_unavailresource(client, m),
-                        ERR_USERNOTINCHANNEL => self.on_err_usernotinchannel(client, m),
-                        ERR_NOTONCHANNEL => self.on_err_notonchannel(client, m),
-                        ERR_USERONCHANNEL => self.on_err_useronchannel(client, m),
-                        ERR_NOLOGIN => self.on_err_nologin(client, m),
-                        ERR_SUMMONDISABLED => self.on_err_summondisabled(client, m),
-                        ERR_USERSDISABLED => self.on_err_usersdisabled(client, m),
-                        ERR_NOTREGISTERED => self.on_err_notregistered(client, m),
-                        ERR_NEEDMOREPARAMS => self.on_err_needmoreparams(client, m),
-                        ERR_ALREADYREGISTERED => self.on_err_alreadyregistered(client, m),
-                        ERR_NOPERMFORHOST => self.on_err_nopermforhost(client, m),
-                        ERR_PASSWDMISMATCH => self.on_err_passwdmismatch(client, m),
-                        ERR_YOUREBANNEDCREEP => self.on_err_yourebannedcreep(client, m),
-                        ERR_YOUWILLBEBANNED => self.on_err_youwillbebanned(client, m),
-                        ERR_KEYSET => self.on_err_keyset(client, m),
-                        ERR_CHANNELISFULL => self.on_err_channelisfull(client, m),
-                        ERR_UNKNOWNMODE => self.on_err_unknownmode(client, m),
-                        ERR_INVITEONLYCHAN => self.on_err_inviteonlychan(client, m),
-                        ERR_BANNEDFROMCHAN => self.on_err_bannedfromchan(client, m),
-                        ERR_BADCHANNELKEY => self.on_err_badchannelkey(client, m),
-                        ERR_BADCHANMASK => self.on_err_badchanmask(client, m),
-                        ERR_NOCHANMODES => self.on_err_nochanmodes(client, m),
-                        ERR_BANLISTFULL => self.on_err_banlistfull(client, m),
-                        ERR_NOPRIVILEGES => self.on_err_noprivileges(client, m),
-                        ERR_CHANOPRIVSNEEDED => self.on_err_chanoprivsneeded(client, m),
-                        ERR_CANTKILLSERVER => self.on_err_cantkillserver(client, m),
-                        ERR_RESTRICTED => self.on_err_restricted(client, m),
-                        ERR_UNIQOPPRIVSNEEDED => self.on_err_uniqopprivsneeded(client, m),
-                        ERR_NOOPERHOST => self.on_err_nooperhost(client, m),
-                        ERR_UMODEUNKNOWNFLAG => self.on_err_umodeunknownflag(client, m),
-                        ERR_USERSDONTMATCH => self.on_err_usersdontmatch(client, m),
-                        RPL_SERVICEINFO => self.on_rpl_serviceinfo(client, m),
-                        RPL_ENDOFSERVICES => self.on_rpl_endofservices(client, m),
-                        RPL_SERVICE => self.on_rpl_service(client, m),
-                        RPL_NONE => self.on_rpl_none(client, m),
-                        RPL_WHOISCHANOP => self.on_rpl_whoischanop(client, m),
-                        RPL_KILLDONE => self.on_rpl_killdone(client, m),
-                        RPL_CLOSING => self.on_rpl_closing(client, m),
-                        RPL_CLOSEEND => self.on_rpl_closeend(client, m),
-                        RPL_INFOSTART => self.on_rpl_infostart(client, m),
-                        RPL_MYPORTIS => self.on_rpl_myportis(client, m),
-                        RPL_STATSCLINE => self.on_rpl_statscline(client, m),
-                        RPL_STATSNLINE => self.on_rpl_statsnline(client, m),
-                        RPL_STATSILINE => self.on_rpl_statsiline(client, m),
-                        RPL_STATSKLINE => self.on_rpl_statskline(client, m),
-                        RPL_STATSQLINE => self.on_rpl_statsqline(client, m),
-                        RPL_STATSYLINE => self.on_rpl_statsyline(client, m),
-                        RPL_STATSVLINE => self.on_rpl_statsvline(client, m),
-                        RPL_STATSLLINE => self.on_rpl_statslline(client, m),
-                        RPL_STATSHLINE => self.on_rpl_statshline(client, m),
-                        RPL_STATSPING => self.on_rpl_statsping(client, m),
-                        RPL_STATSBLINE => self.on_rpl_statsbline(client, m),
-                        RPL_STATSDLINE => self.on_rpl_statsdline(client, m),
-                        ERR_NOSERVICEHOST => self.on_err_noservicehost(client, m),
-                        RPL_TOPICDATE => self.on_rpl_topicdate(client, m),
-                        ERR_MSGFORBIDDEN => self.on_err_msgforbidden(client, m),
-                        _ => self.on_unknown_reply(client, m),
-                    }
-                },
-            }
-        });
-
-        let _ = self.on_client_disconnect(client);
+                            },
+                        }
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Who => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref name), Some(ref o)) => {
+                        match o.as_slice() {
+                            "o" => {
+                                self.on_who(
+                                    client, from,
+                                    name.as_slice(),
+                                    true
+                                )
+                            },
+                            _ => self.on_invalid_message(client, m),
+                        }
+                    },
+                    (Some(ref name), None) => {
+                        self.on_who(
+                            client, from,
+                            name.as_slice(),
+                            false
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Whois => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref server), Some(ref nickmasks)) => {
+                        let nickmasks: Vec<&str> = nickmasks.as_slice().split(',').collect();
+                        self.on_whois(
+                            client, from,
+                            Some(server.as_slice()),
+                            nickmasks.as_slice()
+                        )
+                    },
+                    (Some(ref nickmasks), None) => {
+                        let nickmasks: Vec<&str> = nickmasks.as_slice().split(',').collect();
+                        self.on_whois(
+                            client, from,
+                            None,
+                            nickmasks.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Whowas => {
+                match (p.get(0), p.get(1), p.get(2)) {
+                    (Some(ref nickname), Some(count), Some(ref server)) => {
+                        match from_str(count.as_slice()) {
+                            Some(i) => {
+                                self.on_whowas(
+                                    client, from,
+                                    nickname.as_slice(),
+                                    Some(i),
+                                    Some(server.as_slice()),
+                                )
+                            },
+                            _ => self.on_invalid_message(client, m),
+                        }
+                    },
+                    (Some(ref nickname), Some(count), None) => {
+                        match from_str(count.as_slice()) {
+                            Some(i) => {
+                                self.on_whowas(
+                                    client, from,
+                                    nickname.as_slice(),
+                                    Some(i),
+                                    None
+                                )
+                            },
+                            _ => self.on_invalid_message(client, m),
+                        }
+                    },
+                    (Some(ref nickname), None, None) => {
+                        self.on_whowas(
+                            client, from,
+                            nickname.as_slice(),
+                            None,
+                            None
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Kill => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref nickname), Some(ref comment)) => {
+                        self.on_kill(
+                            client, from,
+                            nickname.as_slice(),
+                            comment.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Ping => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref server1), Some(ref server2)) => {
+                        self.on_ping(
+                            client, from,
+                            server1.as_slice(),
+                            Some(server2.as_slice())
+                        )
+                    },
+                    (Some(ref server1), None) => {
+                        self.on_ping(
+                            client, from,
+                            server1.as_slice(),
+                            None
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Pong => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref daemon1), Some(ref daemon2)) => {
+                        self.on_pong(
+                            client, from,
+                            daemon1.as_slice(),
+                            Some(daemon2.as_slice())
+                        )
+                    },
+                    (Some(ref daemon1), None) => {
+                        self.on_ping(
+                            client, from,
+                            daemon1.as_slice(),
+                            None
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Error => {
+                match (p.get(0),) {
+                    (Some(ref message),) => {
+                        self.on_error(
+                            client, from,
+                            message.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Away => {
+                self.on_away(
+                    client, from,
+                    p.get(0).map(|s| s.as_slice())
+                )
+            },
+            Rehash => {
+                self.on_rehash(
+                    client, from
+                )
+            },
+            Restart => {
+                self.on_restart(
+                    client, from
+                )
+            },
+            Summon => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref user), Some(ref server)) => {
+                        self.on_summon(
+                            client, from,
+                            user.as_slice(),
+                            Some(server.as_slice())
+                        )
+                    },
+                    (Some(ref user), None) => {
+                        self.on_summon(
+                            client, from,
+                            user.as_slice(),
+                            None
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Users => {
+                self.on_users(
+                    client, from,
+                    p.get(0).map(|s| s.as_slice())
+                )
+            },
+            Wallops => {
+                match (p.get(0),) {
+                    (Some(ref text),) => {
+                        self.on_wallops(
+                            client, from,
+                            text.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Userhost => {
+                match (p.get(0),) {
+                    (Some(_),) => {
+                        self.on_userhost(
+                            client, from,
+                            m.params()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Ison => {
+                match (p.get(0),) {
+                    (Some(_),) => {
+                        self.on_userhost(
+                            client, from,
+                            m.params()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Tagmsg => {
+                match (p.get(0),) {
+                    (Some(ref target),) => {
+                        try!(self.on_tagmsg(
+                            client, from,
+                            target.as_slice(),
+                            m.to_tags().as_slice()
+                        ));
+                        let typing = m.tag_value(TAG_TYPING);
+                        match typing.as_ref().and_then(|v| TypingState::from_tag_value(v.as_slice())) {
+                            Some(state) => {
+                                try!(self.on_typing(
+                                    client, from,
+                                    target.as_slice(),
+                                    state
+                                ));
+                            },
+                            None => {},
+                        }
+                        match m.tag_value(TAG_REACT) {
+                            Some(reaction) => {
+                                let parent = m.tag_value(TAG_REPLY);
+                                self.on_react(
+                                    client, from,
+                                    target.as_slice(),
+                                    parent.as_ref().map(|s| s.as_slice()),
+                                    reaction.as_slice()
+                                )
+                            },
+                            None => Ok(()),
+                        }
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Fail | Warn | Note => {
+                match StandardReply::from_message_ref(m) {
+                    Some(ref reply) if *m.message_type() == Fail => {
+                        self.on_fail(client, from, reply)
+                    },
+                    Some(ref reply) if *m.message_type() == Warn => {
+                        self.on_warn(client, from, reply)
+                    },
+                    Some(ref reply) => {
+                        self.on_note(client, from, reply)
+                    },
+                    None => self.on_invalid_message(client, m),
+                }
+            },
+            Cap => {
+                self.on_cap(client, from, m)
+            },
+            Authenticate => {
+                match (p.get(0),) {
+                    (Some(ref data),) => {
+                        self.on_authenticate(
+                            client, from,
+                            data.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Account => {
+                match (p.get(0),) {
+                    (Some(ref account),) => {
+                        // * means they logged out
+                        let account = account.as_slice();
+                        self.on_account(
+                            client, from,
+                            if account == "*" { None } else { Some(account) }
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Batch => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref reference), Some(ref batch_type)) if reference.starts_with("+") => {
+                        self.on_batch_start(
+                            client, from,
+                            reference.slice_from(1), batch_type.as_slice(),
+                            p.slice_from(2)
+                        )
+                    },
+                    (Some(ref reference), None) if reference.starts_with("-") => {
+                        self.on_batch_end(
+                            client, from,
+                            reference.slice_from(1)
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Chghost => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref username), Some(ref hostname)) => {
+                        self.on_chghost(
+                            client, from,
+                            username.as_slice(), hostname.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Knock => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref channel), message) => {
+                        self.on_knock(
+                            client, from,
+                            channel.as_slice(), message.map(|s| *s)
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Monitor => {
+                match (p.get(0), p.get(1)) {
+                    (Some(ref subcommand), targets) => {
+                        let targets: Vec<&str> = match targets {
+                            Some(targets) => targets.split(',').filter(|t| t.len() > 0).collect(),
+                            None => vec![],
+                        };
+                        self.on_monitor(
+                            client, from,
+                            subcommand.as_slice(), targets.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Setname => {
+                match (p.get(0),) {
+                    (Some(ref realname),) => {
+                        self.on_setname(
+                            client, from,
+                            realname.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Watch => {
+                self.on_watch(
+                    client, from,
+                    m.params()
+                )
+            },
+            Silence => {
+                self.on_silence(
+                    client, from,
+                    m.params()
+                )
+            },
+            Cprivmsg => {
+                match (p.get(0), p.get(1), p.get(2)) {
+                    (Some(ref nickname), Some(ref channel), Some(ref text)) => {
+                        self.on_cprivmsg(
+                            client, from,
+                            nickname.as_slice(), channel.as_slice(), text.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Cnotice => {
+                match (p.get(0), p.get(1), p.get(2)) {
+                    (Some(ref nickname), Some(ref channel), Some(ref text)) => {
+                        self.on_cnotice(
+                            client, from,
+                            nickname.as_slice(), channel.as_slice(), text.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            Webirc => {
+                match (p.get(0), p.get(1), p.get(2), p.get(3)) {
+                    (Some(ref password), Some(ref gateway), Some(ref hostname), Some(ref ip)) => {
+                        self.on_webirc(
+                            client, from,
+                            password.as_slice(), gateway.as_slice(),
+                            hostname.as_slice(), ip.as_slice()
+                        )
+                    },
+                    _ => self.on_invalid_message(client, m),
+                }
+            },
+            RawCommand(_) => {
+                self.on_unknown_command(client, m)
+            },
+            Reply(i) => {
+                match i {
+                    RPL_WELCOME => self.on_rpl_welcome(client, m),
+                    RPL_YOURHOST => self.on_rpl_yourhost(client, m),
+                    RPL_CREATED => self.on_rpl_created(client, m),
+                    RPL_MYINFO => self.on_rpl_myinfo(client, m),
+                    RPL_BOUNCE => self.on_rpl_bounce(client, m),
+                    RPL_USERHOST => self.on_rpl_userhost(client, m),
+                    RPL_ISON => self.on_rpl_ison(client, m),
+                    RPL_AWAY => self.on_rpl_away(client, m),
+                    RPL_UNAWAY => self.on_rpl_unaway(client, m),
+                    RPL_NOWAWAY => self.on_rpl_noaway(client, m),
+                    RPL_WHOISUSER => self.on_rpl_whoisuser(client, m),
+                    RPL_WHOISSERVER => self.on_rpl_whoisserver(client, m),
+                    RPL_WHOISOPERATOR => self.on_rpl_whoisoperator(client, m),
+                    RPL_WHOISIDLE => self.on_rpl_whoisidle(client, m),
+                    RPL_ENDOFWHOIS => self.on_rpl_endofwhois(client, m),
+                    RPL_WHOISCHANNELS => self.on_rpl_whoischannels(client, m),
+                    RPL_WHOWASUSER => self.on_rpl_whowasuser(client, m),
+                    RPL_ENDOFWHOWAS => self.on_rpl_endofwhowas(client, m),
+                    RPL_LISTSTART => self.on_rpl_liststart(client, m),
+                    RPL_LIST => self.on_rpl_list(client, m),
+                    RPL_LISTEND => self.on_rpl_listend(client, m),
+                    RPL_UNIQOPIS => self.on_rpl_uniqopis(client, m),
+                    RPL_CHANNELMODEIS => self.on_rpl_channelmodeis(client, m),
+                    RPL_NOTOPIC => self.on_rpl_notopic(client, m),
+                    RPL_TOPIC => self.on_rpl_topic(client, m),
+                    RPL_INVITING => self.on_rpl_inviting(client, m),
+                    RPL_SUMMONING => self.on_rpl_summoning(client, m),
+                    RPL_INVITELIST => self.on_rpl_invitelist(client, m),
+                    RPL_ENDOFINVITELIST => self.on_rpl_endofinvitelist(client, m),
+                    RPL_EXCEPTLIST => self.on_rpl_exceptlist(client, m),
+                    RPL_ENDOFEXCEPTLIST => self.on_rpl_endofexceptlist(client, m),
+                    RPL_VERSION => self.on_rpl_version(client, m),
+                    RPL_WHOREPLY => self.on_rpl_whoreply(client, m),
+                    RPL_ENDOFWHO => self.on_rpl_endofwho(client, m),
+                    RPL_NAMREPLY => self.on_rpl_namreply(client, m),
+                    RPL_ENDOFNAMES => self.on_rpl_endofnames(client, m),
+                    RPL_LINKS => self.on_rpl_links(client, m),
+                    RPL_ENDOFLINKS => self.on_rpl_endoflinks(client, m),
+                    RPL_BANLIST => self.on_rpl_banlist(client, m),
+                    RPL_ENDOFBANLIST => self.on_rpl_endofbanlist(client, m),
+                    RPL_INFO => self.on_rpl_info(client, m),
+                    RPL_ENDOFINFO => self.on_rpl_endofinfo(client, m),
+                    RPL_MOTDSTART => self.on_rpl_motdstart(client, m),
+                    RPL_MOTD => self.on_rpl_motd(client, m),
+                    RPL_ENDOFMOTD => self.on_rpl_endofmotd(client, m),
+                    RPL_YOUREOPER => self.on_rpl_youreoper(client, m),
+                    RPL_REHASHING => self.on_rpl_rehashing(client, m),
+                    RPL_YOURESERVICE => self.on_rpl_youreservice(client, m),
+                    RPL_TIME => self.on_rpl_time(client, m),
+                    RPL_USERSSTART => self.on_rpl_usersstart(client, m),
+                    RPL_USERS => self.on_rpl_users(client, m),
+                    RPL_ENDOFUSERS => self.on_rpl_endofusers(client, m),
+                    RPL_NOUSERS => self.on_rpl_nousers(client, m),
+                    RPL_TRACELINK => self.on_rpl_tracelink(client, m),
+                    RPL_TRACECONNECTING => self.on_rpl_traceconnecting(client, m),
+                    RPL_TRACEHANDSHAKE => self.on_rpl_tracehandshake(client, m),
+                    RPL_TRACEUNKNOWN => self.on_rpl_traceunknown(client, m),
+                    RPL_TRACEOPERATOR => self.on_rpl_traceoperator(client, m),
+                    RPL_TRACEUSER => self.on_rpl_traceuser(client, m),
+                    RPL_TRACESERVER => self.on_rpl_traceserver(client, m),
+                    RPL_TRACESERVICE => self.on_rpl_traceservice(client, m),
+                    RPL_TRACENEWTYPE => self.on_rpl_tracenewtype(client, m),
+                    RPL_TRACECLASS => self.on_rpl_traceclass(client, m),
+                    RPL_TRACERECONNECT => self.on_rpl_tracereconnect(client, m),
+                    RPL_TRACELOG => self.on_rpl_tracelog(client, m),
+                    RPL_TRACEEND => self.on_rpl_traceend(client, m),
+                    RPL_STATSLINKINFO => self.on_rpl_statslinkinfo(client, m),
+                    RPL_STATSCOMMANDS => self.on_rpl_statscommands(client, m),
+                    RPL_ENDOFSTATS => self.on_rpl_endofstats(client, m),
+                    RPL_STATSUPTIME => self.on_rpl_statsuptime(client, m),
+                    RPL_STATSOLINE => self.on_rpl_statsoline(client, m),
+                    RPL_UMODEIS => self.on_rpl_umodeis(client, m),
+                    RPL_SERVLIST => self.on_rpl_servlist(client, m),
+                    RPL_SERVLISTEND => self.on_rpl_servlistend(client, m),
+                    RPL_LUSERCLIENT => self.on_rpl_luserclient(client, m),
+                    RPL_LUSEROP => self.on_rpl_luserop(client, m),
+                    RPL_LUSERUNKNOWN => self.on_rpl_luserunknown(client, m),
+                    RPL_LUSERCHANNELS => self.on_rpl_luserchannels(client, m),
+                    RPL_LUSERME => self.on_rpl_luserme(client, m),
+                    RPL_ADMINME => self.on_rpl_adminme(client, m),
+                    RPL_ADMINLOC1 => self.on_rpl_adminloc1(client, m),
+                    RPL_ADMINLOC2 => self.on_rpl_adminloc2(client, m),
+                    RPL_ADMINEMAIL => self.on_rpl_adminemail(client, m),
+                    RPL_TRYAGAIN => self.on_rpl_tryagain(client, m),
+                    ERR_NOSUCHNICK => self.on_err_nosuchnick(client, m),
+                    ERR_NOSUCHSERVER => self.on_err_nosuchserver(client, m),
+                    ERR_NOSUCHCHANNEL => self.on_err_nosuchchannel(client, m),
+                    ERR_CANNOTSENDTOCHAN => self.on_err_cannotsendtochan(client, m),
+                    ERR_TOOMANYCHANNELS => self.on_err_toomanychannels(client, m),
+                    ERR_WASNOSUCHNICK => self.on_err_wasnosuchnick(client, m),
+                    ERR_TOOMANYTARGETS => self.on_err_toomanytargets(client, m),
+                    ERR_NOSUCHSERVICE => self.on_err_nosuchservice(client, m),
+                    ERR_NOORIGIN => self.on_err_noorigin(client, m),
+                    ERR_NORECIPIENT => self.on_err_norecipient(client, m),
+                    ERR_NOTEXTTOSEND => self.on_err_notexttosend(client, m),
+                    ERR_NOTOPLEVEL => self.on_err_notoplevel(client, m),
+                    ERR_WILDTOPLEVEL => self.on_err_wildtoplevel(client, m),
+                    ERR_BADMASK => self.on_err_badmask(client, m),
+                    ERR_UNKNOWNCOMMAND => self.on_err_unknowncommand(client, m),
+                    ERR_NOMOTD => self.on_err_nomotd(client, m),
+                    ERR_NOADMININFO => self.on_err_noadmininfo(client, m),
+                    ERR_FILEERROR => self.on_err_fileerror(client, m),
+                    ERR_NONICKNAMEGIVEN => self.on_err_nonicknamegiven(client, m),
+                    ERR_ERRONEUSNICKNAME => self.on_err_erroneusnickname(client, m),
+                    ERR_NICKNAMEINUSE => self.on_err_nicknameinuse(client, m),
+                    ERR_NICKCOLLISION => self.on_err_nickcollision(client, m),
+                    ERR_UNAVAILRESOURCE => self.on_err_unavailresource(client, m),
+                    ERR_USERNOTINCHANNEL => self.on_err_usernotinchannel(client, m),
+                    ERR_NOTONCHANNEL => self.on_err_notonchannel(client, m),
+                    ERR_USERONCHANNEL => self.on_err_useronchannel(client, m),
+                    ERR_NOLOGIN => self.on_err_nologin(client, m),
+                    ERR_SUMMONDISABLED => self.on_err_summondisabled(client, m),
+                    ERR_USERSDISABLED => self.on_err_usersdisabled(client, m),
+                    ERR_NOTREGISTERED => self.on_err_notregistered(client, m),
+                    ERR_NEEDMOREPARAMS => self.on_err_needmoreparams(client, m),
+                    ERR_ALREADYREGISTERED => self.on_err_alreadyregistered(client, m),
+                    ERR_NOPERMFORHOST => self.on_err_nopermforhost(client, m),
+                    ERR_PASSWDMISMATCH => self.on_err_passwdmismatch(client, m),
+                    ERR_YOUREBANNEDCREEP => self.on_err_yourebannedcreep(client, m),
+                    ERR_YOUWILLBEBANNED => self.on_err_youwillbebanned(client, m),
+                    ERR_KEYSET => self.on_err_keyset(client, m),
+                    ERR_CHANNELISFULL => self.on_err_channelisfull(client, m),
+                    ERR_UNKNOWNMODE => self.on_err_unknownmode(client, m),
+                    ERR_INVITEONLYCHAN => self.on_err_inviteonlychan(client, m),
+                    ERR_BANNEDFROMCHAN => self.on_err_bannedfromchan(client, m),
+                    ERR_BADCHANNELKEY => self.on_err_badchannelkey(client, m),
+                    ERR_BADCHANMASK => self.on_err_badchanmask(client, m),
+                    ERR_NOCHANMODES => self.on_err_nochanmodes(client, m),
+                    ERR_BANLISTFULL => self.on_err_banlistfull(client, m),
+                    ERR_NOPRIVILEGES => self.on_err_noprivileges(client, m),
+                    ERR_CHANOPRIVSNEEDED => self.on_err_chanoprivsneeded(client, m),
+                    ERR_CANTKILLSERVER => self.on_err_cantkillserver(client, m),
+                    ERR_RESTRICTED => self.on_err_restricted(client, m),
+                    ERR_UNIQOPPRIVSNEEDED => self.on_err_uniqopprivsneeded(client, m),
+                    ERR_NOOPERHOST => self.on_err_nooperhost(client, m),
+                    ERR_UMODEUNKNOWNFLAG => self.on_err_umodeunknownflag(client, m),
+                    ERR_USERSDONTMATCH => self.on_err_usersdontmatch(client, m),
+                    RPL_SERVICEINFO => self.on_rpl_serviceinfo(client, m),
+                    RPL_ENDOFSERVICES => self.on_rpl_endofservices(client, m),
+                    RPL_SERVICE => self.on_rpl_service(client, m),
+                    RPL_NONE => self.on_rpl_none(client, m),
+                    RPL_WHOISCHANOP => self.on_rpl_whoischanop(client, m),
+                    RPL_KILLDONE => self.on_rpl_killdone(client, m),
+                    RPL_CLOSING => self.on_rpl_closing(client, m),
+                    RPL_CLOSEEND => self.on_rpl_closeend(client, m),
+                    RPL_INFOSTART => self.on_rpl_infostart(client, m),
+                    RPL_MYPORTIS => self.on_rpl_myportis(client, m),
+                    RPL_STATSCLINE => self.on_rpl_statscline(client, m),
+                    RPL_STATSNLINE => self.on_rpl_statsnline(client, m),
+                    RPL_STATSILINE => self.on_rpl_statsiline(client, m),
+                    RPL_STATSKLINE => self.on_rpl_statskline(client, m),
+                    RPL_STATSQLINE => self.on_rpl_statsqline(client, m),
+                    RPL_STATSYLINE => self.on_rpl_statsyline(client, m),
+                    RPL_STATSVLINE => self.on_rpl_statsvline(client, m),
+                    RPL_STATSLLINE => self.on_rpl_statslline(client, m),
+                    RPL_STATSHLINE => self.on_rpl_statshline(client, m),
+                    RPL_STATSPING => self.on_rpl_statsping(client, m),
+                    RPL_STATSBLINE => self.on_rpl_statsbline(client, m),
+                    RPL_STATSDLINE => self.on_rpl_statsdline(client, m),
+                    ERR_NOSERVICEHOST => self.on_err_noservicehost(client, m),
+                    RPL_TOPICDATE => self.on_rpl_topicdate(client, m),
+                    ERR_MSGFORBIDDEN => self.on_err_msgforbidden(client, m),
+                    _ => self.on_unknown_reply(client, m),
+                }
+            },
+        }
+    }
 
-        err
+    fn on_client_connect (&mut self, client: &mut Client) -> IrcResult<()> {
+        Ok(try!(client.register()))
     }
+    #[allow(unused_variable)] fn on_client_disconnect (&mut self, client: &mut Client) -> IrcResult<()> { Ok(()) }
 
-    fn on_client_connect (&mut self, client: &mut Client) -> io::IoResult<()> {
-        let nick = client.builder().nick.clone();
-        let pass = client.builder().pass.clone();
-        let username = client.builder().username.clone();
-        let servername = client.builder().servername.clone();
-        let realname = client.builder().realname.clone();
+    #[allow(unused_variable)] fn on_any_message (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_invalid_message (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    // err is a ParseError with the line that was skipped
+    #[allow(unused_variable)] fn on_parse_error (&mut self, client: &mut Client, err: &IrcError) -> IrcResult<()> { Ok(()) }
 
-        match pass {
-            Some(pass) => try!(client.pass(pass.as_slice())),
-            None => {},
-        }
+    #[allow(unused_variable)] fn on_unknown_command (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_unknown_reply (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
 
-        try!(client.nick(nick.as_slice()));
+    #[allow(unused_variable)] fn on_command (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_reply (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    // called for every error numeric, before the numeric's own callback
+    #[allow(unused_variable)] fn on_error_numeric (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
 
-        let hostname = match client.builder().hostname {
-            Some(ref host) => host.clone(),
-            None => {
-                match client.socket_name() {
-                    Some(ref host) => host.to_string(),
-                    // XXX something better here?
-                    None => "localhost".to_string(),
-                }
+    #[allow(unused_variable)] fn on_pass (&mut self, client: &mut Client, from: Option<&str>, pass: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_nick (&mut self, client: &mut Client, from: Option<&str>, nick: &str, hopcount: Option<u32>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_user (&mut self, client: &mut Client, from: Option<&str>, username: &str, hostname: &str, servername: &str, realname: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_server (&mut self, client: &mut Client, from: Option<&str>, servername: &str, hopcount: u32, info: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_oper (&mut self, client: &mut Client, from: Option<&str>, user: &str, pass: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_quit (&mut self, client: &mut Client, from: Option<&str>, msg: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_squit (&mut self, client: &mut Client, from: Option<&str>, server: &str, comment: &str) -> IrcResult<()> { Ok(()) }
+
+    #[allow(unused_variable)] fn on_join (&mut self, client: &mut Client, from: Option<&str>, channels: &[&str], keys: &[&str]) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_part (&mut self, client: &mut Client, from: Option<&str>, channels: &[&str]) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_channel_mode (&mut self, client: &mut Client, from: Option<&str>, channel: &str, modes: &str, params: &[&str]) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_user_mode (&mut self, client: &mut Client, from: Option<&str>, nickname: &str, modes: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_topic (&mut self, client: &mut Client, from: Option<&str>, channel: &str, topic: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_names (&mut self, client: &mut Client, from: Option<&str>, channels: &[&str]) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_list (&mut self, client: &mut Client, from: Option<&str>, channels: &[&str], server: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_invite (&mut self, client: &mut Client, from: Option<&str>, nickname: &str, channel: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_kick (&mut self, client: &mut Client, from: Option<&str>, channel: &str, user: &str, comment: Option<&str>) -> IrcResult<()> { Ok(()) }
+
+    #[allow(unused_variable)] fn on_version (&mut self, client: &mut Client, from: Option<&str>, server: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_stats (&mut self, client: &mut Client, from: Option<&str>, query: Option<&str>, server: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_links (&mut self, client: &mut Client, from: Option<&str>, remote_server: Option<&str>, server_mask: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_time (&mut self, client: &mut Client, from: Option<&str>, server: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_connect (&mut self, client: &mut Client, from: Option<&str>, target_server: &str, port: Option<u16>, remote_server: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_trace (&mut self, client: &mut Client, from: Option<&str>, server: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_admin (&mut self, client: &mut Client, from: Option<&str>, server: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_info (&mut self, client: &mut Client, from: Option<&str>, server: Option<&str>) -> IrcResult<()> { Ok(()) }
+
+    #[allow(unused_variable)] fn on_privmsg (&mut self, client: &mut Client, from: Option<&str>, receivers: &[&str], text: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_privmsg_reply (&mut self, client: &mut Client, from: Option<&str>, receivers: &[&str], parent_msgid: &str, text: &str) -> IrcResult<()> {
+        self.on_privmsg(client, from, receivers, text)
+    }
+    #[allow(unused_variable)] fn on_notice (&mut self, client: &mut Client, from: Option<&str>, nickname: &str, text: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_notice_reply (&mut self, client: &mut Client, from: Option<&str>, nickname: &str, parent_msgid: &str, text: &str) -> IrcResult<()> {
+        self.on_notice(client, from, nickname, text)
+    }
+    #[allow(unused_variable)] fn on_action (&mut self, client: &mut Client, from: Option<&str>, receivers: &[&str], text: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_ctcp_request (&mut self, client: &mut Client, from: Option<&str>, receivers: &[&str], ctcp: &Ctcp) -> IrcResult<()> {
+        match (from, client.ctcp_default_response(ctcp)) {
+            (Some(from), Some(response)) => {
+                Ok(try!(client.ctcp_reply(nick_of(from), response.command(), response.params())))
             },
-        };
+            _ => Ok(()),
+        }
+    }
+    #[allow(unused_variable)] fn on_dcc_offer (&mut self, client: &mut Client, from: Option<&str>, receivers: &[&str], offer: &DccOffer) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_ctcp_reply (&mut self, client: &mut Client, from: Option<&str>, nickname: &str, ctcp: &Ctcp) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_who (&mut self, client: &mut Client, from: Option<&str>, name: &str, o: bool) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_whois (&mut self, client: &mut Client, from: Option<&str>, server: Option<&str>, nickmasks: &[&str]) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_whowas (&mut self, client: &mut Client, from: Option<&str>, nickname: &str, count: Option<u32>, server: Option<&str>) -> IrcResult<()> { Ok(()) }
 
-        try!(client.user(
-            username.as_slice(),
-            hostname.as_slice(),
-            servername.as_slice(),
-            realname.as_slice(),
-        ));
+    #[allow(unused_variable)] fn on_kill (&mut self, client: &mut Client, from: Option<&str>, nickname: &str, comment: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_ping (&mut self, client: &mut Client, from: Option<&str>, server1: &str, server2: Option<&str>) -> IrcResult<()> {
+        Ok(try!(client.pong(server1)))
+    }
+    #[allow(unused_variable)] fn on_pong (&mut self, client: &mut Client, from: Option<&str>, daemon1: &str, daemon2: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_error (&mut self, client: &mut Client, from: Option<&str>, message: &str) -> IrcResult<()> { Ok(()) }
 
-        Ok(())
+    #[allow(unused_variable)] fn on_away (&mut self, client: &mut Client, from: Option<&str>, message: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rehash (&mut self, client: &mut Client, from: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_restart (&mut self, client: &mut Client, from: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_summon (&mut self, client: &mut Client, from: Option<&str>, user: &str, server: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_users (&mut self, client: &mut Client, from: Option<&str>, server: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_wallops (&mut self, client: &mut Client, from: Option<&str>, text: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_userhost (&mut self, client: &mut Client, from: Option<&str>, nicknames: &[&str]) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_ison (&mut self, client: &mut Client, from: Option<&str>, nicknames: &[&str]) -> IrcResult<()> { Ok(()) }
+
+    #[allow(unused_variable)] fn on_tagmsg (&mut self, client: &mut Client, from: Option<&str>, target: &str, tags: &[Tag]) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_typing (&mut self, client: &mut Client, from: Option<&str>, target: &str, state: TypingState) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_react (&mut self, client: &mut Client, from: Option<&str>, target: &str, parent_msgid: Option<&str>, reaction: &str) -> IrcResult<()> { Ok(()) }
+
+    #[allow(unused_variable)] fn on_cap (&mut self, client: &mut Client, from: Option<&str>, m: &MessageRef) -> IrcResult<()> {
+        Ok(try!(client.negotiate_caps(m)))
     }
-    #[allow(unused_variable)] fn on_client_disconnect (&mut self, client: &mut Client) -> io::IoResult<()> { Ok(()) }
-
-    #[allow(unused_variable)] fn on_any_message (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_invalid_message (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-
-    #[allow(unused_variable)] fn on_unknown_command (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_unknown_reply (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-
-    #[allow(unused_variable)] fn on_command (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_reply (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-
-    #[allow(unused_variable)] fn on_pass (&mut self, client: &mut Client, from: Option<&str>, pass: &str) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_nick (&mut self, client: &mut Client, from: Option<&str>, nick: &str, hopcount: Option<u32>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_user (&mut self, client: &mut Client, from: Option<&str>, username: &str, hostname: &str, servername: &str, realname: &str) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_server (&mut self, client: &mut Client, from: Option<&str>, servername: &str, hopcount: u32, info: &str) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_oper (&mut self, client: &mut Client, from: Option<&str>, user: &str, pass: &str) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_quit (&mut self, client: &mut Client, from: Option<&str>, msg: Option<&str>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_squit (&mut self, client: &mut Client, from: Option<&str>, server: &str, comment: &str) -> io::IoResult<()> { Ok(()) }
-
-    #[allow(unused_variable)] fn on_join (&mut self, client: &mut Client, from: Option<&str>, channels: &[&str], keys: &[&str]) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_part (&mut self, client: &mut Client, from: Option<&str>, channels: &[&str]) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_channel_mode (&mut self, client: &mut Client, from: Option<&str>, channel: &str, modes: &str, params: &[&str]) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_user_mode (&mut self, client: &mut Client, from: Option<&str>, nickname: &str, modes: &str) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_topic (&mut self, client: &mut Client, from: Option<&str>, channel: &str, topic: Option<&str>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_names (&mut self, client: &mut Client, from: Option<&str>, channels: &[&str]) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_list (&mut self, client: &mut Client, from: Option<&str>, channels: &[&str], server: Option<&str>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_invite (&mut self, client: &mut Client, from: Option<&str>, nickname: &str, channel: &str) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_kick (&mut self, client: &mut Client, from: Option<&str>, channel: &str, user: &str, comment: Option<&str>) -> io::IoResult<()> { Ok(()) }
-
-    #[allow(unused_variable)] fn on_version (&mut self, client: &mut Client, from: Option<&str>, server: Option<&str>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_stats (&mut self, client: &mut Client, from: Option<&str>, query: Option<&str>, server: Option<&str>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_links (&mut self, client: &mut Client, from: Option<&str>, remote_server: Option<&str>, server_mask: Option<&str>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_time (&mut self, client: &mut Client, from: Option<&str>, server: Option<&str>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_connect (&mut self, client: &mut Client, from: Option<&str>, target_server: &str, port: Option<u16>, remote_server: Option<&str>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_trace (&mut self, client: &mut Client, from: Option<&str>, server: Option<&str>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_admin (&mut self, client: &mut Client, from: Option<&str>, server: Option<&str>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_info (&mut self, client: &mut Client, from: Option<&str>, server: Option<&str>) -> io::IoResult<()> { Ok(()) }
-
-    #[allow(unused_variable)] fn on_privmsg (&mut self, client: &mut Client, from: Option<&str>, receivers: &[&str], text: &str) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_notice (&mut self, client: &mut Client, from: Option<&str>, nickname: &str, text: &str) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_who (&mut self, client: &mut Client, from: Option<&str>, name: &str, o: bool) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_whois (&mut self, client: &mut Client, from: Option<&str>, server: Option<&str>, nickmasks: &[&str]) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_whowas (&mut self, client: &mut Client, from: Option<&str>, nickname: &str, count: Option<u32>, server: Option<&str>) -> io::IoResult<()> { Ok(()) }
-
-    #[allow(unused_variable)] fn on_kill (&mut self, client: &mut Client, from: Option<&str>, nickname: &str, comment: &str) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_ping (&mut self, client: &mut Client, from: Option<&str>, server1: &str, server2: Option<&str>) -> io::IoResult<()> {
-        client.pong(server1)
-    }
-    #[allow(unused_variable)] fn on_pong (&mut self, client: &mut Client, from: Option<&str>, daemon1: &str, daemon2: Option<&str>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_error (&mut self, client: &mut Client, from: Option<&str>, message: &str) -> io::IoResult<()> { Ok(()) }
-
-    #[allow(unused_variable)] fn on_away (&mut self, client: &mut Client, from: Option<&str>, message: Option<&str>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rehash (&mut self, client: &mut Client, from: Option<&str>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_restart (&mut self, client: &mut Client, from: Option<&str>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_summon (&mut self, client: &mut Client, from: Option<&str>, user: &str, server: Option<&str>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_users (&mut self, client: &mut Client, from: Option<&str>, server: Option<&str>) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_wallops (&mut self, client: &mut Client, from: Option<&str>, text: &str) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_userhost (&mut self, client: &mut Client, from: Option<&str>, nicknames: &[&str]) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_ison (&mut self, client: &mut Client, from: Option<&str>, nicknames: &[&str]) -> io::IoResult<()> { Ok(()) }
-
-    #[allow(unused_variable)] fn on_rpl_welcome (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_yourhost (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_created (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_myinfo (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_bounce (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_userhost (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_ison (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_away (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_unaway (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_noaway (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_whoisuser (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_whoisserver (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_whoisoperator (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_whoisidle (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_endofwhois (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_whoischannels (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_whowasuser (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_endofwhowas (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_liststart (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_list (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_listend (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_uniqopis (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_channelmodeis (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_notopic (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_topic (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_inviting (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_summoning (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_invitelist (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_endofinvitelist (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_exceptlist (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_endofexceptlist (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_version (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_whoreply (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_endofwho (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_namreply (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_endofnames (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_links (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_endoflinks (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_banlist (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_endofbanlist (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_info (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_endofinfo (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_motdstart (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_motd (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_endofmotd (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_youreoper (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_rehashing (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_youreservice (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_time (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_usersstart (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_users (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_endofusers (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_nousers (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_tracelink (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_traceconnecting (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_tracehandshake (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_traceunknown (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_traceoperator (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_traceuser (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_traceserver (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_traceservice (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_tracenewtype (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_traceclass (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_tracereconnect (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_tracelog (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_traceend (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_statslinkinfo (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_statscommands (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_endofstats (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_statsuptime (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_statsoline (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_umodeis (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_servlist (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_servlistend (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_luserclient (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_luserop (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_luserunknown (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_luserchannels (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_luserme (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_adminme (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_adminloc1 (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_adminloc2 (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_adminemail (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_tryagain (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_nosuchnick (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_nosuchserver (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_nosuchchannel (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_cannotsendtochan (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_toomanychannels (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_wasnosuchnick (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_toomanytargets (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_nosuchservice (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_noorigin (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_norecipient (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_notexttosend (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_notoplevel (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_wildtoplevel (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_badmask (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_unknowncommand (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_nomotd (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_noadmininfo (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_fileerror (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_nonicknamegiven (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_erroneusnickname (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_nicknameinuse (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_nickcollision (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_unavailresource (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_usernotinchannel (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_notonchannel (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_useronchannel (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_nologin (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_summondisabled (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_usersdisabled (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_notregistered (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_needmoreparams (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_alreadyregistered (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_nopermforhost (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_passwdmismatch (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_yourebannedcreep (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_youwillbebanned (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_keyset (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_channelisfull (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_unknownmode (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_inviteonlychan (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_bannedfromchan (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_badchannelkey (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_badchanmask (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_nochanmodes (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_banlistfull (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_noprivileges (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_chanoprivsneeded (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_cantkillserver (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_restricted (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_uniqopprivsneeded (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_nooperhost (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_umodeunknownflag (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_usersdontmatch (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_serviceinfo (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_endofservices (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_service (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_none (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_whoischanop (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_killdone (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_closing (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_closeend (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_infostart (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_myportis (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_statscline (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_statsnline (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_statsiline (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_statskline (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_statsqline (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_statsyline (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_statsvline (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_statslline (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_statshline (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_statsping (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_statsbline (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_statsdline (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_noservicehost (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_rpl_topicdate (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
-    #[allow(unused_variable)] fn on_err_msgforbidden (&mut self, client: &mut Client, m: &Message) -> io::IoResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_authenticate (&mut self, client: &mut Client, from: Option<&str>, data: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_account (&mut self, client: &mut Client, from: Option<&str>, account: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_batch_start (&mut self, client: &mut Client, from: Option<&str>, reference: &str, batch_type: &str, params: &[&str]) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_batch_end (&mut self, client: &mut Client, from: Option<&str>, reference: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_chghost (&mut self, client: &mut Client, from: Option<&str>, username: &str, hostname: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_knock (&mut self, client: &mut Client, from: Option<&str>, channel: &str, message: Option<&str>) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_monitor (&mut self, client: &mut Client, from: Option<&str>, subcommand: &str, targets: &[&str]) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_setname (&mut self, client: &mut Client, from: Option<&str>, realname: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_watch (&mut self, client: &mut Client, from: Option<&str>, entries: &[&str]) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_silence (&mut self, client: &mut Client, from: Option<&str>, masks: &[&str]) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_cprivmsg (&mut self, client: &mut Client, from: Option<&str>, nickname: &str, channel: &str, text: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_cnotice (&mut self, client: &mut Client, from: Option<&str>, nickname: &str, channel: &str, text: &str) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_webirc (&mut self, client: &mut Client, from: Option<&str>, password: &str, gateway: &str, hostname: &str, ip: &str) -> IrcResult<()> { Ok(()) }
+
+    #[allow(unused_variable)] fn on_chathistory (&mut self, client: &mut Client, batch: &HistoryBatch) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_chathistory_targets (&mut self, client: &mut Client, targets: &[HistoryTarget]) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_chathistory_fail (&mut self, client: &mut Client, err: &HistoryError) -> IrcResult<()> { Ok(()) }
+
+    #[allow(unused_variable)] fn on_channel_names (&mut self, client: &mut Client, channel: &str, members: &[NameEntry]) -> IrcResult<()> { Ok(()) }
+
+    #[allow(unused_variable)] fn on_fail (&mut self, client: &mut Client, from: Option<&str>, reply: &StandardReply) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_warn (&mut self, client: &mut Client, from: Option<&str>, reply: &StandardReply) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_note (&mut self, client: &mut Client, from: Option<&str>, reply: &StandardReply) -> IrcResult<()> { Ok(()) }
+
+    #[allow(unused_variable)] fn on_rpl_welcome (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_yourhost (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_created (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_myinfo (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_bounce (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_userhost (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_ison (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_away (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_unaway (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_noaway (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_whoisuser (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_whoisserver (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_whoisoperator (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_whoisidle (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_endofwhois (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_whoischannels (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_whowasuser (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_endofwhowas (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_liststart (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_list (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_listend (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_uniqopis (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_channelmodeis (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_notopic (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_topic (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_inviting (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_summoning (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_invitelist (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_endofinvitelist (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_exceptlist (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_endofexceptlist (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_version (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_whoreply (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_endofwho (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_namreply (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_endofnames (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_links (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_endoflinks (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_banlist (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_endofbanlist (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_info (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_endofinfo (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_motdstart (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_motd (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_endofmotd (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_youreoper (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_rehashing (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_youreservice (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_time (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_usersstart (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_users (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_endofusers (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_nousers (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_tracelink (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_traceconnecting (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_tracehandshake (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_traceunknown (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_traceoperator (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_traceuser (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_traceserver (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_traceservice (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_tracenewtype (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_traceclass (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_tracereconnect (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_tracelog (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_traceend (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_statslinkinfo (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_statscommands (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_endofstats (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_statsuptime (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_statsoline (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_umodeis (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_servlist (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_servlistend (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_luserclient (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_luserop (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_luserunknown (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_luserchannels (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_luserme (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_adminme (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_adminloc1 (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_adminloc2 (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_adminemail (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_tryagain (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_nosuchnick (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_nosuchserver (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_nosuchchannel (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_cannotsendtochan (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_toomanychannels (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_wasnosuchnick (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_toomanytargets (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_nosuchservice (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_noorigin (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_norecipient (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_notexttosend (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_notoplevel (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_wildtoplevel (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_badmask (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_unknowncommand (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_nomotd (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_noadmininfo (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_fileerror (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_nonicknamegiven (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_erroneusnickname (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_nicknameinuse (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_nickcollision (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_unavailresource (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_usernotinchannel (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_notonchannel (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_useronchannel (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_nologin (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_summondisabled (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_usersdisabled (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_notregistered (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_needmoreparams (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_alreadyregistered (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_nopermforhost (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_passwdmismatch (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_yourebannedcreep (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_youwillbebanned (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_keyset (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_channelisfull (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_unknownmode (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_inviteonlychan (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_bannedfromchan (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_badchannelkey (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_badchanmask (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_nochanmodes (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_banlistfull (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_noprivileges (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_chanoprivsneeded (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_cantkillserver (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_restricted (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_uniqopprivsneeded (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_nooperhost (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_umodeunknownflag (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_usersdontmatch (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_serviceinfo (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_endofservices (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_service (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_none (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_whoischanop (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_killdone (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_closing (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_closeend (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_infostart (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_myportis (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_statscline (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_statsnline (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_statsiline (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_statskline (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_statsqline (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_statsyline (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_statsvline (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_statslline (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_statshline (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_statsping (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_statsbline (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_statsdline (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_noservicehost (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_rpl_topicdate (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
+    #[allow(unused_variable)] fn on_err_msgforbidden (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> { Ok(()) }
 }
 
 fn is_channel (name: &str) -> bool {
     name.starts_with("#") || name.starts_with("&")
 }
+
+fn message_target (m: &Message) -> Option<&str> {
+    match *m.message_type() {
+        Privmsg | Notice | Tagmsg | Topic | Part | Kick => {
+            m.params().as_slice().get(0).map(|s| s.as_slice())
+        },
+        _ => None,
+    }
+}
+
+fn nick_of (prefix: &str) -> &str {
+    match prefix.find('!') {
+        Some(idx) => prefix.slice_to(idx),
+        None => prefix,
+    }
+}
+
+#[test]
+fn test_client_sender () {
+    use std::io::{Acceptor, Listener, TcpListener};
+
+    let mut listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+    let port = listener.socket_name().unwrap().port;
+    let mut acceptor = listener.listen().unwrap();
+
+    spawn(proc() {
+        let mut conn = io::BufferedStream::new(acceptor.accept().unwrap());
+        conn.write_str(":irc.host 001 me :Welcome\r\n").unwrap();
+        conn.flush().unwrap();
+        loop {
+            let line = conn.read_line().unwrap();
+            if line.as_slice().starts_with("PRIVMSG") {
+                assert_eq!(line.as_slice(), "PRIVMSG #chan :from another task\r\n");
+                break;
+            }
+        }
+        conn.write_str("ERROR :bye\r\n").unwrap();
+        conn.flush().unwrap();
+    });
+
+    let mut builder = ClientBuilder::new("me", "127.0.0.1");
+    builder.set_port(port);
+    builder.set_send_queue_limit(Some(1));
+    let mut client = builder.connect();
+    let sender = client.sender();
+
+    // nothing is read while the queue is full, so this fails rather than
+    // waiting
+    sender.privmsg(["#chan"], "from another task").unwrap();
+    match sender.try_write(Message::new(None, Ping, vec!["x".to_string()])) {
+        Err(e) => assert_eq!(e.kind, io::ResourceUnavailable),
+        Ok(()) => panic!("queue should be full"),
+    }
+    assert_eq!(sender.privmsg(["#chan"], "two\r\nlines").unwrap_err().kind, io::InvalidInput);
+
+    match client.run_loop(|_, _| Ok(())) {
+        ServerError(text) => assert_eq!(text.as_slice(), "bye"),
+        e => panic!("unexpected {}", e),
+    }
+    assert_eq!(sender.privmsg(["#chan"], "too late").unwrap_err().kind, io::BrokenPipe);
+    assert_eq!(client.sender().notice("me", "too late").unwrap_err().kind, io::BrokenPipe);
+}
+
+// a client connected to a local server which sends lines and then waits for
+// the client to hang up. the receiver gets everything the client sent.
+#[cfg(test)]
+fn test_connection (lines: &'static str) -> (Client, Receiver<String>) {
+    test_connection_with(lines, |_| {})
+}
+
+#[cfg(test)]
+fn test_connection_with (lines: &'static str, setup: |&mut ClientBuilder|) -> (Client, Receiver<String>) {
+    use std::io::{Acceptor, Listener, TcpListener};
+
+    let mut listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+    let port = listener.socket_name().unwrap().port;
+    let mut acceptor = listener.listen().unwrap();
+
+    let (tx, rx) = channel();
+    spawn(proc() {
+        let mut conn = acceptor.accept().unwrap();
+        conn.write_str(lines).unwrap();
+        tx.send(String::from_utf8(conn.read_to_end().unwrap()).unwrap());
+    });
+
+    let mut builder = ClientBuilder::new("me", "127.0.0.1");
+    builder.set_port(port);
+    setup(&mut builder);
+    (builder.connect(), rx)
+}
+
+#[test]
+fn test_server_caps () {
+    struct NullCallbacks;
+    impl ClientCallbacks for NullCallbacks {}
+
+    let (mut client, _) = test_connection(concat!(
+        "CAP * LS * :a=1 b\r\n",
+        "CAP * LS :c\r\n",
+        "CAP * NEW :a=2 d\r\n",
+        "CAP * LS :a=3 c\r\n",
+    ));
+    let mut cbs = NullCallbacks;
+    let mut buf = vec![];
+
+    for _ in range(0u, 3) {
+        client.handle_next(&mut buf, |c, m| cbs.handle_message(c, m)).unwrap();
+    }
+    assert_eq!(client.server_cap_value("a"), Some("2"));
+    assert_eq!(client.server_caps.len(), 4);
+
+    // a later LS replaces everything
+    client.handle_next(&mut buf, |c, m| cbs.handle_message(c, m)).unwrap();
+    assert_eq!(client.server_cap_value("a"), Some("3"));
+    assert_eq!(client.server_caps.len(), 2);
+}
+
+#[test]
+fn test_client_tags () {
+    struct Recorder {
+        calls: Vec<String>,
+    }
+    impl ClientCallbacks for Recorder {
+        fn on_privmsg (&mut self, _client: &mut Client, from: Option<&str>, receivers: &[&str], text: &str) -> IrcResult<()> {
+            self.calls.push(format!("privmsg {} {} {}", from, receivers, text));
+            Ok(())
+        }
+        fn on_privmsg_reply (&mut self, _client: &mut Client, from: Option<&str>, receivers: &[&str], parent_msgid: &str, text: &str) -> IrcResult<()> {
+            self.calls.push(format!("privmsg_reply {} {} {} {}", from, receivers, parent_msgid, text));
+            Ok(())
+        }
+        fn on_notice (&mut self, _client: &mut Client, from: Option<&str>, nickname: &str, text: &str) -> IrcResult<()> {
+            self.calls.push(format!("notice {} {} {}", from, nickname, text));
+            Ok(())
+        }
+        fn on_notice_reply (&mut self, _client: &mut Client, from: Option<&str>, nickname: &str, parent_msgid: &str, text: &str) -> IrcResult<()> {
+            self.calls.push(format!("notice_reply {} {} {} {}", from, nickname, parent_msgid, text));
+            Ok(())
+        }
+        fn on_typing (&mut self, _client: &mut Client, from: Option<&str>, target: &str, state: TypingState) -> IrcResult<()> {
+            self.calls.push(format!("typing {} {} {}", from, target, state));
+            Ok(())
+        }
+        fn on_react (&mut self, _client: &mut Client, from: Option<&str>, target: &str, parent_msgid: Option<&str>, reaction: &str) -> IrcResult<()> {
+            self.calls.push(format!("react {} {} {} {}", from, target, parent_msgid, reaction));
+            Ok(())
+        }
+    }
+
+    let (mut client, _) = test_connection(concat!(
+        ":nick!u@h PRIVMSG #chan :hi\r\n",
+        "@+draft/reply=abc :nick!u@h PRIVMSG #chan :yes\r\n",
+        ":nick!u@h NOTICE me :psst\r\n",
+        "@+draft/reply=abc :nick!u@h NOTICE me :noted\r\n",
+        "@+typing=active :nick!u@h TAGMSG #chan\r\n",
+        "@+typing=bogus :nick!u@h TAGMSG #chan\r\n",
+        "@+draft/react=lol;+draft/reply=abc :nick!u@h TAGMSG #chan\r\n",
+        "@+draft/react=lol :nick!u@h TAGMSG me\r\n",
+    ));
+    let mut cbs = Recorder { calls: vec![] };
+    let mut buf = vec![];
+    for _ in range(0u, 8) {
+        client.handle_next(&mut buf, |c, m| cbs.handle_message(c, m)).unwrap();
+    }
+
+    assert_eq!(cbs.calls, vec![
+        "privmsg Some(nick!u@h) [#chan] hi".to_string(),
+        "privmsg_reply Some(nick!u@h) [#chan] abc yes".to_string(),
+        "notice Some(nick!u@h) me psst".to_string(),
+        "notice_reply Some(nick!u@h) me abc noted".to_string(),
+        "typing Some(nick!u@h) #chan TypingActive".to_string(),
+        "react Some(nick!u@h) #chan Some(abc) lol".to_string(),
+        "react Some(nick!u@h) me None lol".to_string(),
+    ]);
+}
+
+#[test]
+fn test_chathistory_fail () {
+    struct Recorder {
+        calls: Vec<String>,
+    }
+    impl ClientCallbacks for Recorder {
+        fn on_fail (&mut self, _client: &mut Client, _from: Option<&str>, reply: &StandardReply) -> IrcResult<()> {
+            self.calls.push(format!("fail {} {}", reply.command(), reply.code()));
+            Ok(())
+        }
+        fn on_chathistory_fail (&mut self, _client: &mut Client, err: &HistoryError) -> IrcResult<()> {
+            self.calls.push(format!("chathistory_fail {}", err.code()));
+            Ok(())
+        }
+    }
+
+    let (mut client, _) = test_connection(
+        "FAIL CHATHISTORY INVALID_TARGET LATEST #nope :Messages could not be retrieved\r\n"
+    );
+    let mut cbs = Recorder { calls: vec![] };
+    let mut buf = vec![];
+    client.handle_next(&mut buf, |c, m| cbs.handle_message(c, m)).unwrap();
+    assert_eq!(cbs.calls, vec![
+        "fail CHATHISTORY INVALID_TARGET".to_string(),
+        "chathistory_fail INVALID_TARGET".to_string(),
+    ]);
+}
+
+#[test]
+fn test_parse_errors () {
+    use message::{InvalidCommand, MissingCommand, MessageTooLong, ParseErrorKind};
+
+    struct Recorder {
+        calls: Vec<String>,
+        errors: Vec<(String, ParseErrorKind)>,
+    }
+    impl ClientCallbacks for Recorder {
+        fn on_privmsg (&mut self, _client: &mut Client, _from: Option<&str>, _receivers: &[&str], text: &str) -> IrcResult<()> {
+            self.calls.push(text.to_string());
+            Ok(())
+        }
+        fn on_parse_error (&mut self, _client: &mut Client, err: &IrcError) -> IrcResult<()> {
+            match err {
+                &ParseError(ref line, ref e) => self.errors.push((line.clone(), e.kind())),
+                e => panic!("unexpected {}", e),
+            }
+            Ok(())
+        }
+        fn on_client_disconnect (&mut self, _client: &mut Client) -> IrcResult<()> {
+            assert_eq!(self.calls, vec!["one".to_string(), "two".to_string()]);
+            assert_eq!(self.errors, vec![
+                (":nick!u@h PR!VMSG #chan :bad\r\n".to_string(), InvalidCommand),
+                (":nick!u@h\r\n".to_string(), MissingCommand),
+                // lines which are too long aren't kept
+                ("".to_string(), MessageTooLong),
+            ]);
+            Ok(())
+        }
+    }
+
+    let (client, _) = test_connection_with(concat!(
+        ":nick!u@h PRIVMSG #chan :one\r\n",
+        ":nick!u@h PR!VMSG #chan :bad\r\n",
+        ":nick!u@h\r\n",
+        ":nick!u@h PRIVMSG #chan :this line is far too long to be read\r\n",
+        ":nick!u@h PRIVMSG #chan :two\r\n",
+        "ERROR :bye\r\n",
+    ), |builder| { builder.set_max_line_length(40); });
+    match client.run_loop_with_callbacks(Recorder { calls: vec![], errors: vec![] }) {
+        ServerError(text) => assert_eq!(text.as_slice(), "bye"),
+        e => panic!("unexpected {}", e),
+    }
+}
+
+#[cfg(feature = "ssl")]
+#[test]
+fn test_registration_waits_for_caps () {
+    use std::io::{Acceptor, Listener, TcpListener};
+
+    struct NullCallbacks;
+    impl ClientCallbacks for NullCallbacks {}
+
+    let mut listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+    let port = listener.socket_name().unwrap().port;
+    let mut acceptor = listener.listen().unwrap();
+
+    let (tx, rx) = channel();
+    spawn(proc() {
+        let mut conn = io::BufferedStream::new(acceptor.accept().unwrap());
+        assert_eq!(conn.read_line().unwrap().as_slice(), "CAP LS 302\r\n");
+        // nothing else until the server has answered
+        conn.get_mut().set_read_timeout(Some(200));
+        assert_eq!(conn.read_line().unwrap_err().kind, io::TimedOut);
+        conn.get_mut().set_read_timeout(None);
+
+        conn.write_str(":irc.host CAP * LS :multi-prefix\r\n").unwrap();
+        conn.flush().unwrap();
+        let mut lines = vec![];
+        for _ in range(0u, 3) {
+            lines.push(conn.read_line().unwrap());
+        }
+        tx.send(lines);
+    });
+
+    let mut builder = ClientBuilder::new("me", "127.0.0.1");
+    builder.set_port(port);
+    builder.set_pass("secret");
+    builder.set_sts_store(None);
+    let mut client = builder.connect();
+    let mut cbs = NullCallbacks;
+    cbs.on_client_connect(&mut client).unwrap();
+    let mut buf = vec![];
+    client.handle_next(&mut buf, |c, m| cbs.handle_message(c, m)).unwrap();
+
+    let lines = rx.recv();
+    assert_eq!(lines[0].as_slice(), "PASS secret\r\n");
+    assert!(lines[1].as_slice().starts_with("NICK me"));
+    assert!(lines[2].as_slice().starts_with("USER me"));
+}
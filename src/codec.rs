@@ -0,0 +1,201 @@
+use std::io;
+
+use constants::{MAX_MESSAGE_LENGTH, MAX_TAGS_LENGTH};
+
+static READ_CHUNK: uint = 4096;
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum FrameError {
+    // the line (which has been skipped) was this many bytes long, or at
+    // least this long if its end hadn't been seen yet
+    LineTooLong(uint),
+    ReadError(io::IoError),
+}
+
+// splits a stream of bytes into lines ending in \r\n or \n. bytes are
+// buffered until a whole line is available, so partial reads are fine, and
+// it doesn't care where the bytes come from: either feed it data from an
+// async source and call decode, or let read_line pull from a Reader.
+pub struct LineCodec {
+    buf: Vec<u8>,
+    // everything before this in buf is known not to contain a \n
+    scanned: uint,
+    max_len: uint,
+    // set while skipping the rest of a line which was too long
+    discarding: bool,
+}
+
+impl LineCodec {
+    // the max length includes the line ending
+    pub fn new (max_len: uint) -> LineCodec {
+        LineCodec { buf: vec![], scanned: 0, max_len: max_len, discarding: false }
+    }
+
+    pub fn with_tags () -> LineCodec {
+        LineCodec::new(MAX_TAGS_LENGTH + MAX_MESSAGE_LENGTH)
+    }
+
+    pub fn without_tags () -> LineCodec {
+        LineCodec::new(MAX_MESSAGE_LENGTH)
+    }
+
+    pub fn max_len (&self) -> uint {
+        self.max_len
+    }
+
+    pub fn set_max_len (&mut self, max_len: uint) {
+        self.max_len = max_len;
+    }
+
+    // bytes which have been received but not returned as part of a line
+    pub fn buffered (&self) -> &[u8] {
+        self.buf.as_slice()
+    }
+
+    pub fn feed (&mut self, bytes: &[u8]) {
+        self.buf.push_all(bytes);
+    }
+
+    // copies the next complete line, including its line ending, into out.
+    // returns None if more data is needed. after a LineTooLong error the
+    // rest of that line is skipped, so it's fine to keep going.
+    pub fn decode (&mut self, out: &mut Vec<u8>) -> Option<Result<(), FrameError>> {
+        loop {
+            let newline = self.buf.slice_from(self.scanned).iter().position(|&b| b == b'\n');
+            match newline {
+                Some(idx) => {
+                    let end = self.scanned + idx + 1;
+                    let discarding = self.discarding;
+                    self.discarding = false;
+                    if discarding {
+                        // the end of a line which was already reported
+                        self.consume(end);
+                        continue;
+                    }
+                    if end > self.max_len {
+                        self.consume(end);
+                        return Some(Err(LineTooLong(end)));
+                    }
+                    out.clear();
+                    out.push_all(self.buf.slice_to(end));
+                    self.consume(end);
+                    return Some(Ok(()));
+                },
+                None => {
+                    let len = self.buf.len();
+                    if self.discarding {
+                        self.consume(len);
+                        return None;
+                    }
+                    // there's no way for this to end up short enough
+                    if len >= self.max_len {
+                        self.consume(len);
+                        self.discarding = true;
+                        return Some(Err(LineTooLong(len)));
+                    }
+                    self.scanned = len;
+                    return None;
+                },
+            }
+        }
+    }
+
+    // reads from r until a whole line is available. if the reader hits eof
+    // in the middle of a line, that line is returned without a line ending.
+    pub fn read_line_into<R: Reader> (&mut self, r: &mut R, out: &mut Vec<u8>) -> Result<(), FrameError> {
+        let mut chunk = [0u8, ..READ_CHUNK];
+        loop {
+            match self.decode(out) {
+                Some(res) => return res,
+                None => {},
+            }
+
+            match r.read(chunk) {
+                Ok(n) => self.feed(chunk.slice_to(n)),
+                Err(ref e) if e.kind == io::EndOfFile && self.buf.len() > 0 => {
+                    out.clear();
+                    out.push_all(self.buf.as_slice());
+                    let len = self.buf.len();
+                    self.consume(len);
+                    return Ok(());
+                },
+                Err(e) => return Err(ReadError(e)),
+            }
+        }
+    }
+
+    pub fn read_line<R: Reader> (&mut self, r: &mut R) -> Result<Vec<u8>, FrameError> {
+        let mut line = vec![];
+        try!(self.read_line_into(r, &mut line));
+        Ok(line)
+    }
+
+    // drops the first n bytes, shifting the rest down in place so that the
+    // buffer is reused
+    fn consume (&mut self, n: uint) {
+        let len = self.buf.len();
+        {
+            let buf = self.buf.as_mut_slice();
+            for i in range(n, len) {
+                buf[i - n] = buf[i];
+            }
+        }
+        self.buf.truncate(len - n);
+        self.scanned = 0;
+    }
+}
+
+#[test]
+fn test_partial_lines () {
+    let mut codec = LineCodec::without_tags();
+    let mut line = vec![];
+
+    codec.feed(b":irc.host PING :a");
+    assert_eq!(codec.decode(&mut line), None);
+    codec.feed(b"bc\r\n:irc.host PI");
+    assert_eq!(codec.decode(&mut line), Some(Ok(())));
+    assert_eq!(line.as_slice(), b":irc.host PING :abc\r\n");
+    assert_eq!(codec.decode(&mut line), None);
+    assert_eq!(codec.buffered(), b":irc.host PI");
+
+    codec.feed(b"NG :def\n\r\n");
+    assert_eq!(codec.decode(&mut line), Some(Ok(())));
+    assert_eq!(line.as_slice(), b":irc.host PING :def\n");
+    assert_eq!(codec.decode(&mut line), Some(Ok(())));
+    assert_eq!(line.as_slice(), b"\r\n");
+    assert_eq!(codec.decode(&mut line), None);
+}
+
+#[test]
+fn test_line_too_long () {
+    let mut codec = LineCodec::new(10);
+    let mut line = vec![];
+
+    codec.feed(b"0123456789abcdef\r\nok\r\n");
+    assert_eq!(codec.decode(&mut line), Some(Err(LineTooLong(18))));
+    assert_eq!(codec.decode(&mut line), Some(Ok(())));
+    assert_eq!(line.as_slice(), b"ok\r\n");
+
+    // the end of the line hasn't arrived yet
+    codec.feed(b"0123456789");
+    assert_eq!(codec.decode(&mut line), Some(Err(LineTooLong(10))));
+    codec.feed(b"abcdef");
+    assert_eq!(codec.decode(&mut line), None);
+    codec.feed(b"ghi\r\nok again\r\n");
+    assert_eq!(codec.decode(&mut line), Some(Ok(())));
+    assert_eq!(line.as_slice(), b"ok again\r\n");
+}
+
+#[test]
+fn test_read_line () {
+    let mut r = io::MemReader::new(b"PING :a\r\nPING :b\nPING :c".to_vec());
+    let mut codec = LineCodec::with_tags();
+
+    assert_eq!(codec.read_line(&mut r), Ok(b"PING :a\r\n".to_vec()));
+    assert_eq!(codec.read_line(&mut r), Ok(b"PING :b\n".to_vec()));
+    assert_eq!(codec.read_line(&mut r), Ok(b"PING :c".to_vec()));
+    match codec.read_line(&mut r) {
+        Err(ReadError(e)) => assert_eq!(e.kind, io::EndOfFile),
+        other => panic!("unexpected {}", other),
+    }
+}
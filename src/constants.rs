@@ -3,7 +3,9 @@
 use std::fmt::{FormatError, Formatter, Show};
 use std::from_str::FromStr;
 
-#[deriving(PartialEq, Eq)]
+use numerics::{numeric, is_error_numeric};
+
+#[deriving(PartialEq, Eq, Clone)]
 pub enum MessageType {
     Pass,
     Nick,
@@ -45,6 +47,23 @@ pub enum MessageType {
     Wallops,
     Userhost,
     Ison,
+    Tagmsg,
+    Fail,
+    Warn,
+    Note,
+    Cap,
+    Authenticate,
+    Account,
+    Batch,
+    Chghost,
+    Knock,
+    Monitor,
+    Setname,
+    Watch,
+    Silence,
+    Cnotice,
+    Cprivmsg,
+    Webirc,
     RawCommand(String),
     Reply(u16),
 }
@@ -92,8 +111,30 @@ impl Show for MessageType {
             &Wallops => try!(write!(f, "WALLOPS")),
             &Userhost => try!(write!(f, "USERHOST")),
             &Ison => try!(write!(f, "ISON")),
+            &Tagmsg => try!(write!(f, "TAGMSG")),
+            &Fail => try!(write!(f, "FAIL")),
+            &Warn => try!(write!(f, "WARN")),
+            &Note => try!(write!(f, "NOTE")),
+            &Cap => try!(write!(f, "CAP")),
+            &Authenticate => try!(write!(f, "AUTHENTICATE")),
+            &Account => try!(write!(f, "ACCOUNT")),
+            &Batch => try!(write!(f, "BATCH")),
+            &Chghost => try!(write!(f, "CHGHOST")),
+            &Knock => try!(write!(f, "KNOCK")),
+            &Monitor => try!(write!(f, "MONITOR")),
+            &Setname => try!(write!(f, "SETNAME")),
+            &Watch => try!(write!(f, "WATCH")),
+            &Silence => try!(write!(f, "SILENCE")),
+            &Cnotice => try!(write!(f, "CNOTICE")),
+            &Cprivmsg => try!(write!(f, "CPRIVMSG")),
+            &Webirc => try!(write!(f, "WEBIRC")),
             &RawCommand(ref s) => try!(write!(f, "{}", s)),
-            &Reply(i) => try!(write!(f, "{:03}", i)),
+            &Reply(i) => {
+                match numeric(i) {
+                    Some(info) => try!(write!(f, "{}({:03})", info.name(), i)),
+                    None => try!(write!(f, "{:03}", i)),
+                }
+            },
         }
 
         Ok(())
@@ -143,6 +184,24 @@ impl FromStr for MessageType {
             "WALLOPS" => Some(Wallops),
             "USERHOST" => Some(Userhost),
             "ISON" => Some(Ison),
+            "TAGMSG" => Some(Tagmsg),
+            "FAIL" => Some(Fail),
+            "WARN" => Some(Warn),
+            "NOTE" => Some(Note),
+            "CAP" => Some(Cap),
+            "AUTHENTICATE" => Some(Authenticate),
+            "ACCOUNT" => Some(Account),
+            "BATCH" => Some(Batch),
+            "CHGHOST" => Some(Chghost),
+            "KNOCK" => Some(Knock),
+            "MONITOR" => Some(Monitor),
+            "SETNAME" => Some(Setname),
+            "WATCH" => Some(Watch),
+            "SILENCE" => Some(Silence),
+            "CNOTICE" => Some(Cnotice),
+            "CPRIVMSG" => Some(Cprivmsg),
+            "WEBIRC" => Some(Webirc),
+            "" => Some(RawCommand(String::new())),
             s => {
                 match s.char_at(0) {
                     '0'..'9' => {
@@ -165,6 +224,22 @@ impl MessageType {
             _ => false,
         }
     }
+
+    pub fn is_error_reply (&self) -> bool {
+        match self {
+            &Reply(i) => is_error_numeric(i),
+            _ => false,
+        }
+    }
+
+    // the command as it's written on the wire. replies display with their
+    // names, so use this rather than to_string when serializing.
+    pub fn to_command (&self) -> String {
+        match self {
+            &Reply(i) => format!("{:03}", i),
+            _ => self.to_string(),
+        }
+    }
 }
 
 // normal replies
@@ -173,6 +248,7 @@ pub static RPL_YOURHOST: u16 = 2;
 pub static RPL_CREATED: u16 = 3;
 pub static RPL_MYINFO: u16 = 4;
 pub static RPL_BOUNCE: u16 = 5;
+pub static RPL_ISUPPORT: u16 = 5; // modern servers use 005 for this instead of RPL_BOUNCE
 pub static RPL_USERHOST: u16 = 302;
 pub static RPL_ISON: u16 = 303;
 pub static RPL_AWAY: u16 = 301;
@@ -308,7 +384,7 @@ pub static ERR_NOOPERHOST: u16 = 491; // No O-lines for your host
 pub static ERR_UMODEUNKNOWNFLAG: u16 = 501; // Unknown MODE flag
 pub static ERR_USERSDONTMATCH: u16 = 502; // Can't change mode for other users
 
-//unused
+// reserved in the rfcs, and not sent by anything current
 pub static RPL_SERVICEINFO: u16 = 231;
 pub static RPL_ENDOFSERVICES: u16 = 232;
 pub static RPL_SERVICE: u16 = 233;
@@ -333,15 +409,93 @@ pub static RPL_STATSBLINE: u16 = 247;
 pub static RPL_STATSDLINE: u16 = 250;
 pub static ERR_NOSERVICEHOST: u16 = 492;
 
-// guesses
-pub static RPL_TOPICDATE: u16 = 333; // date the topic was set, in seconds since the epoch
-pub static ERR_MSGFORBIDDEN: u16 = 505; // freenode blocking privmsg from unreged users
+// extensions. see numerics.rs for where each of these comes from.
+pub static RPL_REDIR: u16 = 10;
+pub static RPL_YOURID: u16 = 42;
+pub static RPL_STATSCONN: u16 = 250;
+pub static RPL_LOCALUSERS: u16 = 265;
+pub static RPL_GLOBALUSERS: u16 = 266;
+pub static RPL_WHOISCERTFP: u16 = 276;
+pub static RPL_WHOISREGNICK: u16 = 307;
+pub static RPL_WHOISSPECIAL: u16 = 320;
+pub static RPL_CREATIONTIME: u16 = 329;
+pub static RPL_WHOISACCOUNT: u16 = 330;
+pub static RPL_TOPICWHOTIME: u16 = 333;
+pub static RPL_TOPICDATE: u16 = 333; // the old name for RPL_TOPICWHOTIME
+pub static RPL_WHOISBOT: u16 = 335;
+pub static RPL_WHOISACTUALLY: u16 = 338;
+pub static RPL_WHOSPCRPL: u16 = 354;
+pub static RPL_WHOISHOST: u16 = 378;
+pub static RPL_WHOISMODES: u16 = 379;
+pub static RPL_VISIBLEHOST: u16 = 396;
+pub static ERR_UNKNOWNERROR: u16 = 400;
+pub static ERR_INPUTTOOLONG: u16 = 417;
+pub static ERR_MSGFORBIDDEN: u16 = 505;
+pub static ERR_HELPNOTFOUND: u16 = 524;
+pub static ERR_INVALIDKEY: u16 = 525;
+pub static RPL_STARTTLS: u16 = 670;
+pub static RPL_WHOISSECURE: u16 = 671;
+pub static ERR_STARTTLS: u16 = 691;
+pub static ERR_INVALIDMODEPARAM: u16 = 696;
+pub static RPL_HELPSTART: u16 = 704;
+pub static RPL_HELPTXT: u16 = 705;
+pub static RPL_ENDOFHELP: u16 = 706;
+pub static ERR_NOPRIVS: u16 = 723;
+pub static RPL_MONONLINE: u16 = 730;
+pub static RPL_MONOFFLINE: u16 = 731;
+pub static RPL_MONLIST: u16 = 732;
+pub static RPL_ENDOFMONLIST: u16 = 733;
+pub static ERR_MONLISTFULL: u16 = 734;
+pub static RPL_LOGGEDIN: u16 = 900;
+pub static RPL_LOGGEDOUT: u16 = 901;
+pub static ERR_NICKLOCKED: u16 = 902;
+pub static RPL_SASLSUCCESS: u16 = 903;
+pub static ERR_SASLFAIL: u16 = 904;
+pub static ERR_SASLTOOLONG: u16 = 905;
+pub static ERR_SASLABORTED: u16 = 906;
+pub static ERR_SASLALREADY: u16 = 907;
+pub static RPL_SASLMECHS: u16 = 908;
+
+// client-only tags
+pub static TAG_REPLY: &'static str = "+draft/reply";
+pub static TAG_REACT: &'static str = "+draft/react";
+pub static TAG_TYPING: &'static str = "+typing";
 
 pub static MAX_MESSAGE_LENGTH: uint = 512;
+pub static MAX_TAGS_LENGTH: uint = 8191;
+pub static MAX_PARAMS: uint = 15;
 
 #[test]
 fn test_message_type () {
     assert!(!from_str::<MessageType>("PASS").unwrap().is_reply());
     assert!(from_str::<MessageType>("001").unwrap().is_reply());
     assert!(!from_str::<MessageType>("NOTACOMMAND").unwrap().is_reply());
+    assert_eq!(from_str::<MessageType>("TAGMSG"), Some(Tagmsg));
+    assert_eq!(from_str::<MessageType>(""), Some(RawCommand(String::new())));
+    assert_eq!(Tagmsg.to_string().as_slice(), "TAGMSG");
+
+    let commands = [
+        "CAP", "AUTHENTICATE", "ACCOUNT", "BATCH", "CHGHOST", "KNOCK", "MONITOR",
+        "SETNAME", "WATCH", "SILENCE", "CNOTICE", "CPRIVMSG", "WEBIRC",
+    ];
+    for &command in commands.iter() {
+        let t = from_str::<MessageType>(command).unwrap();
+        match t {
+            RawCommand(_) => panic!("{} should have its own variant", command),
+            _ => {},
+        }
+        assert_eq!(t.to_string().as_slice(), command);
+    }
+}
+
+#[test]
+fn test_reply_display () {
+    assert_eq!(Reply(330).to_string().as_slice(), "RPL_WHOISACCOUNT(330)");
+    assert_eq!(Reply(1).to_string().as_slice(), "RPL_WELCOME(001)");
+    assert_eq!(Reply(999).to_string().as_slice(), "999");
+    assert_eq!(Reply(1).to_command().as_slice(), "001");
+    assert!(Reply(433).is_error_reply());
+    assert!(!Reply(1).is_error_reply());
+    assert!(!Privmsg.is_error_reply());
+    assert_eq!(Privmsg.to_command().as_slice(), "PRIVMSG");
 }
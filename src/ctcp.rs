@@ -0,0 +1,243 @@
+use time;
+
+static CTCP_DELIM: char = '\x01';
+static M_QUOTE: char = '\x10';
+static X_QUOTE: char = '\\';
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub struct Ctcp {
+    command: String,
+    params: Option<String>,
+}
+
+impl Ctcp {
+    pub fn new (command: &str, params: Option<&str>) -> Ctcp {
+        Ctcp {
+            command: command.to_string(),
+            params: params.map(|s| s.to_string()),
+        }
+    }
+
+    // the trailing delimiter is optional, since enough clients leave it off
+    pub fn parse (text: &str) -> Option<Ctcp> {
+        if !text.starts_with("\x01") || text.len() < 2 {
+            return None;
+        }
+
+        let text = low_level_dequote(text.slice_from(1));
+        let text = if text.as_slice().ends_with("\x01") {
+            text.as_slice().slice_to(text.len() - 1).to_string()
+        }
+        else {
+            text
+        };
+        let text = ctcp_dequote(text.as_slice());
+
+        match text.as_slice().find(' ') {
+            Some(idx) => Some(Ctcp {
+                command: text.as_slice().slice_to(idx).to_string(),
+                params: Some(text.as_slice().slice_from(idx + 1).to_string()),
+            }),
+            None if text.len() > 0 => Some(Ctcp {
+                command: text.clone(),
+                params: None,
+            }),
+            None => None,
+        }
+    }
+
+    pub fn command (&self) -> &str {
+        self.command.as_slice()
+    }
+
+    pub fn params (&self) -> Option<&str> {
+        self.params.as_ref().map(|s| s.as_slice())
+    }
+
+    pub fn to_text (&self) -> String {
+        let mut inner = self.command.clone();
+        match self.params {
+            Some(ref params) => {
+                inner.push(' ');
+                inner.push_str(params.as_slice());
+            },
+            None => {},
+        }
+
+        let mut ret = String::new();
+        ret.push(CTCP_DELIM);
+        ret.push_str(low_level_quote(ctcp_quote(inner.as_slice()).as_slice()).as_slice());
+        ret.push(CTCP_DELIM);
+        ret
+    }
+}
+
+pub fn low_level_quote (text: &str) -> String {
+    let mut ret = String::new();
+    for c in text.chars() {
+        match c {
+            '\0' => { ret.push(M_QUOTE); ret.push('0') },
+            '\r' => { ret.push(M_QUOTE); ret.push('r') },
+            '\n' => { ret.push(M_QUOTE); ret.push('n') },
+            '\x10' => { ret.push(M_QUOTE); ret.push(M_QUOTE) },
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+pub fn low_level_dequote (text: &str) -> String {
+    let mut ret = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        if quoted {
+            match c {
+                '0' => ret.push('\0'),
+                'r' => ret.push('\r'),
+                'n' => ret.push('\n'),
+                c => ret.push(c),
+            }
+            quoted = false;
+        }
+        else if c == M_QUOTE {
+            quoted = true;
+        }
+        else {
+            ret.push(c);
+        }
+    }
+    ret
+}
+
+pub fn ctcp_quote (text: &str) -> String {
+    let mut ret = String::new();
+    for c in text.chars() {
+        match c {
+            '\x01' => { ret.push(X_QUOTE); ret.push('a') },
+            '\\' => { ret.push(X_QUOTE); ret.push(X_QUOTE) },
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+pub fn ctcp_dequote (text: &str) -> String {
+    let mut ret = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        if quoted {
+            match c {
+                'a' => ret.push('\x01'),
+                c => ret.push(c),
+            }
+            quoted = false;
+        }
+        else if c == X_QUOTE {
+            quoted = true;
+        }
+        else {
+            ret.push(c);
+        }
+    }
+    ret
+}
+
+pub struct CtcpResponder {
+    version: Option<String>,
+    source: Option<String>,
+    ping: bool,
+    time: bool,
+}
+
+impl CtcpResponder {
+    pub fn new () -> CtcpResponder {
+        CtcpResponder {
+            version: Some(format!("rust-irc {}", env!("CARGO_PKG_VERSION"))),
+            source: Some("https://github.com/doy/rust-irc".to_string()),
+            ping: true,
+            time: true,
+        }
+    }
+
+    pub fn set_version (&mut self, version: Option<&str>) {
+        self.version = version.map(|s| s.to_string());
+    }
+
+    pub fn set_source (&mut self, source: Option<&str>) {
+        self.source = source.map(|s| s.to_string());
+    }
+
+    pub fn set_ping (&mut self, ping: bool) {
+        self.ping = ping;
+    }
+
+    pub fn set_time (&mut self, time: bool) {
+        self.time = time;
+    }
+
+    pub fn clientinfo (&self) -> String {
+        let mut supported = vec!["ACTION", "CLIENTINFO"];
+        if self.ping { supported.push("PING") }
+        if self.source.is_some() { supported.push("SOURCE") }
+        if self.time { supported.push("TIME") }
+        if self.version.is_some() { supported.push("VERSION") }
+        supported.connect(" ")
+    }
+
+    pub fn respond (&self, request: &Ctcp) -> Option<Ctcp> {
+        match request.command() {
+            "VERSION" => {
+                self.version.as_ref().map(|v| Ctcp::new("VERSION", Some(v.as_slice())))
+            },
+            "SOURCE" => {
+                self.source.as_ref().map(|s| Ctcp::new("SOURCE", Some(s.as_slice())))
+            },
+            "PING" if self.ping => Some(Ctcp::new("PING", request.params())),
+            "TIME" if self.time => {
+                Some(Ctcp::new("TIME", Some(time::now().rfc822().to_string().as_slice())))
+            },
+            "CLIENTINFO" => Some(Ctcp::new("CLIENTINFO", Some(self.clientinfo().as_slice()))),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_ctcp_parse () {
+    assert_eq!(Ctcp::parse("\x01VERSION\x01"), Some(Ctcp::new("VERSION", None)));
+    assert_eq!(Ctcp::parse("\x01ACTION waves\x01"), Some(Ctcp::new("ACTION", Some("waves"))));
+    assert_eq!(Ctcp::parse("\x01ACTION waves"), Some(Ctcp::new("ACTION", Some("waves"))));
+    assert_eq!(Ctcp::parse("\x01PING 123\\a\\\\ \x10n\x01"), Some(Ctcp::new("PING", Some("123\x01\\ \n"))));
+    assert_eq!(Ctcp::parse("not ctcp"), None);
+    assert_eq!(Ctcp::parse("\x01\x01"), None);
+}
+
+#[test]
+fn test_ctcp_to_text () {
+    assert_eq!(Ctcp::new("ACTION", Some("waves")).to_text().as_slice(), "\x01ACTION waves\x01");
+    assert_eq!(Ctcp::new("VERSION", None).to_text().as_slice(), "\x01VERSION\x01");
+    let ctcp = Ctcp::new("PING", Some("a\\b\x01c\r\n"));
+    assert_eq!(Ctcp::parse(ctcp.to_text().as_slice()), Some(ctcp));
+}
+
+#[test]
+fn test_ctcp_responder () {
+    let mut responder = CtcpResponder::new();
+    responder.set_version(Some("mybot 1.0"));
+    responder.set_source(None);
+
+    assert_eq!(
+        responder.respond(&Ctcp::new("VERSION", None)),
+        Some(Ctcp::new("VERSION", Some("mybot 1.0")))
+    );
+    assert_eq!(
+        responder.respond(&Ctcp::new("PING", Some("12345"))),
+        Some(Ctcp::new("PING", Some("12345")))
+    );
+    assert_eq!(responder.respond(&Ctcp::new("SOURCE", None)), None);
+    assert_eq!(
+        responder.respond(&Ctcp::new("CLIENTINFO", None)),
+        Some(Ctcp::new("CLIENTINFO", Some("ACTION CLIENTINFO PING TIME VERSION")))
+    );
+    assert_eq!(responder.respond(&Ctcp::new("FINGER", None)), None);
+}
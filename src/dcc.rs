@@ -0,0 +1,498 @@
+use std::io;
+use std::io::{Acceptor, Listener};
+use std::io::net::ip::{IpAddr, Ipv4Addr};
+
+use ctcp::Ctcp;
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum DccOffer {
+    DccChat(IpAddr, u16, Option<String>),
+    DccSend(DccFile),
+    DccResume(String, u16, u64, Option<String>),
+    DccAccept(String, u16, u64, Option<String>),
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub struct DccFile {
+    filename: String,
+    addr: IpAddr,
+    port: u16,
+    size: Option<u64>,
+    token: Option<String>,
+}
+
+impl DccFile {
+    pub fn new (filename: &str, addr: IpAddr, port: u16, size: Option<u64>, token: Option<&str>) -> DccFile {
+        DccFile {
+            filename: filename.to_string(),
+            addr: addr,
+            port: port,
+            size: size,
+            token: token.map(|s| s.to_string()),
+        }
+    }
+
+    pub fn filename (&self) -> &str {
+        self.filename.as_slice()
+    }
+
+    pub fn addr (&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn port (&self) -> u16 {
+        self.port
+    }
+
+    pub fn size (&self) -> Option<u64> {
+        self.size
+    }
+
+    pub fn token (&self) -> Option<&str> {
+        self.token.as_ref().map(|s| s.as_slice())
+    }
+
+    // a passive (reverse) offer asks the receiver to listen instead, and
+    // reply with the same offer filled in with its own address and port
+    pub fn is_passive (&self) -> bool {
+        self.port == 0 && self.token.is_some()
+    }
+}
+
+impl DccOffer {
+    pub fn from_ctcp (ctcp: &Ctcp) -> Option<DccOffer> {
+        if ctcp.command() != "DCC" {
+            return None;
+        }
+        let args = match ctcp.params() {
+            Some(params) => split_args(params),
+            None => return None,
+        };
+        let args: Vec<&str> = args.iter().map(|s| s.as_slice()).collect();
+        if args.len() < 4 {
+            return None;
+        }
+
+        let token = args.get(5).map(|s| s.to_string());
+        match args[0] {
+            "CHAT" => {
+                match (decode_addr(args[2]), from_str(args[3])) {
+                    (Some(addr), Some(port)) => {
+                        Some(DccChat(addr, port, args.get(4).map(|s| s.to_string())))
+                    },
+                    _ => None,
+                }
+            },
+            "SEND" => {
+                match (decode_addr(args[2]), from_str(args[3])) {
+                    (Some(addr), Some(port)) => {
+                        Some(DccSend(DccFile {
+                            filename: args[1].to_string(),
+                            addr: addr,
+                            port: port,
+                            size: args.get(4).and_then(|s| from_str(*s)),
+                            token: token,
+                        }))
+                    },
+                    _ => None,
+                }
+            },
+            "RESUME" | "ACCEPT" => {
+                let token = args.get(4).map(|s| s.to_string());
+                match (from_str(args[2]), from_str(args[3])) {
+                    (Some(port), Some(position)) if args[0] == "RESUME" => {
+                        Some(DccResume(args[1].to_string(), port, position, token))
+                    },
+                    (Some(port), Some(position)) => {
+                        Some(DccAccept(args[1].to_string(), port, position, token))
+                    },
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
+
+    pub fn to_ctcp (&self) -> Ctcp {
+        let params = match self {
+            &DccChat(addr, port, ref token) => {
+                let mut params = format!("CHAT chat {} {}", encode_addr(addr), port);
+                push_token(&mut params, token);
+                params
+            },
+            &DccSend(ref file) => {
+                let mut params = format!(
+                    "SEND {} {} {}",
+                    quote_filename(file.filename.as_slice()), encode_addr(file.addr), file.port
+                );
+                match file.size {
+                    Some(size) => params.push_str(format!(" {}", size).as_slice()),
+                    // a token has to come after the size
+                    None if file.token.is_some() => params.push_str(" 0"),
+                    None => {},
+                }
+                push_token(&mut params, &file.token);
+                params
+            },
+            &DccResume(ref filename, port, position, ref token) => {
+                let mut params = format!("RESUME {} {} {}", quote_filename(filename.as_slice()), port, position);
+                push_token(&mut params, token);
+                params
+            },
+            &DccAccept(ref filename, port, position, ref token) => {
+                let mut params = format!("ACCEPT {} {} {}", quote_filename(filename.as_slice()), port, position);
+                push_token(&mut params, token);
+                params
+            },
+        };
+        Ctcp::new("DCC", Some(params.as_slice()))
+    }
+}
+
+pub struct DccListener {
+    acceptor: io::TcpAcceptor,
+    addr: IpAddr,
+    port: u16,
+}
+
+impl DccListener {
+    pub fn bind (addr: IpAddr) -> io::IoResult<DccListener> {
+        let listener = try!(io::TcpListener::bind(addr.to_string().as_slice(), 0));
+        let mut acceptor = try!(listener.listen());
+        let name = try!(acceptor.socket_name());
+        Ok(DccListener { acceptor: acceptor, addr: name.ip, port: name.port })
+    }
+
+    pub fn addr (&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn port (&self) -> u16 {
+        self.port
+    }
+
+    pub fn set_timeout (&mut self, ms: Option<u64>) {
+        self.acceptor.set_timeout(ms);
+    }
+
+    pub fn accept (mut self) -> io::IoResult<io::TcpStream> {
+        self.acceptor.accept()
+    }
+}
+
+pub fn connect (addr: IpAddr, port: u16) -> io::IoResult<io::TcpStream> {
+    io::TcpStream::connect(addr.to_string().as_slice(), port)
+}
+
+// sends the file contents, starting from offset (for resumed transfers).
+// file has to be positioned at offset already, since only the counts start
+// from there. the receiver acknowledges with the total number of bytes it
+// has seen, which we wait for before returning.
+pub fn send_file<R: Reader> (stream: &mut io::TcpStream, file: &mut R, size: u64, offset: u64, progress: |u64, u64|) -> io::IoResult<u64> {
+    let mut buf = [0u8, ..4096];
+    let mut sent = offset;
+    let mut acks = AckReader { buf: [0u8, ..4], len: 0, acked: offset };
+    while sent < size {
+        let len = match file.read(buf) {
+            Ok(len) => len,
+            Err(ref e) if e.kind == io::EndOfFile => break,
+            Err(e) => return Err(e),
+        };
+        try!(stream.write(buf.slice_to(len)));
+        sent += len as u64;
+        progress(sent, size);
+        // the receiver acks every chunk, and if nothing reads them they
+        // fill up the connection until both ends are stuck writing
+        try!(acks.read(stream, Some(1)));
+    }
+    try!(stream.flush());
+
+    if sent != size {
+        return Err(dcc_error("file is shorter than the advertised size"));
+    }
+
+    while acks.acked < size {
+        try!(acks.read(stream, None));
+    }
+    stream.set_read_timeout(None);
+    Ok(sent)
+}
+
+// acks are only 32 bits wide, so this keeps track of how many times they've
+// wrapped around to get the full count
+struct AckReader {
+    buf: [u8, ..4],
+    len: uint,
+    acked: u64,
+}
+
+impl AckReader {
+    // with a timeout, reads whatever acks arrive until nothing has for that
+    // long. without one, waits for the next ack.
+    fn read (&mut self, stream: &mut io::TcpStream, timeout: Option<u64>) -> io::IoResult<()> {
+        stream.set_read_timeout(timeout);
+        loop {
+            let len = match stream.read(self.buf.slice_from_mut(self.len)) {
+                Ok(len) => len,
+                Err(ref e) if e.kind == io::TimedOut && timeout.is_some() => return Ok(()),
+                Err(e) => return Err(e),
+            };
+            self.len += len;
+            if self.len < 4 {
+                continue;
+            }
+            let ack = (self.buf[0] as u32 << 24) | (self.buf[1] as u32 << 16)
+                | (self.buf[2] as u32 << 8) | self.buf[3] as u32;
+            self.acked = unwrap_ack(self.acked, ack);
+            self.len = 0;
+            if timeout.is_none() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// acks only ever go up, so a smaller one means the count wrapped
+fn unwrap_ack (prev: u64, ack: u32) -> u64 {
+    let acked = (prev & !0xffffffff) | ack as u64;
+    if acked < prev { acked + (1 << 32) } else { acked }
+}
+
+pub fn receive_file<W: Writer> (stream: &mut io::TcpStream, file: &mut W, size: u64, offset: u64, progress: |u64, u64|) -> io::IoResult<u64> {
+    let mut buf = [0u8, ..4096];
+    let mut received = offset;
+    while received < size {
+        let len = match stream.read(buf) {
+            Ok(len) => len,
+            Err(ref e) if e.kind == io::EndOfFile => {
+                return Err(dcc_error("connection closed before the whole file was received"));
+            },
+            Err(e) => return Err(e),
+        };
+        if received + len as u64 > size {
+            return Err(dcc_error("received more data than the advertised size"));
+        }
+        try!(file.write(buf.slice_to(len)));
+        received += len as u64;
+        try!(stream.write_be_u32((received & 0xffffffff) as u32));
+        try!(stream.flush());
+        progress(received, size);
+    }
+    try!(file.flush());
+    Ok(received)
+}
+
+pub struct DccChatStream {
+    stream: io::BufferedStream<io::TcpStream>,
+}
+
+impl DccChatStream {
+    pub fn new (stream: io::TcpStream) -> DccChatStream {
+        DccChatStream { stream: io::BufferedStream::new(stream) }
+    }
+
+    pub fn send_line (&mut self, line: &str) -> io::IoResult<()> {
+        try!(self.stream.write_str(line));
+        try!(self.stream.write_str("\n"));
+        self.stream.flush()
+    }
+
+    pub fn read_line (&mut self) -> io::IoResult<String> {
+        let line = try!(self.stream.read_line());
+        Ok(line.as_slice().trim_right_chars(['\r', '\n'].as_slice()).to_string())
+    }
+}
+
+// addresses are sent as a single decimal integer for ipv4, but as the usual
+// textual form for ipv6
+pub fn encode_addr (addr: IpAddr) -> String {
+    match addr {
+        Ipv4Addr(a, b, c, d) => {
+            ((a as u32 << 24) | (b as u32 << 16) | (c as u32 << 8) | d as u32).to_string()
+        },
+        addr => addr.to_string(),
+    }
+}
+
+pub fn decode_addr (addr: &str) -> Option<IpAddr> {
+    match from_str::<u32>(addr) {
+        Some(n) => Some(Ipv4Addr((n >> 24) as u8, (n >> 16) as u8, (n >> 8) as u8, n as u8)),
+        None => from_str(addr),
+    }
+}
+
+fn quote_filename (filename: &str) -> String {
+    if filename.contains_char(' ') {
+        format!("\"{}\"", filename)
+    }
+    else {
+        filename.to_string()
+    }
+}
+
+fn split_args (params: &str) -> Vec<String> {
+    let mut ret = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in params.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ' ' if !quoted => {
+                if current.len() > 0 {
+                    ret.push(current);
+                    current = String::new();
+                }
+            },
+            c => current.push(c),
+        }
+    }
+    if current.len() > 0 {
+        ret.push(current);
+    }
+    ret
+}
+
+fn push_token (params: &mut String, token: &Option<String>) {
+    match *token {
+        Some(ref token) => {
+            params.push(' ');
+            params.push_str(token.as_slice());
+        },
+        None => {},
+    }
+}
+
+fn dcc_error (desc: &'static str) -> io::IoError {
+    io::IoError {
+        kind: io::OtherIoError,
+        desc: desc,
+        detail: None,
+    }
+}
+
+#[test]
+fn test_dcc_offer () {
+    {
+        let ctcp = Ctcp::parse("\x01DCC SEND \"my file.txt\" 2130706433 5000 1234\x01").unwrap();
+        let offer = DccOffer::from_ctcp(&ctcp).unwrap();
+        assert_eq!(
+            offer,
+            DccSend(DccFile::new("my file.txt", Ipv4Addr(127, 0, 0, 1), 5000, Some(1234), None))
+        );
+        assert_eq!(offer.to_ctcp(), ctcp);
+    }
+
+    {
+        let ctcp = Ctcp::parse("\x01DCC SEND file.txt 2130706433 0 1234 17\x01").unwrap();
+        match DccOffer::from_ctcp(&ctcp).unwrap() {
+            DccSend(file) => {
+                assert!(file.is_passive());
+                assert_eq!(file.token(), Some("17"));
+            },
+            other => panic!("unexpected {}", other),
+        }
+    }
+
+    {
+        let ctcp = Ctcp::parse("\x01DCC CHAT chat 3232235777 6000\x01").unwrap();
+        let offer = DccOffer::from_ctcp(&ctcp).unwrap();
+        assert_eq!(offer, DccChat(Ipv4Addr(192, 168, 1, 1), 6000, None));
+        assert_eq!(offer.to_ctcp(), ctcp);
+    }
+
+    {
+        let ctcp = Ctcp::parse("\x01DCC RESUME file.txt 5000 100\x01").unwrap();
+        let offer = DccOffer::from_ctcp(&ctcp).unwrap();
+        assert_eq!(offer, DccResume("file.txt".to_string(), 5000, 100, None));
+        assert_eq!(offer.to_ctcp(), ctcp);
+        assert_eq!(
+            DccAccept("file.txt".to_string(), 5000, 100, None).to_ctcp().params(),
+            Some("ACCEPT file.txt 5000 100")
+        );
+    }
+
+    assert_eq!(DccOffer::from_ctcp(&Ctcp::new("DCC", Some("SEND file"))), None);
+    assert_eq!(DccOffer::from_ctcp(&Ctcp::new("VERSION", None)), None);
+}
+
+#[test]
+fn test_dcc_send_loopback () {
+    let data = Vec::from_fn(10000, |i| (i % 256) as u8);
+    let size = data.len() as u64;
+
+    let listener = DccListener::bind(Ipv4Addr(127, 0, 0, 1)).unwrap();
+    let port = listener.port();
+
+    let sender_data = data.clone();
+    let (tx, rx) = channel();
+    spawn(proc() {
+        let mut stream = listener.accept().unwrap();
+        let mut reader = io::MemReader::new(sender_data);
+        let sent = send_file(&mut stream, &mut reader, size, 0, |_, _| {}).unwrap();
+        tx.send(sent);
+    });
+
+    let mut stream = connect(Ipv4Addr(127, 0, 0, 1), port).unwrap();
+    let mut writer = io::MemWriter::new();
+    let mut last_progress = 0;
+    let received = receive_file(&mut stream, &mut writer, size, 0, |done, _| {
+        last_progress = done;
+    }).unwrap();
+
+    assert_eq!(received, size);
+    assert_eq!(last_progress, size);
+    assert_eq!(writer.unwrap(), data);
+    assert_eq!(rx.recv(), size);
+}
+
+#[test]
+fn test_dcc_send_large () {
+    // big enough that the acks fill the connection if they aren't read
+    // while sending
+    let data = Vec::from_fn(8 * 1024 * 1024, |i| (i % 251) as u8);
+    let size = data.len() as u64;
+
+    let listener = DccListener::bind(Ipv4Addr(127, 0, 0, 1)).unwrap();
+    let port = listener.port();
+
+    let sender_data = data.clone();
+    let (tx, rx) = channel();
+    spawn(proc() {
+        let mut stream = listener.accept().unwrap();
+        let mut reader = io::MemReader::new(sender_data);
+        tx.send(send_file(&mut stream, &mut reader, size, 0, |_, _| {}).unwrap());
+    });
+
+    let mut stream = connect(Ipv4Addr(127, 0, 0, 1), port).unwrap();
+    let mut writer = io::MemWriter::new();
+    assert_eq!(receive_file(&mut stream, &mut writer, size, 0, |_, _| {}).unwrap(), size);
+    assert!(writer.unwrap() == data);
+    assert_eq!(rx.recv(), size);
+}
+
+#[test]
+fn test_dcc_send_complete () {
+    // resuming at the end sends nothing, and there's nothing to wait for
+    let listener = DccListener::bind(Ipv4Addr(127, 0, 0, 1)).unwrap();
+    let port = listener.port();
+    let (tx, rx) = channel();
+    spawn(proc() {
+        let mut stream = listener.accept().unwrap();
+        let mut reader = io::MemReader::new(vec![]);
+        tx.send(send_file(&mut stream, &mut reader, 100, 100, |_, _| {}).unwrap());
+    });
+
+    let mut stream = connect(Ipv4Addr(127, 0, 0, 1), port).unwrap();
+    let mut writer = io::MemWriter::new();
+    assert_eq!(receive_file(&mut stream, &mut writer, 100, 100, |_, _| {}).unwrap(), 100);
+    assert_eq!(rx.recv(), 100);
+}
+
+#[test]
+fn test_unwrap_ack () {
+    assert_eq!(unwrap_ack(0, 1000), 1000);
+    assert_eq!(unwrap_ack(0xfffffff0, 0x10), 0x100000010);
+    assert_eq!(unwrap_ack(0x100000010, 0x20), 0x100000020);
+    // an ack for exactly 4 GiB doesn't look like the end of a bigger file
+    assert_eq!(unwrap_ack(0xffffff00, 0), 0x100000000);
+}
@@ -0,0 +1,315 @@
+use std::ascii::AsciiExt;
+use std::char;
+use std::collections::HashMap;
+
+pub trait TextEncoding {
+    fn name (&self) -> &'static str;
+    // returns None if the bytes aren't valid in this encoding
+    fn decode (&self, bytes: &[u8]) -> Option<String>;
+    // characters which can't be represented are replaced with '?'
+    fn encode (&self, text: &str) -> Vec<u8>;
+}
+
+pub struct Utf8Encoding;
+
+impl TextEncoding for Utf8Encoding {
+    fn name (&self) -> &'static str {
+        "utf-8"
+    }
+
+    fn decode (&self, bytes: &[u8]) -> Option<String> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+
+    fn encode (&self, text: &str) -> Vec<u8> {
+        text.as_bytes().to_vec()
+    }
+}
+
+pub struct Latin1Encoding;
+
+impl TextEncoding for Latin1Encoding {
+    fn name (&self) -> &'static str {
+        "iso-8859-1"
+    }
+
+    fn decode (&self, bytes: &[u8]) -> Option<String> {
+        Some(bytes.iter().map(|&b| b as char).collect())
+    }
+
+    fn encode (&self, text: &str) -> Vec<u8> {
+        text.chars().map(|c| if (c as u32) < 0x100 { c as u8 } else { b'?' }).collect()
+    }
+}
+
+// code points for 0x80-0x9f, which is the only place cp1252 differs from
+// latin-1. the unassigned bytes map to the matching c1 control characters.
+static CP1252_HIGH: [u32, ..32] = [
+    0x20ac, 0x0081, 0x201a, 0x0192, 0x201e, 0x2026, 0x2020, 0x2021,
+    0x02c6, 0x2030, 0x0160, 0x2039, 0x0152, 0x008d, 0x017d, 0x008f,
+    0x0090, 0x2018, 0x2019, 0x201c, 0x201d, 0x2022, 0x2013, 0x2014,
+    0x02dc, 0x2122, 0x0161, 0x203a, 0x0153, 0x009d, 0x017e, 0x0178,
+];
+
+pub struct Cp1252Encoding;
+
+impl TextEncoding for Cp1252Encoding {
+    fn name (&self) -> &'static str {
+        "windows-1252"
+    }
+
+    fn decode (&self, bytes: &[u8]) -> Option<String> {
+        Some(bytes.iter().map(|&b| {
+            if b >= 0x80 && b < 0xa0 {
+                char::from_u32(CP1252_HIGH[(b - 0x80) as uint]).unwrap()
+            }
+            else {
+                b as char
+            }
+        }).collect())
+    }
+
+    fn encode (&self, text: &str) -> Vec<u8> {
+        text.chars().map(|c| {
+            let cp = c as u32;
+            match CP1252_HIGH.iter().position(|&high| high == cp) {
+                Some(idx) => (0x80 + idx) as u8,
+                None if cp < 0x100 => cp as u8,
+                None => b'?',
+            }
+        }).collect()
+    }
+}
+
+pub fn encoding_from_label (label: &str) -> Option<Box<TextEncoding + Send>> {
+    match label.to_ascii_lower().as_slice() {
+        "utf-8" | "utf8" => Some(box Utf8Encoding as Box<TextEncoding + Send>),
+        "iso-8859-1" | "latin1" | "latin-1" => Some(box Latin1Encoding as Box<TextEncoding + Send>),
+        "windows-1252" | "cp1252" => Some(box Cp1252Encoding as Box<TextEncoding + Send>),
+        _ => None,
+    }
+}
+
+pub struct EncodingConfig {
+    default: Box<TextEncoding + Send>,
+    targets: HashMap<String, Box<TextEncoding + Send>>,
+    try_utf8: bool,
+}
+
+impl EncodingConfig {
+    pub fn new () -> EncodingConfig {
+        EncodingConfig {
+            default: box Utf8Encoding as Box<TextEncoding + Send>,
+            targets: HashMap::new(),
+            try_utf8: true,
+        }
+    }
+
+    pub fn set_default (&mut self, encoding: Box<TextEncoding + Send>) {
+        self.default = encoding;
+    }
+
+    pub fn set_target (&mut self, target: &str, encoding: Box<TextEncoding + Send>) {
+        self.targets.insert(target.to_ascii_lower(), encoding);
+    }
+
+    pub fn clear_target (&mut self, target: &str) {
+        self.targets.pop(&target.to_ascii_lower());
+    }
+
+    // when set, incoming text is decoded as utf-8 if it's valid utf-8, and
+    // only falls back to the configured encoding otherwise
+    pub fn set_try_utf8 (&mut self, try_utf8: bool) {
+        self.try_utf8 = try_utf8;
+    }
+
+    pub fn try_utf8 (&self) -> bool {
+        self.try_utf8
+    }
+
+    pub fn encoding_for (&self, target: Option<&str>) -> &TextEncoding {
+        match target.and_then(|t| self.targets.find(&t.to_ascii_lower())) {
+            Some(encoding) => &**encoding,
+            None => &*self.default,
+        }
+    }
+
+    pub fn decode (&self, target: Option<&str>, bytes: &[u8]) -> String {
+        if self.try_utf8 {
+            match String::from_utf8(bytes.to_vec()) {
+                Ok(s) => return s,
+                Err(_) => {},
+            }
+        }
+        match self.encoding_for(target).decode(bytes) {
+            Some(s) => s,
+            None => String::from_utf8_lossy(bytes).into_string(),
+        }
+    }
+
+    pub fn encode (&self, target: Option<&str>, text: &str) -> Vec<u8> {
+        self.encoding_for(target).encode(text)
+    }
+
+    pub fn encoded_len (&self, target: Option<&str>, text: &str) -> uint {
+        self.encode(target, text).len()
+    }
+
+    // splits text into pieces which each take at most max_bytes bytes once
+    // encoded, preferring to split at spaces
+    pub fn split (&self, target: Option<&str>, text: &str, max_bytes: uint) -> Vec<String> {
+        let encoding = self.encoding_for(target);
+        let mut ret = vec![];
+        let mut current = String::new();
+        let mut current_len = 0;
+        let mut last_space = None;
+
+        for c in text.chars() {
+            let mut s = String::new();
+            s.push(c);
+            let len = encoding.encode(s.as_slice()).len();
+
+            if current_len + len > max_bytes && current.len() > 0 {
+                if c == ' ' {
+                    ret.push(current);
+                    current = String::new();
+                    current_len = 0;
+                    last_space = None;
+                    continue;
+                }
+                match last_space {
+                    Some(idx) => {
+                        let rest = current.as_slice().slice_from(idx + 1).to_string();
+                        ret.push(current.as_slice().slice_to(idx).to_string());
+                        current_len = encoding.encode(rest.as_slice()).len();
+                        current = rest;
+                    },
+                    None => {
+                        ret.push(current);
+                        current = String::new();
+                        current_len = 0;
+                    },
+                }
+                last_space = None;
+            }
+
+            if c == ' ' {
+                last_space = Some(current.len());
+            }
+            current.push(c);
+            current_len += len;
+        }
+
+        if current.len() > 0 {
+            ret.push(current);
+        }
+
+        ret
+    }
+}
+
+// finds the target whose encoding applies to a raw line, without decoding
+// the rest of it. that's the channel for messages to a channel, and
+// otherwise whoever sent the line, since a private message's first param is
+// just our own nick. lines without a prefix fall back to the first param.
+pub fn raw_target (line: &[u8]) -> Option<String> {
+    let line = if line.ends_with(b"\r\n") {
+        line.slice_to(line.len() - 2)
+    }
+    else if line.ends_with(b"\n") {
+        line.slice_to(line.len() - 1)
+    }
+    else {
+        line
+    };
+
+    let mut words = line.split(|&b| b == b' ').filter(|w| w.len() > 0);
+    let mut word = words.next();
+    if word.map(|w| w[0] == b'@').unwrap_or(false) {
+        word = words.next();
+    }
+    let mut sender = None;
+    if word.map(|w| w[0] == b':').unwrap_or(false) {
+        let prefix = word.unwrap().slice_from(1);
+        sender = match prefix.split(|&b| b == b'!' || b == b'@').next() {
+            Some(nick) if nick.len() > 0 => Some(nick),
+            _ => None,
+        };
+        word = words.next();
+    }
+    // the command
+    if word.is_none() {
+        return None;
+    }
+    let param = match words.next() {
+        Some(w) if w[0] != b':' => Some(w),
+        _ => None,
+    };
+
+    let target = match (param, sender) {
+        (Some(p), _) if p[0] == b'#' || p[0] == b'&' => Some(p),
+        (_, Some(nick)) => Some(nick),
+        (p, None) => p,
+    };
+    target.map(|t| String::from_utf8_lossy(t).into_string())
+}
+
+#[test]
+fn test_encodings () {
+    assert_eq!(Latin1Encoding.decode(b"caf\xe9"), Some("café".to_string()));
+    assert_eq!(Latin1Encoding.encode("café €"), b"caf\xe9 ?".to_vec());
+    assert_eq!(Cp1252Encoding.decode(b"\x80 \x93hi\x94"), Some("€ “hi”".to_string()));
+    assert_eq!(Cp1252Encoding.encode("€ é"), b"\x80 \xe9".to_vec());
+    assert_eq!(Utf8Encoding.decode(b"caf\xe9"), None);
+}
+
+#[test]
+fn test_encoding_config () {
+    let mut config = EncodingConfig::new();
+    config.set_default(encoding_from_label("cp1252").unwrap());
+    config.set_target("#Latin", encoding_from_label("latin1").unwrap());
+
+    assert_eq!(config.decode(None, "café".as_bytes()).as_slice(), "café");
+    assert_eq!(config.decode(None, b"\x80").as_slice(), "€");
+    assert_eq!(config.decode(Some("#latin"), b"\x80").as_slice(), "\u0080");
+    assert_eq!(config.encode(Some("#LATIN"), "café"), b"caf\xe9".to_vec());
+
+    config.set_try_utf8(false);
+    assert_eq!(config.decode(None, "é".as_bytes()).as_slice(), "Ã©");
+}
+
+#[test]
+fn test_split () {
+    let config = EncodingConfig::new();
+    assert_eq!(
+        config.split(None, "aaa bbb ccc", 7),
+        vec!["aaa bbb".to_string(), "ccc".to_string()]
+    );
+    assert_eq!(
+        config.split(None, "ééééé", 4),
+        vec!["éé".to_string(), "éé".to_string(), "é".to_string()]
+    );
+
+    let mut config = EncodingConfig::new();
+    config.set_default(encoding_from_label("latin1").unwrap());
+    assert_eq!(config.split(None, "ééééé", 4), vec!["éééé".to_string(), "é".to_string()]);
+}
+
+#[test]
+fn test_raw_target () {
+    assert_eq!(raw_target(b":nick!u@h PRIVMSG #chan :hi\r\n"), Some("#chan".to_string()));
+    assert_eq!(raw_target(b"@time=x :nick!u@h NOTICE me :hi\r\n"), Some("nick".to_string()));
+    assert_eq!(raw_target(b":nick@h PRIVMSG me :hi\r\n"), Some("nick".to_string()));
+    assert_eq!(raw_target(b"PING :server\r\n"), None);
+    assert_eq!(raw_target(b"PART #chan\r\n"), Some("#chan".to_string()));
+    assert_eq!(raw_target(b"PART #chan\n"), Some("#chan".to_string()));
+    assert_eq!(raw_target(b"NOTICE me :hi\r\n"), Some("me".to_string()));
+
+    // an encoding set for a nick covers what they send us, not only what
+    // we send them
+    let mut config = EncodingConfig::new();
+    config.set_target("SomeNick", encoding_from_label("latin1").unwrap());
+    let line = b":somenick!u@h PRIVMSG me :caf\xe9\r\n";
+    let target = raw_target(line);
+    assert_eq!(config.decode(target.as_ref().map(|t| t.as_slice()), line).as_slice(), ":somenick!u@h PRIVMSG me :café\r\n");
+}
@@ -0,0 +1,154 @@
+use std::error;
+use std::error::{Error, FromError};
+use std::fmt::{FormatError, Formatter, Show};
+use std::io;
+
+use codec::{FrameError, LineTooLong, ReadError};
+use message;
+
+// everything that can go wrong while running a client. when run_loop
+// returns one of these, it's also the reason the loop stopped.
+pub enum IrcError {
+    // the line which couldn't be parsed (empty if it was too long to keep
+    // around), and what was wrong with it
+    ParseError(String, message::ParseError),
+    IoError(io::IoError),
+    // the server closed the connection without sending ERROR first
+    ConnectionClosed,
+    // we sent QUIT and the server closed the connection
+    ClientQuit,
+    // the numeric the server refused the registration with, and its text
+    RegistrationFailed(u16, String),
+    // the server sent ERROR, which means it's about to close the connection
+    ServerError(String),
+    // nothing was received for the configured ping timeout, even after
+    // sending a PING
+    PingTimeout,
+    // returned by a callback or handler
+    HandlerError(Box<error::Error + Send>),
+}
+
+pub type IrcResult<T> = Result<T, IrcError>;
+
+impl IrcError {
+    // wraps an error from user code so that it can be returned from a
+    // callback and come back out of run_loop
+    pub fn custom<E: error::Error + Send> (err: E) -> IrcError {
+        HandlerError(box err as Box<error::Error + Send>)
+    }
+
+    pub fn is_io_error (&self) -> bool {
+        match self {
+            &IoError(_) | &ConnectionClosed | &PingTimeout => true,
+            _ => false,
+        }
+    }
+}
+
+impl error::Error for IrcError {
+    fn description (&self) -> &str {
+        match self {
+            &ParseError(_, ref e) => e.description(),
+            &IoError(ref e) => e.desc,
+            &ConnectionClosed => "connection closed by the server",
+            &ClientQuit => "client quit",
+            &RegistrationFailed(..) => "registration failed",
+            &ServerError(..) => "server sent ERROR",
+            &PingTimeout => "ping timeout",
+            &HandlerError(ref e) => e.description(),
+        }
+    }
+
+    fn detail (&self) -> Option<String> {
+        match self {
+            &ParseError(ref line, ref e) => Some(format!("{} in {}", e, line)),
+            &IoError(ref e) => e.detail.clone(),
+            &RegistrationFailed(code, ref text) => Some(format!("{:03}: {}", code, text)),
+            &ServerError(ref text) => Some(text.clone()),
+            &HandlerError(ref e) => e.detail(),
+            _ => None,
+        }
+    }
+
+    fn cause (&self) -> Option<&error::Error> {
+        match self {
+            &ParseError(_, ref e) => Some(e as &error::Error),
+            &IoError(ref e) => Some(e as &error::Error),
+            &HandlerError(ref e) => Some(&**e),
+            _ => None,
+        }
+    }
+}
+
+impl Show for IrcError {
+    fn fmt (&self, f: &mut Formatter) -> Result<(), FormatError> {
+        match self.detail() {
+            Some(detail) => write!(f, "{}: {}", self.description(), detail),
+            None => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl FromError<io::IoError> for IrcError {
+    fn from_error (err: io::IoError) -> IrcError {
+        if err.kind == io::EndOfFile {
+            ConnectionClosed
+        }
+        else {
+            IoError(err)
+        }
+    }
+}
+
+impl FromError<FrameError> for IrcError {
+    fn from_error (err: FrameError) -> IrcError {
+        match err {
+            LineTooLong(len) => {
+                ParseError(
+                    String::new(),
+                    message::ParseError::new(message::MessageTooLong, len)
+                )
+            },
+            ReadError(e) => FromError::from_error(e),
+        }
+    }
+}
+
+#[test]
+fn test_from_error () {
+    let eof = io::IoError { kind: io::EndOfFile, desc: "end of file", detail: None };
+    match FromError::from_error(eof) {
+        ConnectionClosed => {},
+        e => panic!("unexpected {}", e),
+    }
+
+    let reset = io::IoError { kind: io::ConnectionReset, desc: "connection reset", detail: None };
+    match FromError::from_error(ReadError(reset)) {
+        IoError(e) => assert_eq!(e.kind, io::ConnectionReset),
+        e => panic!("unexpected {}", e),
+    }
+
+    match FromError::from_error(LineTooLong(9000)) {
+        ParseError(_, e) => {
+            assert_eq!(e.kind(), message::MessageTooLong);
+            assert_eq!(e.position(), 9000);
+        },
+        e => panic!("unexpected {}", e),
+    }
+}
+
+#[test]
+fn test_cause () {
+    let err = IrcError::custom(io::IoError {
+        kind: io::OtherIoError,
+        desc: "handler failed",
+        detail: Some("details".to_string()),
+    });
+    assert_eq!(err.description(), "handler failed");
+    assert_eq!(err.cause().map(|e| e.description()), Some("handler failed"));
+    assert_eq!(err.to_string().as_slice(), "handler failed: details");
+
+    let err = RegistrationFailed(433, "Nickname is already in use".to_string());
+    assert!(err.cause().is_none());
+    assert_eq!(err.to_string().as_slice(), "registration failed: 433: Nickname is already in use");
+}
@@ -0,0 +1,329 @@
+// an iterator over what happens on a connection, for callers which want to
+// run the loop themselves rather than handing it over to run_loop:
+//
+//   for event in client.events() {
+//       match event {
+//           Ok(PrivmsgEvent(from, receivers, text)) => ...,
+//           Ok(_) => {},
+//           Err(e) => println!("disconnected: {}", e),
+//       }
+//   }
+//
+// messages are decoded by the same ClientCallbacks::handle_message that
+// run_loop_with_callbacks uses, so pings, cap negotiation and ctcp requests
+// are still answered.
+
+use std::collections::{Deque, RingBuf};
+
+use chathistory::{HistoryBatch, HistoryError, HistoryTarget};
+use client::{Client, ClientCallbacks};
+use ctcp::Ctcp;
+use error;
+use error::{IrcError, IrcResult};
+use message;
+use message::{Message, MessageRef};
+use names::NameEntry;
+
+// from is the prefix of the message, if it had one
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum Event {
+    // from, receivers, text
+    PrivmsgEvent(Option<String>, Vec<String>, String),
+    ActionEvent(Option<String>, Vec<String>, String),
+    // from, target, text
+    NoticeEvent(Option<String>, String, String),
+    // from, receivers, request. the default response has already been sent.
+    CtcpRequestEvent(Option<String>, Vec<String>, Ctcp),
+    CtcpReplyEvent(Option<String>, String, Ctcp),
+    JoinEvent(Option<String>, Vec<String>),
+    PartEvent(Option<String>, Vec<String>),
+    // from, message
+    QuitEvent(Option<String>, Option<String>),
+    // from, new nick
+    NickEvent(Option<String>, String),
+    // from, channel, user, comment
+    KickEvent(Option<String>, String, String, Option<String>),
+    // from, channel, topic
+    TopicEvent(Option<String>, String, Option<String>),
+    // from, nickname, channel
+    InviteEvent(Option<String>, String, String),
+    // the server is about to close the connection
+    ErrorEvent(String),
+    // a line which couldn't be parsed, and was skipped
+    ParseErrorEvent(String, message::ParseError),
+    // channel, members
+    NamesEvent(String, Vec<NameEntry>),
+    ChatHistoryEvent(HistoryBatch),
+    ChatHistoryTargetsEvent(Vec<HistoryTarget>),
+    ChatHistoryFailEvent(HistoryError),
+    // every numeric
+    ReplyEvent(Message),
+    // commands which aren't covered by anything above
+    OtherEvent(Message),
+}
+
+pub struct Events<'a> {
+    client: &'a mut Client,
+    decoder: EventDecoder,
+    buf: Vec<u8>,
+    connected: bool,
+    finished: bool,
+    error: Option<IrcError>,
+}
+
+impl<'a> Events<'a> {
+    // registers with the server when the first event is asked for
+    pub fn new (client: &'a mut Client) -> Events<'a> {
+        Events {
+            client: client,
+            decoder: EventDecoder::new(),
+            buf: vec![],
+            connected: false,
+            finished: false,
+            error: None,
+        }
+    }
+
+    pub fn client (&mut self) -> &mut Client {
+        &mut *self.client
+    }
+}
+
+// yields the error which stopped the loop (the same one run_loop would
+// return) after any events from the message which caused it, and then ends
+impl<'a> Iterator<IrcResult<Event>> for Events<'a> {
+    fn next (&mut self) -> Option<IrcResult<Event>> {
+        if !self.connected {
+            self.connected = true;
+            match self.decoder.on_client_connect(&mut *self.client) {
+                Ok(()) => {},
+                Err(e) => {
+                    self.finished = true;
+                    self.error = Some(e);
+                },
+            }
+        }
+
+        loop {
+            match self.decoder.next_event() {
+                Some(event) => return Some(Ok(event)),
+                None => {},
+            }
+            match self.error.take() {
+                Some(e) => return Some(Err(e)),
+                None => {},
+            }
+            if self.finished {
+                return None;
+            }
+
+            let res = {
+                let decoder = &mut self.decoder;
+                self.client.handle_next(&mut self.buf, |client, m| decoder.handle(client, m))
+            };
+            let res = res.and_then(|()| self.decoder.check_parse_error(&mut *self.client));
+            match res {
+                Ok(()) => {},
+                Err(e) => {
+                    self.finished = true;
+                    self.error = Some(e);
+                },
+            }
+        }
+    }
+}
+
+// turns messages into events, for loops which are run somewhere other than
+// in Events
+pub struct EventDecoder {
+    events: RingBuf<Event>,
+    // on_command sees every command before the more specific callbacks do,
+    // and anything which is still here afterwards becomes an OtherEvent
+    unhandled: Option<Message>,
+}
+
+impl EventDecoder {
+    pub fn new () -> EventDecoder {
+        EventDecoder { events: RingBuf::new(), unhandled: None }
+    }
+
+    // the events for a message are queued up until next_event takes them
+    pub fn handle (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> {
+        self.unhandled = None;
+        try!(self.handle_message(client, m));
+        match self.unhandled.take() {
+            Some(m) => self.events.push_back(OtherEvent(m)),
+            None => {},
+        }
+        Ok(())
+    }
+
+    pub fn next_event (&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    fn push (&mut self, event: Event) -> IrcResult<()> {
+        self.unhandled = None;
+        self.events.push_back(event);
+        Ok(())
+    }
+}
+
+fn owned (from: Option<&str>) -> Option<String> {
+    from.map(|s| s.to_string())
+}
+
+fn owned_vec (strs: &[&str]) -> Vec<String> {
+    strs.iter().map(|s| s.to_string()).collect()
+}
+
+impl ClientCallbacks for EventDecoder {
+    fn on_command (&mut self, _client: &mut Client, m: &MessageRef) -> IrcResult<()> {
+        self.unhandled = Some(m.to_message());
+        Ok(())
+    }
+    fn on_reply (&mut self, _client: &mut Client, m: &MessageRef) -> IrcResult<()> {
+        self.push(ReplyEvent(m.to_message()))
+    }
+
+    fn on_privmsg (&mut self, _client: &mut Client, from: Option<&str>, receivers: &[&str], text: &str) -> IrcResult<()> {
+        self.push(PrivmsgEvent(owned(from), owned_vec(receivers), text.to_string()))
+    }
+    fn on_action (&mut self, _client: &mut Client, from: Option<&str>, receivers: &[&str], text: &str) -> IrcResult<()> {
+        self.push(ActionEvent(owned(from), owned_vec(receivers), text.to_string()))
+    }
+    fn on_notice (&mut self, _client: &mut Client, from: Option<&str>, nickname: &str, text: &str) -> IrcResult<()> {
+        self.push(NoticeEvent(owned(from), nickname.to_string(), text.to_string()))
+    }
+    fn on_ctcp_request (&mut self, client: &mut Client, from: Option<&str>, receivers: &[&str], ctcp: &Ctcp) -> IrcResult<()> {
+        match (from, client.ctcp_default_response(ctcp)) {
+            (Some(from), Some(response)) => {
+                let nick = from.split('!').next().unwrap();
+                try!(client.ctcp_reply(nick, response.command(), response.params()));
+            },
+            _ => {},
+        }
+        self.push(CtcpRequestEvent(owned(from), owned_vec(receivers), ctcp.clone()))
+    }
+    fn on_ctcp_reply (&mut self, _client: &mut Client, from: Option<&str>, nickname: &str, ctcp: &Ctcp) -> IrcResult<()> {
+        self.push(CtcpReplyEvent(owned(from), nickname.to_string(), ctcp.clone()))
+    }
+
+    fn on_join (&mut self, _client: &mut Client, from: Option<&str>, channels: &[&str], _keys: &[&str]) -> IrcResult<()> {
+        self.push(JoinEvent(owned(from), owned_vec(channels)))
+    }
+    fn on_part (&mut self, _client: &mut Client, from: Option<&str>, channels: &[&str]) -> IrcResult<()> {
+        self.push(PartEvent(owned(from), owned_vec(channels)))
+    }
+    fn on_quit (&mut self, _client: &mut Client, from: Option<&str>, msg: Option<&str>) -> IrcResult<()> {
+        self.push(QuitEvent(owned(from), owned(msg)))
+    }
+    fn on_nick (&mut self, _client: &mut Client, from: Option<&str>, nick: &str, _hopcount: Option<u32>) -> IrcResult<()> {
+        self.push(NickEvent(owned(from), nick.to_string()))
+    }
+    fn on_kick (&mut self, _client: &mut Client, from: Option<&str>, channel: &str, user: &str, comment: Option<&str>) -> IrcResult<()> {
+        self.push(KickEvent(owned(from), channel.to_string(), user.to_string(), owned(comment)))
+    }
+    fn on_topic (&mut self, _client: &mut Client, from: Option<&str>, channel: &str, topic: Option<&str>) -> IrcResult<()> {
+        self.push(TopicEvent(owned(from), channel.to_string(), owned(topic)))
+    }
+    fn on_invite (&mut self, _client: &mut Client, from: Option<&str>, nickname: &str, channel: &str) -> IrcResult<()> {
+        self.push(InviteEvent(owned(from), nickname.to_string(), channel.to_string()))
+    }
+    fn on_error (&mut self, _client: &mut Client, _from: Option<&str>, message: &str) -> IrcResult<()> {
+        self.push(ErrorEvent(message.to_string()))
+    }
+    fn on_parse_error (&mut self, _client: &mut Client, err: &IrcError) -> IrcResult<()> {
+        match err {
+            &error::ParseError(ref line, ref e) => self.push(ParseErrorEvent(line.clone(), e.clone())),
+            _ => Ok(()),
+        }
+    }
+
+    fn on_channel_names (&mut self, _client: &mut Client, channel: &str, members: &[NameEntry]) -> IrcResult<()> {
+        self.push(NamesEvent(channel.to_string(), members.to_vec()))
+    }
+    fn on_chathistory (&mut self, _client: &mut Client, batch: &HistoryBatch) -> IrcResult<()> {
+        self.push(ChatHistoryEvent(batch.clone()))
+    }
+    fn on_chathistory_targets (&mut self, _client: &mut Client, targets: &[HistoryTarget]) -> IrcResult<()> {
+        self.push(ChatHistoryTargetsEvent(targets.to_vec()))
+    }
+    fn on_chathistory_fail (&mut self, _client: &mut Client, err: &HistoryError) -> IrcResult<()> {
+        self.push(ChatHistoryFailEvent(err.clone()))
+    }
+}
+
+#[test]
+fn test_events () {
+    use std::io::{Acceptor, Listener, TcpListener};
+    use client::ClientBuilder;
+    use constants::{Ping, Reply, RPL_WELCOME};
+    use error::ServerError;
+
+    let mut listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+    let port = listener.socket_name().unwrap().port;
+    let mut acceptor = listener.listen().unwrap();
+
+    let (tx, rx) = channel();
+    spawn(proc() {
+        let mut conn = acceptor.accept().unwrap();
+        conn.write_str(concat!(
+            ":irc.host 001 me :Welcome\r\n",
+            ":nick!u@h PRIVMSG #chan :hi\r\n",
+            ":nick!u@h PR!VMSG #chan :hi\r\n",
+            "PING :irc.host\r\n",
+            ":nick!u@h PRIVMSG #chan :\x01ACTION waves\x01\r\n",
+            "ERROR :Closing link\r\n",
+        )).unwrap();
+        tx.send(String::from_utf8(conn.read_to_end().unwrap()).unwrap());
+    });
+
+    let mut builder = ClientBuilder::new("me", "127.0.0.1");
+    builder.set_port(port);
+    let mut client = builder.connect();
+    {
+        let mut events = client.events();
+        match events.next() {
+            Some(Ok(ReplyEvent(m))) => assert_eq!(*m.message_type(), Reply(RPL_WELCOME)),
+            e => panic!("unexpected {}", e),
+        }
+        match events.next() {
+            Some(Ok(e)) => {
+                assert_eq!(e, PrivmsgEvent(Some("nick!u@h".to_string()), vec!["#chan".to_string()], "hi".to_string()));
+            },
+            e => panic!("unexpected {}", e),
+        }
+        match events.next() {
+            Some(Ok(ParseErrorEvent(line, e))) => {
+                assert_eq!(line.as_slice(), ":nick!u@h PR!VMSG #chan :hi\r\n");
+                assert_eq!(e.kind(), message::InvalidCommand);
+            },
+            e => panic!("unexpected {}", e),
+        }
+        match events.next() {
+            Some(Ok(OtherEvent(m))) => assert_eq!(*m.message_type(), Ping),
+            e => panic!("unexpected {}", e),
+        }
+        match events.next() {
+            Some(Ok(e)) => {
+                assert_eq!(e, ActionEvent(Some("nick!u@h".to_string()), vec!["#chan".to_string()], "waves".to_string()));
+            },
+            e => panic!("unexpected {}", e),
+        }
+        match events.next() {
+            Some(Ok(e)) => assert_eq!(e, ErrorEvent("Closing link".to_string())),
+            e => panic!("unexpected {}", e),
+        }
+        match events.next() {
+            Some(Err(ServerError(text))) => assert_eq!(text.as_slice(), "Closing link"),
+            e => panic!("unexpected {}", e),
+        }
+        assert!(events.next().is_none());
+    }
+    drop(client);
+
+    // the ping was still answered
+    let sent = rx.recv();
+    assert!(sent.as_slice().contains("PONG irc.host\r\n"));
+}
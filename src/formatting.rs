@@ -0,0 +1,470 @@
+use std::cmp::min;
+
+pub static WHITE: u8 = 0;
+pub static BLACK: u8 = 1;
+pub static BLUE: u8 = 2;
+pub static GREEN: u8 = 3;
+pub static RED: u8 = 4;
+pub static BROWN: u8 = 5;
+pub static MAGENTA: u8 = 6;
+pub static ORANGE: u8 = 7;
+pub static YELLOW: u8 = 8;
+pub static LIGHT_GREEN: u8 = 9;
+pub static CYAN: u8 = 10;
+pub static LIGHT_CYAN: u8 = 11;
+pub static LIGHT_BLUE: u8 = 12;
+pub static PINK: u8 = 13;
+pub static GREY: u8 = 14;
+pub static LIGHT_GREY: u8 = 15;
+pub static DEFAULT_COLOR: u8 = 99;
+
+static BOLD_CODE: char = '\x02';
+static COLOR_CODE: char = '\x03';
+static HEX_COLOR_CODE: char = '\x04';
+static RESET_CODE: char = '\x0F';
+static MONOSPACE_CODE: char = '\x11';
+static REVERSE_CODE: char = '\x16';
+static ITALIC_CODE: char = '\x1D';
+static STRIKETHROUGH_CODE: char = '\x1E';
+static UNDERLINE_CODE: char = '\x1F';
+
+// rgb values for the 99 colors that mirc defines
+static PALETTE: [u32, ..99] = [
+    0xffffff, 0x000000, 0x00007f, 0x009300, 0xff0000, 0x7f0000, 0x9c009c, 0xfc7f00,
+    0xffff00, 0x00fc00, 0x009393, 0x00ffff, 0x0000fc, 0xff00ff, 0x7f7f7f, 0xd2d2d2,
+    0x470000, 0x472100, 0x474700, 0x324700, 0x004700, 0x00472c, 0x004747, 0x002747,
+    0x000047, 0x2e0047, 0x470047, 0x47002a, 0x740000, 0x743a00, 0x747400, 0x517400,
+    0x007400, 0x007449, 0x007474, 0x004074, 0x000074, 0x4b0074, 0x740074, 0x740045,
+    0xb50000, 0xb56300, 0xb5b500, 0x7db500, 0x00b500, 0x00b571, 0x00b5b5, 0x0063b5,
+    0x0000b5, 0x7500b5, 0xb500b5, 0xb5006b, 0xff0000, 0xff8c00, 0xffff00, 0xb2ff00,
+    0x00ff00, 0x00ffa0, 0x00ffff, 0x008cff, 0x0000ff, 0xa500ff, 0xff00ff, 0xff0098,
+    0xff5959, 0xffb459, 0xffff71, 0xcfff60, 0x6fff6f, 0x65ffc9, 0x6dffff, 0x59b4ff,
+    0x5959ff, 0xc459ff, 0xff66ff, 0xff59bc, 0xff9c9c, 0xffd39c, 0xffff9c, 0xe2ff9c,
+    0x9cff9c, 0x9cffdb, 0x9cffff, 0x9cd3ff, 0x9c9cff, 0xdc9cff, 0xff9cff, 0xff94d3,
+    0x000000, 0x131313, 0x282828, 0x363636, 0x4d4d4d, 0x656565, 0x818181, 0x9f9f9f,
+    0xbcbcbc, 0xe2e2e2, 0xffffff,
+];
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum Color {
+    PaletteColor(u8),
+    RgbColor(u8, u8, u8),
+}
+
+impl Color {
+    pub fn rgb (&self) -> (u8, u8, u8) {
+        match *self {
+            PaletteColor(i) => {
+                let rgb = PALETTE[i as uint % PALETTE.len()];
+                ((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+            },
+            RgbColor(r, g, b) => (r, g, b),
+        }
+    }
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub monospace: bool,
+    pub reverse: bool,
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+}
+
+impl Style {
+    pub fn new () -> Style {
+        Style {
+            bold: false,
+            italic: false,
+            underline: false,
+            strikethrough: false,
+            monospace: false,
+            reverse: false,
+            fg: None,
+            bg: None,
+        }
+    }
+
+    pub fn is_plain (&self) -> bool {
+        *self == Style::new()
+    }
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub struct Span {
+    pub style: Style,
+    pub text: String,
+}
+
+pub fn parse (text: &str) -> Vec<Span> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans = vec![];
+    let mut style = Style::new();
+    let mut current = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+
+        let is_code = c == BOLD_CODE || c == COLOR_CODE || c == HEX_COLOR_CODE
+            || c == RESET_CODE || c == MONOSPACE_CODE || c == REVERSE_CODE
+            || c == ITALIC_CODE || c == STRIKETHROUGH_CODE || c == UNDERLINE_CODE;
+        if !is_code {
+            current.push(c);
+            continue;
+        }
+
+        if current.len() > 0 {
+            spans.push(Span { style: style.clone(), text: current });
+            current = String::new();
+        }
+
+        if c == BOLD_CODE {
+            style.bold = !style.bold;
+        }
+        else if c == ITALIC_CODE {
+            style.italic = !style.italic;
+        }
+        else if c == UNDERLINE_CODE {
+            style.underline = !style.underline;
+        }
+        else if c == STRIKETHROUGH_CODE {
+            style.strikethrough = !style.strikethrough;
+        }
+        else if c == MONOSPACE_CODE {
+            style.monospace = !style.monospace;
+        }
+        else if c == REVERSE_CODE {
+            style.reverse = !style.reverse;
+        }
+        else if c == RESET_CODE {
+            style = Style::new();
+        }
+        else if c == COLOR_CODE {
+            match read_palette_color(chars.as_slice(), &mut i) {
+                Some(fg) => {
+                    style.fg = to_color(fg);
+                    if i + 1 < chars.len() && chars[i] == ',' && chars[i + 1].is_digit() {
+                        i += 1;
+                        style.bg = read_palette_color(chars.as_slice(), &mut i).and_then(to_color);
+                    }
+                },
+                None => {
+                    style.fg = None;
+                    style.bg = None;
+                },
+            }
+        }
+        else if c == HEX_COLOR_CODE {
+            match read_hex_color(chars.as_slice(), &mut i) {
+                Some(fg) => {
+                    style.fg = Some(fg);
+                    if i < chars.len() && chars[i] == ',' {
+                        i += 1;
+                        let start = i;
+                        match read_hex_color(chars.as_slice(), &mut i) {
+                            Some(bg) => style.bg = Some(bg),
+                            // not a color after all, so the comma is text
+                            None => i = start - 1,
+                        }
+                    }
+                },
+                None => {
+                    style.fg = None;
+                    style.bg = None;
+                },
+            }
+        }
+    }
+
+    if current.len() > 0 {
+        spans.push(Span { style: style, text: current });
+    }
+
+    spans
+}
+
+pub fn strip (text: &str) -> String {
+    let mut ret = String::new();
+    for span in parse(text).iter() {
+        ret.push_str(span.text.as_slice());
+    }
+    ret
+}
+
+pub fn to_ansi (text: &str) -> String {
+    let mut ret = String::new();
+    for span in parse(text).iter() {
+        if span.style.is_plain() {
+            ret.push_str(span.text.as_slice());
+            continue;
+        }
+
+        let mut codes = vec![];
+        if span.style.bold { codes.push("1".to_string()) }
+        if span.style.italic { codes.push("3".to_string()) }
+        if span.style.underline { codes.push("4".to_string()) }
+        if span.style.reverse { codes.push("7".to_string()) }
+        if span.style.strikethrough { codes.push("9".to_string()) }
+        match span.style.fg {
+            Some(ref fg) => {
+                let (r, g, b) = fg.rgb();
+                codes.push(format!("38;2;{};{};{}", r, g, b));
+            },
+            None => {},
+        }
+        match span.style.bg {
+            Some(ref bg) => {
+                let (r, g, b) = bg.rgb();
+                codes.push(format!("48;2;{};{};{}", r, g, b));
+            },
+            None => {},
+        }
+
+        ret.push_str(format!("\x1b[{}m{}\x1b[0m", codes.connect(";"), span.text).as_slice());
+    }
+    ret
+}
+
+pub fn to_html (text: &str) -> String {
+    let mut ret = String::new();
+    for span in parse(text).iter() {
+        let text = escape_html(span.text.as_slice());
+        if span.style.is_plain() {
+            ret.push_str(text.as_slice());
+            continue;
+        }
+
+        let (fg, bg) = if span.style.reverse {
+            (span.style.bg.clone().or(Some(PaletteColor(WHITE))), span.style.fg.clone().or(Some(PaletteColor(BLACK))))
+        }
+        else {
+            (span.style.fg.clone(), span.style.bg.clone())
+        };
+
+        let mut css = vec![];
+        if span.style.bold { css.push("font-weight: bold".to_string()) }
+        if span.style.italic { css.push("font-style: italic".to_string()) }
+        match (span.style.underline, span.style.strikethrough) {
+            (true, true) => css.push("text-decoration: underline line-through".to_string()),
+            (true, false) => css.push("text-decoration: underline".to_string()),
+            (false, true) => css.push("text-decoration: line-through".to_string()),
+            (false, false) => {},
+        }
+        if span.style.monospace { css.push("font-family: monospace".to_string()) }
+        match fg {
+            Some(fg) => {
+                let (r, g, b) = fg.rgb();
+                css.push(format!("color: #{:02x}{:02x}{:02x}", r, g, b));
+            },
+            None => {},
+        }
+        match bg {
+            Some(bg) => {
+                let (r, g, b) = bg.rgb();
+                css.push(format!("background-color: #{:02x}{:02x}{:02x}", r, g, b));
+            },
+            None => {},
+        }
+
+        ret.push_str(format!("<span style=\"{}\">{}</span>", css.connect("; "), text).as_slice());
+    }
+    ret
+}
+
+pub struct TextBuilder {
+    text: String,
+}
+
+impl TextBuilder {
+    pub fn new () -> TextBuilder {
+        TextBuilder { text: String::new() }
+    }
+
+    pub fn text (&mut self, text: &str) -> &mut TextBuilder {
+        self.text.push_str(text);
+        self
+    }
+
+    pub fn bold (&mut self, text: &str) -> &mut TextBuilder {
+        self.wrap(BOLD_CODE, text)
+    }
+
+    pub fn italic (&mut self, text: &str) -> &mut TextBuilder {
+        self.wrap(ITALIC_CODE, text)
+    }
+
+    pub fn underline (&mut self, text: &str) -> &mut TextBuilder {
+        self.wrap(UNDERLINE_CODE, text)
+    }
+
+    pub fn strikethrough (&mut self, text: &str) -> &mut TextBuilder {
+        self.wrap(STRIKETHROUGH_CODE, text)
+    }
+
+    pub fn monospace (&mut self, text: &str) -> &mut TextBuilder {
+        self.wrap(MONOSPACE_CODE, text)
+    }
+
+    pub fn reverse (&mut self, text: &str) -> &mut TextBuilder {
+        self.wrap(REVERSE_CODE, text)
+    }
+
+    // colors only go up to 99, and anything higher is clamped to 99 (the
+    // default color) since a third digit would end up in the text
+    pub fn color (&mut self, fg: u8, bg: Option<u8>, text: &str) -> &mut TextBuilder {
+        // always use two digits, so text starting with a digit isn't
+        // mistaken for part of the color
+        self.text.push(COLOR_CODE);
+        self.text.push_str(format!("{:02}", min(fg, 99)).as_slice());
+        match bg {
+            Some(bg) => self.text.push_str(format!(",{:02}", min(bg, 99)).as_slice()),
+            None => {},
+        }
+        self.push_after_color(text, bg.is_none());
+        self.text.push(COLOR_CODE);
+        self
+    }
+
+    pub fn hex_color (&mut self, fg: (u8, u8, u8), bg: Option<(u8, u8, u8)>, text: &str) -> &mut TextBuilder {
+        let (r, g, b) = fg;
+        self.text.push(HEX_COLOR_CODE);
+        self.text.push_str(format!("{:02X}{:02X}{:02X}", r, g, b).as_slice());
+        match bg {
+            Some((r, g, b)) => self.text.push_str(format!(",{:02X}{:02X}{:02X}", r, g, b).as_slice()),
+            None => {},
+        }
+        self.push_after_color(text, bg.is_none());
+        self.text.push(HEX_COLOR_CODE);
+        self
+    }
+
+    pub fn build (&self) -> String {
+        self.text.clone()
+    }
+
+    fn wrap (&mut self, code: char, text: &str) -> &mut TextBuilder {
+        self.text.push(code);
+        self.text.push_str(text);
+        self.text.push(code);
+        self
+    }
+
+    // a comma right after a foreground color would be read as the start of
+    // a background color, so separate them with an empty bold toggle
+    fn push_after_color (&mut self, text: &str, no_bg: bool) {
+        if no_bg && text.starts_with(",") {
+            self.text.push(BOLD_CODE);
+            self.text.push(BOLD_CODE);
+        }
+        self.text.push_str(text);
+    }
+}
+
+fn read_palette_color (chars: &[char], i: &mut uint) -> Option<u8> {
+    let start = *i;
+    while *i < chars.len() && *i - start < 2 && chars[*i].is_digit() {
+        *i += 1;
+    }
+    if *i == start {
+        return None;
+    }
+    let digits: String = chars.slice(start, *i).iter().map(|&c| c).collect();
+    from_str(digits.as_slice())
+}
+
+fn read_hex_color (chars: &[char], i: &mut uint) -> Option<Color> {
+    if *i + 6 > chars.len() {
+        return None;
+    }
+    let digits: String = chars.slice(*i, *i + 6).iter().map(|&c| c).collect();
+    match ::std::num::from_str_radix::<u32>(digits.as_slice(), 16) {
+        Some(rgb) => {
+            *i += 6;
+            Some(RgbColor((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+        },
+        None => None,
+    }
+}
+
+fn to_color (i: u8) -> Option<Color> {
+    if i == DEFAULT_COLOR { None } else { Some(PaletteColor(i)) }
+}
+
+fn escape_html (text: &str) -> String {
+    let mut ret = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => ret.push_str("&amp;"),
+            '<' => ret.push_str("&lt;"),
+            '>' => ret.push_str("&gt;"),
+            '"' => ret.push_str("&quot;"),
+            '\'' => ret.push_str("&#39;"),
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+#[test]
+fn test_parse () {
+    let spans = parse("plain \x02bold\x02 \x0304,02red on blue\x03 \x1Ditalic\x0F done");
+    assert_eq!(spans.len(), 7);
+    assert_eq!(spans[0], Span { style: Style::new(), text: "plain ".to_string() });
+    assert!(spans[1].style.bold);
+    assert_eq!(spans[1].text.as_slice(), "bold");
+    assert_eq!(spans[3].style.fg, Some(PaletteColor(RED)));
+    assert_eq!(spans[3].style.bg, Some(PaletteColor(BLUE)));
+    assert_eq!(spans[3].text.as_slice(), "red on blue");
+    assert_eq!(spans[4].style, Style::new());
+    assert!(spans[5].style.italic);
+    assert_eq!(spans[6].style, Style::new());
+
+    let spans = parse("\x043fa9c2,000000hex\x04, x");
+    assert_eq!(spans[0].style.fg, Some(RgbColor(0x3f, 0xa9, 0xc2)));
+    assert_eq!(spans[0].style.bg, Some(RgbColor(0, 0, 0)));
+    assert_eq!(spans[1].text.as_slice(), ", x");
+
+    let spans = parse("\x034,text");
+    assert_eq!(spans[0].style.fg, Some(PaletteColor(RED)));
+    assert_eq!(spans[0].style.bg, None);
+    assert_eq!(spans[0].text.as_slice(), ",text");
+}
+
+#[test]
+fn test_strip () {
+    assert_eq!(strip("\x02\x0312,4!karma\x0F \x1Ffoo\x1F\x16").as_slice(), "!karma foo");
+    assert_eq!(strip("\x03\x0399nothing").as_slice(), "nothing");
+    assert_eq!(strip("no codes").as_slice(), "no codes");
+}
+
+#[test]
+fn test_render () {
+    assert_eq!(to_ansi("a \x02b\x02").as_slice(), "a \x1b[1mb\x1b[0m");
+    assert_eq!(
+        to_html("<\x0304red\x03>").as_slice(),
+        "&lt;<span style=\"color: #ff0000\">red</span>&gt;"
+    );
+}
+
+#[test]
+fn test_builder () {
+    let text = TextBuilder::new()
+        .text("build ")
+        .bold("passed")
+        .text(": ")
+        .color(GREEN, None, "100")
+        .color(RED, Some(BLACK), ",0")
+        .build();
+    assert_eq!(text.as_slice(), "build \x02passed\x02: \x0303100\x03\x0304,01,0\x03");
+    assert_eq!(strip(text.as_slice()).as_slice(), "build passed: 100,0");
+
+    let text = TextBuilder::new().color(150, Some(255), "5").build();
+    assert_eq!(text.as_slice(), "\x0399,995\x03");
+    assert_eq!(strip(text.as_slice()).as_slice(), "5");
+}
@@ -0,0 +1,85 @@
+// entry points for fuzzing, built with the fuzz feature. each one takes
+// arbitrary bytes and panics if it finds a bug. fuzz/ has a driver which
+// feeds them mutated server traffic.
+
+use std::io::{Acceptor, Listener, TcpListener};
+
+use client::{ClientBuilder, ClientCallbacks};
+use codec::LineCodec;
+use encoding::{EncodingConfig, raw_target};
+use message::{Message, MessageRef, Strict, Lenient};
+
+// parsing, and everything that slices into a parsed line
+pub fn parse (data: &[u8]) {
+    let text = String::from_utf8_lossy(data);
+    let text = text.as_slice();
+    for &mode in [Strict, Lenient].iter() {
+        match MessageRef::parse_with_mode(text, mode) {
+            Ok(m) => {
+                let owned = m.to_message();
+                for (key, _) in m.tags() {
+                    assert_eq!(
+                        m.tag_value(key).map(|v| v.as_slice().to_string()),
+                        owned.tag_value(key).map(|v| v.to_string())
+                    );
+                }
+                assert_eq!(m.from(), owned.from().as_ref().map(|s| s.as_slice()));
+                assert_eq!(m.message_type(), owned.message_type());
+                assert_eq!(m.params().len(), owned.params().len());
+            },
+            Err(e) => assert!(e.position() <= text.len()),
+        }
+    }
+
+    let _ = raw_target(data);
+    let _ = EncodingConfig::new().decode(None, data);
+
+    let mut codec = LineCodec::with_tags();
+    let mut line = vec![];
+    codec.feed(data);
+    while codec.decode(&mut line).is_some() {}
+}
+
+// anything which parses and is valid has to serialize to something which
+// parses back to the same message
+pub fn round_trip (data: &[u8]) {
+    let text = String::from_utf8_lossy(data);
+    for &mode in [Strict, Lenient].iter() {
+        let m = match Message::parse_with_mode(text.as_slice(), mode) {
+            Ok(m) => m,
+            Err(_) => continue,
+        };
+        if m.validate().is_err() {
+            continue;
+        }
+        let line = m.to_protocol_string();
+        assert_eq!(Message::parse_with_mode(line.as_slice(), Strict), Ok(m.clone()));
+        assert!(m.sanitize().validate().is_ok());
+    }
+}
+
+struct NullCallbacks;
+impl ClientCallbacks for NullCallbacks {}
+
+// runs the whole callback dispatch over data, as if a server had sent it
+pub fn dispatch (data: &[u8]) {
+    let mut listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+    let port = listener.socket_name().unwrap().port;
+    let mut acceptor = listener.listen().unwrap();
+
+    let data = data.to_vec();
+    spawn(proc() {
+        let mut conn = acceptor.accept().unwrap();
+        let _ = conn.write(data.as_slice());
+        let _ = conn.write(b"\r\n");
+        let _ = conn.close_write();
+        // wait for the client to hang up, so that nothing it sends gets
+        // the connection reset before it has read everything
+        let _ = conn.read_to_end();
+    });
+
+    let mut builder = ClientBuilder::new("fuzz", "127.0.0.1");
+    builder.set_port(port);
+    let client = builder.connect();
+    let _ = client.run_loop_with_callbacks(NullCallbacks);
+}
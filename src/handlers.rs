@@ -0,0 +1,212 @@
+// a registry of closures to run for incoming messages, for bots which don't
+// need a whole ClientCallbacks impl. Handlers implements ClientCallbacks
+// itself, so the default behavior (answering pings, cap negotiation, ctcp
+// replies) still happens:
+//
+//   let mut handlers = Handlers::new();
+//   handlers.on_numeric(RPL_WELCOME, box |&mut: _: &mut Handlers, client: &mut Client, _: &Message| {
+//       Ok(try!(client.join(["#chan"], [])))
+//   });
+//   client.run_loop_with_callbacks(handlers);
+
+use std::ascii::AsciiExt;
+
+use client::{Client, ClientCallbacks};
+use constants::{MessageType, Privmsg, Reply};
+use ctcp::Ctcp;
+use error::IrcResult;
+use message::{IntoMessageType, Message, MessageRef};
+
+pub type HandlerId = uint;
+
+// handlers get the registry they're in, so that they can add and remove
+// handlers (including themselves) while the client is running
+pub type Handler = Box<FnMut(&mut Handlers, &mut Client, &Message) -> IrcResult<()> + 'static>;
+
+pub enum HandlerFilter {
+    AnyMessage,
+    // numerics are matched with Reply
+    CommandIs(MessageType),
+    // privmsgs containing a ctcp request of this type, ignoring case
+    CtcpIs(String),
+    Matching(Box<Fn(&Message) -> bool + 'static>),
+}
+
+impl HandlerFilter {
+    pub fn matches (&self, m: &Message) -> bool {
+        match self {
+            &AnyMessage => true,
+            &CommandIs(ref message_type) => m.message_type() == message_type,
+            &CtcpIs(ref ctcp_type) => {
+                if *m.message_type() != Privmsg {
+                    return false;
+                }
+                match m.params().get(1).and_then(|text| Ctcp::parse(text.as_slice())) {
+                    Some(ctcp) => ctcp.command().eq_ignore_ascii_case(ctcp_type.as_slice()),
+                    None => false,
+                }
+            },
+            &Matching(ref predicate) => predicate.call((m,)),
+        }
+    }
+}
+
+struct Entry {
+    id: HandlerId,
+    priority: int,
+    filter: HandlerFilter,
+    // taken out while the handler is running
+    handler: Option<Handler>,
+}
+
+pub struct Handlers {
+    entries: Vec<Entry>,
+    next_id: HandlerId,
+    current: Option<HandlerId>,
+}
+
+impl Handlers {
+    pub fn new () -> Handlers {
+        Handlers { entries: vec![], next_id: 0, current: None }
+    }
+
+    // handlers run in order of priority, lowest first, and then in the order
+    // they were added
+    pub fn add (&mut self, filter: HandlerFilter, priority: int, handler: Handler) -> HandlerId {
+        let id = self.next_id;
+        self.next_id += 1;
+        let idx = self.entries.iter().position(|e| e.priority > priority).unwrap_or(self.entries.len());
+        self.entries.insert(idx, Entry {
+            id: id,
+            priority: priority,
+            filter: filter,
+            handler: Some(handler),
+        });
+        id
+    }
+
+    pub fn on_any (&mut self, handler: Handler) -> HandlerId {
+        self.add(AnyMessage, 0, handler)
+    }
+    pub fn on_command<C: IntoMessageType> (&mut self, command: C, handler: Handler) -> HandlerId {
+        self.add(CommandIs(command.into_message_type()), 0, handler)
+    }
+    pub fn on_numeric (&mut self, code: u16, handler: Handler) -> HandlerId {
+        self.add(CommandIs(Reply(code)), 0, handler)
+    }
+    pub fn on_ctcp (&mut self, ctcp_type: &str, handler: Handler) -> HandlerId {
+        self.add(CtcpIs(ctcp_type.to_string()), 0, handler)
+    }
+    pub fn on_match (&mut self, predicate: Box<Fn(&Message) -> bool + 'static>, handler: Handler) -> HandlerId {
+        self.add(Matching(predicate), 0, handler)
+    }
+
+    // returns false if there was no handler with this id
+    pub fn remove (&mut self, id: HandlerId) -> bool {
+        match self.entries.iter().position(|e| e.id == id) {
+            Some(idx) => {
+                self.entries.remove(idx);
+                true
+            },
+            None => false,
+        }
+    }
+
+    // the id of the handler which is running right now
+    pub fn current (&self) -> Option<HandlerId> {
+        self.current
+    }
+
+    pub fn len (&self) -> uint {
+        self.entries.len()
+    }
+
+    // runs each handler which matches the message, stopping at the first
+    // error. handlers removed while this runs are skipped if they haven't
+    // run yet, and handlers added while this runs wait for the next message.
+    pub fn dispatch (&mut self, client: &mut Client, m: &Message) -> IrcResult<()> {
+        let ids: Vec<HandlerId> = self.entries.iter()
+            .filter(|e| e.filter.matches(m))
+            .map(|e| e.id)
+            .collect();
+
+        for &id in ids.iter() {
+            let handler = self.entries.iter_mut()
+                .find(|e| e.id == id)
+                .and_then(|e| e.handler.take());
+            let mut handler = match handler {
+                Some(handler) => handler,
+                None => continue,
+            };
+
+            self.current = Some(id);
+            let res = handler.call_mut((&mut *self, &mut *client, m));
+            self.current = None;
+
+            match self.entries.iter_mut().find(|e| e.id == id) {
+                Some(e) => e.handler = Some(handler),
+                None => {},
+            }
+            try!(res);
+        }
+
+        Ok(())
+    }
+}
+
+impl ClientCallbacks for Handlers {
+    fn on_any_message (&mut self, client: &mut Client, m: &MessageRef) -> IrcResult<()> {
+        if self.entries.len() == 0 {
+            return Ok(());
+        }
+        self.dispatch(client, &m.to_message())
+    }
+}
+
+#[test]
+fn test_handler_order () {
+    fn noop () -> Handler {
+        box |&mut: _: &mut Handlers, _: &mut Client, _: &Message| Ok(())
+    }
+
+    let mut handlers = Handlers::new();
+    let a = handlers.add(AnyMessage, 0, noop());
+    let b = handlers.add(AnyMessage, -10, noop());
+    let c = handlers.add(AnyMessage, 0, noop());
+    let d = handlers.add(AnyMessage, 5, noop());
+    let ids: Vec<HandlerId> = handlers.entries.iter().map(|e| e.id).collect();
+    assert_eq!(ids, vec![b, a, c, d]);
+
+    assert!(handlers.remove(a));
+    assert!(!handlers.remove(a));
+    let ids: Vec<HandlerId> = handlers.entries.iter().map(|e| e.id).collect();
+    assert_eq!(ids, vec![b, c, d]);
+    assert_eq!(handlers.len(), 3);
+}
+
+#[test]
+fn test_handler_filters () {
+    use constants::{Notice, RPL_WELCOME};
+
+    let welcome = Message::parse(":irc.host 001 me :Welcome\r\n").unwrap();
+    let privmsg = Message::parse(":nick!u@h PRIVMSG me :hello\r\n").unwrap();
+    let version = Message::parse(":nick!u@h PRIVMSG me :\x01version\x01\r\n").unwrap();
+    let reply = Message::parse(":nick!u@h NOTICE me :\x01VERSION irc\x01\r\n").unwrap();
+
+    assert!(AnyMessage.matches(&welcome));
+    assert!(CommandIs(Reply(RPL_WELCOME)).matches(&welcome));
+    assert!(!CommandIs(Reply(RPL_WELCOME)).matches(&privmsg));
+    assert!(CommandIs("privmsg".into_message_type()).matches(&privmsg));
+    assert!(CommandIs(Notice).matches(&reply));
+
+    let ctcp = CtcpIs("VERSION".to_string());
+    assert!(ctcp.matches(&version));
+    assert!(!ctcp.matches(&privmsg));
+    assert!(!ctcp.matches(&reply));
+
+    let from_nick = Matching(box |&: m: &Message| {
+        m.from().as_ref().map(|f| f.as_slice().starts_with("nick!")).unwrap_or(false)
+    });
+    assert!(from_nick.matches(&privmsg));
+    assert!(!from_nick.matches(&welcome));
+}
@@ -1,11 +1,31 @@
-#![feature(phase, globs)]
+#![feature(globs, unboxed_closures)]
 
-#[phase(plugin)] extern crate regex_macros;
-extern crate regex;
+extern crate time;
+#[cfg(feature = "ssl")] extern crate openssl;
+#[cfg(feature = "serde")] extern crate serialize;
 
-pub use client::{Client, ClientBuilder, ClientCallbacks};
-pub use message::Message;
+pub use client::{Client, ClientBuilder, ClientCallbacks, ClientSender};
+pub use error::{IrcError, IrcResult};
+pub use handlers::Handlers;
+pub use message::{Message, MessageBuilder, MessageRef, RawMessage};
 
+#[cfg(feature = "threaded")] pub mod threaded_client;
+pub mod chathistory;
 pub mod client;
+pub mod codec;
 pub mod constants;
+pub mod ctcp;
+pub mod dcc;
+pub mod encoding;
+pub mod error;
+pub mod events;
+pub mod formatting;
+#[cfg(feature = "fuzz")] pub mod fuzz;
+pub mod handlers;
 pub mod message;
+pub mod names;
+pub mod net;
+pub mod numerics;
+#[cfg(feature = "serde")] pub mod serialization;
+pub mod standard_reply;
+pub mod sts;
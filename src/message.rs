@@ -1,9 +1,91 @@
-use constants::{MessageType, MAX_MESSAGE_LENGTH};
+use constants::{MessageType, RawCommand, Reply, MAX_MESSAGE_LENGTH, MAX_TAGS_LENGTH, MAX_PARAMS};
+use encoding::{EncodingConfig, raw_target};
 
+use std::ascii::AsciiExt;
+use std::error::Error;
+use std::fmt::{FormatError, Formatter, Show};
 use std::io;
+use std::str::{MaybeOwned, Slice, Owned};
 
-#[deriving(PartialEq, Eq, Show)]
+pub type Tag = (String, Option<String>);
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum ParseMode {
+    // only accepts messages as rfc 2812 (plus ircv3 message tags) describes
+    // them: terminated by \r\n, within the length limits, with a command of
+    // letters or three digits, and a single space between each part
+    Strict,
+    // accepts what servers actually send: lines ending in \n or with no
+    // line ending at all, runs of spaces between parameters, trailing
+    // spaces, and lines over the length limits
+    Lenient,
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum ParseErrorKind {
+    MissingLineEnding,
+    InvalidCharacter,
+    MessageTooLong,
+    TagsTooLong,
+    UnexpectedSpace,
+    EmptyPrefix,
+    MissingCommand,
+    InvalidCommand,
+}
+
+#[deriving(PartialEq, Eq, Clone)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    position: uint,
+}
+
+impl ParseError {
+    pub fn new (kind: ParseErrorKind, position: uint) -> ParseError {
+        ParseError { kind: kind, position: position }
+    }
+
+    pub fn kind (&self) -> ParseErrorKind {
+        self.kind
+    }
+
+    // the byte offset in the line where parsing failed
+    pub fn position (&self) -> uint {
+        self.position
+    }
+
+    pub fn description (&self) -> &'static str {
+        match self.kind {
+            MissingLineEnding => "message not terminated by \\r\\n",
+            InvalidCharacter => "message contains NUL, CR, or LF",
+            MessageTooLong => "message too long",
+            TagsTooLong => "message tags too long",
+            UnexpectedSpace => "unexpected space",
+            EmptyPrefix => "empty prefix",
+            MissingCommand => "missing command",
+            InvalidCommand => "invalid command",
+        }
+    }
+}
+
+impl Show for ParseError {
+    fn fmt (&self, f: &mut Formatter) -> Result<(), FormatError> {
+        write!(f, "{} at position {}", self.description(), self.position)
+    }
+}
+
+impl Error for ParseError {
+    fn description (&self) -> &str {
+        ParseError::description(self)
+    }
+
+    fn detail (&self) -> Option<String> {
+        Some(format!("at position {}", self.position))
+    }
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
 pub struct Message {
+    tags: Vec<Tag>,
     from: Option<String>,
     message_type: MessageType,
     params: Vec<String>,
@@ -11,30 +93,42 @@ pub struct Message {
 
 impl Message {
     pub fn new (from: Option<String>, message_type: MessageType, params: Vec<String>) -> Message {
-        Message { from: from, message_type: message_type, params: params }
+        Message::new_with_tags(vec![], from, message_type, params)
     }
 
-    pub fn parse (msg: &str) -> Result<Message, &'static str> {
-        if msg.len() > MAX_MESSAGE_LENGTH {
-            return Err("message too long");
-        }
+    pub fn new_with_tags (tags: Vec<Tag>, from: Option<String>, message_type: MessageType, params: Vec<String>) -> Message {
+        // an empty value can't be told apart from a missing one on the wire
+        let tags = tags.into_iter().map(|(k, v)| {
+            (k, v.and_then(|v| if v.len() > 0 { Some(v) } else { None }))
+        }).collect();
+        Message { tags: tags, from: from, message_type: message_type, params: params }
+    }
 
-        let message_parser = regex!(r"^(?::([^ ]+) )?([A-Z]+|[0-9]{3}) ([^\r\n\0]*)\r\n$");
-        match message_parser.captures(msg) {
-            Some(captures) => {
-                let from = captures.at(1);
-                let from = if from.len() > 0 { Some(from.to_string()) } else { None };
-                let command = captures.at(2);
+    pub fn parse (msg: &str) -> Result<Message, ParseError> {
+        Message::parse_with_mode(msg, Lenient)
+    }
 
-                let params = Message::parse_params(captures.at(3));
+    pub fn parse_with_mode (msg: &str, mode: ParseMode) -> Result<Message, ParseError> {
+        MessageRef::parse_with_mode(msg, mode).map(|m| m.to_message())
+    }
 
-                match from_str(command) {
-                    Some(c) => Ok(Message::new(from, c, params)),
-                    None => Err("command parsing failed"),
-                }
-            },
-            None => Err("message parsing failed"),
+    pub fn tags (&self) -> &Vec<Tag> {
+        &self.tags
+    }
+
+    pub fn has_tag (&self, key: &str) -> bool {
+        self.tags.iter().any(|&(ref k, _)| k.as_slice() == key)
+    }
+
+    // if a tag is repeated, the last value wins
+    pub fn tag_value (&self, key: &str) -> Option<&str> {
+        let mut value = None;
+        for &(ref k, ref v) in self.tags.iter() {
+            if k.as_slice() == key {
+                value = v.as_ref().map(|s| s.as_slice());
+            }
         }
+        value
     }
 
     pub fn from (&self) -> &Option<String> {
@@ -53,74 +147,708 @@ impl Message {
         self.message_type.is_reply()
     }
 
+    // checks that the message can be written out in a way that will parse
+    // back into the same message
+    pub fn validate (&self) -> Result<(), &'static str> {
+        for &(ref k, _) in self.tags.iter() {
+            if k.len() == 0 || k.as_slice().chars().any(|c| c == ' ' || c == ';' || c == '=' || is_forbidden(c)) {
+                return Err("invalid tag name");
+            }
+        }
+
+        match self.from {
+            Some(ref f) if f.len() == 0 || f.as_slice().chars().any(|c| c == ' ' || is_forbidden(c)) => {
+                return Err("invalid prefix");
+            },
+            _ => {},
+        }
+
+        match self.message_type {
+            RawCommand(ref c) if parse_command(c.as_slice(), true).is_none() => {
+                return Err("invalid command");
+            },
+            Reply(i) if i > 999 => return Err("invalid command"),
+            _ => {},
+        }
+
+        if self.params.len() > MAX_PARAMS {
+            return Err("too many parameters");
+        }
+        for (i, param) in self.params.iter().enumerate() {
+            if param.as_slice().chars().any(is_forbidden) {
+                return Err("parameter contains NUL, CR, or LF");
+            }
+            if i < self.params.len() - 1 && needs_trailing_marker(param.as_slice()) {
+                return Err("only the last parameter can be empty, contain spaces, or start with ':'");
+            }
+        }
+
+        if self.tags.len() > 0 && Message::format_tags(self.tags.as_slice()).len() > MAX_TAGS_LENGTH {
+            return Err("message tags too long");
+        }
+        if self.format_message().len() > MAX_MESSAGE_LENGTH {
+            return Err("message too long");
+        }
+
+        Ok(())
+    }
+
+    // replaces NUL, CR, and LF in parameters with spaces, so that text taken
+    // from elsewhere can't be used to inject extra lines
+    pub fn sanitize (&self) -> Message {
+        let params = self.params.iter().map(|p| {
+            p.as_slice().chars().map(|c| if is_forbidden(c) { ' ' } else { c }).collect()
+        }).collect();
+        Message::new_with_tags(self.tags.clone(), self.from.clone(), self.message_type.clone(), params)
+    }
+
     pub fn write_protocol_string<W: Writer> (&self, w: &mut W) -> io::IoResult<()> {
-        let mut buf = [0u8, ..MAX_MESSAGE_LENGTH];
+        match self.validate() {
+            Ok(()) => {},
+            Err(e) => return Err(io::IoError { kind: io::InvalidInput, desc: e, detail: None }),
+        }
+        try!(w.write_str(self.to_protocol_string().as_slice()));
+        w.flush()
+    }
 
-        {
-            let mut bufw = io::BufWriter::new(buf);
+    // this doesn't validate the message, so it's only guaranteed to parse
+    // back to the same message if validate succeeds. Client::write takes
+    // care of converting to the wire encoding.
+    pub fn to_protocol_string (&self) -> String {
+        if self.tags.len() > 0 {
+            let mut ret = Message::format_tags(self.tags.as_slice());
+            ret.push_str(self.format_message().as_slice());
+            ret
+        }
+        else {
+            self.format_message()
+        }
+    }
+
+    fn format_message (&self) -> String {
+        let mut ret = String::new();
+
+        match self.from {
+            Some(ref f) => {
+                ret.push(':');
+                ret.push_str(f.as_slice());
+                ret.push(' ');
+            },
+            None => {},
+        }
+
+        ret.push_str(self.message_type.to_command().as_slice());
+
+        for (i, param) in self.params.iter().enumerate() {
+            ret.push(' ');
+            if i == self.params.len() - 1 && needs_trailing_marker(param.as_slice()) {
+                ret.push(':');
+            }
+            ret.push_str(param.as_slice());
+        }
 
-            match self.from {
-                Some(ref f) => { try!(write!(bufw, ":{} ", f)) },
+        ret.push_str("\r\n");
+        ret
+    }
+
+    fn parse_tags (tags: &str) -> Vec<Tag> {
+        TagIter { rest: tags }.map(|(k, v)| (k.to_string(), v.map(unescape_tag_value))).collect()
+    }
+
+    fn format_tags (tags: &[Tag]) -> String {
+        let mut ret = "@".to_string();
+        for (i, &(ref k, ref v)) in tags.iter().enumerate() {
+            if i > 0 {
+                ret.push(';');
+            }
+            ret.push_str(k.as_slice());
+            match *v {
+                Some(ref v) => {
+                    ret.push('=');
+                    ret.push_str(escape_tag_value(v.as_slice()).as_slice());
+                },
                 None => {},
             }
+        }
+        ret.push(' ');
+        ret
+    }
+}
 
-            try!(write!(bufw, "{}", self.message_type));
+// anything which can be used as the command of a MessageBuilder
+pub trait IntoMessageType {
+    fn into_message_type (self) -> MessageType;
+}
 
-            for param in self.params.iter() {
-                if param.as_slice().contains_char(' ') {
-                    try!(write!(bufw, " :{}", param));
-                }
-                else {
-                    try!(write!(bufw, " {}", param));
+impl IntoMessageType for MessageType {
+    fn into_message_type (self) -> MessageType {
+        self
+    }
+}
+
+impl<'a> IntoMessageType for &'a str {
+    // unknown commands are sent in upper case. anything which isn't a valid
+    // command is kept as-is so that building the message fails.
+    fn into_message_type (self) -> MessageType {
+        match parse_command(self, false) {
+            Some(RawCommand(c)) => RawCommand(c.as_slice().to_ascii_upper()),
+            Some(t) => t,
+            None => RawCommand(self.to_string()),
+        }
+    }
+}
+
+impl IntoMessageType for String {
+    fn into_message_type (self) -> MessageType {
+        self.as_slice().into_message_type()
+    }
+}
+
+impl IntoMessageType for u16 {
+    fn into_message_type (self) -> MessageType {
+        Reply(self)
+    }
+}
+
+// builds a message one piece at a time. parameters can be anything that can
+// be displayed, so strings and numbers can be mixed freely:
+//
+//   MessageBuilder::new("KNOCK").param("#chan").param("let me in").build()
+//
+// build checks the result against the protocol rules, so a message which
+// builds successfully can always be sent.
+pub struct MessageBuilder {
+    tags: Vec<Tag>,
+    from: Option<String>,
+    message_type: MessageType,
+    params: Vec<String>,
+}
+
+impl MessageBuilder {
+    pub fn new<C: IntoMessageType> (command: C) -> MessageBuilder {
+        MessageBuilder {
+            tags: vec![],
+            from: None,
+            message_type: command.into_message_type(),
+            params: vec![],
+        }
+    }
+
+    pub fn tag (mut self, key: &str, value: Option<&str>) -> MessageBuilder {
+        self.tags.push((key.to_string(), value.map(|v| v.to_string())));
+        self
+    }
+
+    pub fn from (mut self, from: &str) -> MessageBuilder {
+        self.from = Some(from.to_string());
+        self
+    }
+
+    pub fn param<P: Show> (mut self, param: P) -> MessageBuilder {
+        self.params.push(param.to_string());
+        self
+    }
+
+    pub fn params<P: Show> (mut self, params: &[P]) -> MessageBuilder {
+        for param in params.iter() {
+            self.params.push(param.to_string());
+        }
+        self
+    }
+
+    pub fn build (self) -> Result<Message, &'static str> {
+        let m = Message::new_with_tags(self.tags, self.from, self.message_type, self.params);
+        try!(m.validate());
+        Ok(m)
+    }
+}
+
+// a view of a message which borrows everything from the line it was parsed
+// from, so parsing it doesn't allocate (except for the name of an unknown
+// command). use to_message to get an owned copy.
+pub struct MessageRef<'a> {
+    tags: &'a str,
+    from: Option<&'a str>,
+    message_type: MessageType,
+    params: [&'a str, ..MAX_PARAMS],
+    param_count: uint,
+}
+
+impl<'a> MessageRef<'a> {
+    pub fn parse (msg: &'a str) -> Result<MessageRef<'a>, ParseError> {
+        MessageRef::parse_with_mode(msg, Lenient)
+    }
+
+    pub fn parse_with_mode (msg: &'a str, mode: ParseMode) -> Result<MessageRef<'a>, ParseError> {
+        let strict = mode == Strict;
+
+        let line = if msg.ends_with("\r\n") {
+            msg.slice_to(msg.len() - 2)
+        }
+        else if strict {
+            return Err(ParseError::new(MissingLineEnding, msg.len()));
+        }
+        else if msg.ends_with("\n") {
+            msg.slice_to(msg.len() - 1)
+        }
+        else {
+            msg
+        };
+        match line.find(is_forbidden) {
+            Some(idx) => return Err(ParseError::new(InvalidCharacter, idx)),
+            None => {},
+        }
+
+        let mut pos = 0;
+
+        let mut tags = "";
+        if line.starts_with("@") {
+            let end = match line.find(' ') {
+                Some(idx) => idx,
+                None => return Err(ParseError::new(MissingCommand, line.len())),
+            };
+            tags = line.slice(1, end);
+            // the tags section has its own length limit, separate from the
+            // limit on the rest of the message
+            if strict && tags.len() > MAX_TAGS_LENGTH - 2 {
+                return Err(ParseError::new(TagsTooLong, 0));
+            }
+            pos = try!(skip_spaces(line, end, strict));
+        }
+
+        if strict && line.len() - pos + 2 > MAX_MESSAGE_LENGTH {
+            return Err(ParseError::new(MessageTooLong, pos));
+        }
+
+        let mut from = None;
+        if line.slice_from(pos).starts_with(":") {
+            let end = match line.slice_from(pos).find(' ') {
+                Some(idx) => pos + idx,
+                None => return Err(ParseError::new(MissingCommand, line.len())),
+            };
+            if end == pos + 1 {
+                return Err(ParseError::new(EmptyPrefix, pos));
+            }
+            from = Some(line.slice(pos + 1, end));
+            pos = try!(skip_spaces(line, end, strict));
+        }
+
+        let end = line.slice_from(pos).find(' ').map(|idx| pos + idx).unwrap_or(line.len());
+        let command = line.slice(pos, end);
+        if command.len() == 0 {
+            return Err(ParseError::new(MissingCommand, pos));
+        }
+        let message_type = match parse_command(command, strict) {
+            Some(c) => c,
+            None => return Err(ParseError::new(InvalidCommand, pos)),
+        };
+        pos = end;
+
+        let mut m = MessageRef {
+            tags: tags,
+            from: from,
+            message_type: message_type,
+            params: ["", ..MAX_PARAMS],
+            param_count: 0,
+        };
+
+        while pos < line.len() {
+            let start = try!(skip_spaces(line, pos, strict));
+            if start == line.len() {
+                if strict {
+                    return Err(ParseError::new(UnexpectedSpace, pos));
                 }
+                break;
+            }
+
+            // the last parameter can leave off the : if it's the fifteenth
+            if line.as_bytes()[start] == b':' {
+                m.push_param(line.slice_from(start + 1));
+                break;
+            }
+            if m.param_count == MAX_PARAMS - 1 {
+                m.push_param(line.slice_from(start));
+                break;
+            }
+
+            let end = line.slice_from(start).find(' ').map(|idx| start + idx).unwrap_or(line.len());
+            m.push_param(line.slice(start, end));
+            pos = end;
+        }
+
+        Ok(m)
+    }
+
+    pub fn tags (&self) -> TagIter<'a> {
+        TagIter { rest: self.tags }
+    }
+
+    pub fn has_tag (&self, key: &str) -> bool {
+        self.tags().any(|(k, _)| k == key)
+    }
+
+    // the value as it appears on the wire, with escapes intact
+    pub fn raw_tag_value (&self, key: &str) -> Option<&'a str> {
+        let mut value = None;
+        for (k, v) in self.tags() {
+            if k == key {
+                value = v;
             }
+        }
+        value
+    }
+
+    // only values which contain escapes need to be copied. a value which
+    // unescapes to nothing is treated as missing, the same as in Message.
+    pub fn tag_value (&self, key: &str) -> Option<MaybeOwned<'a>> {
+        self.raw_tag_value(key).and_then(|v| {
+            if !v.contains_char('\\') {
+                Some(Slice(v))
+            }
+            else {
+                let v = unescape_tag_value(v);
+                if v.len() > 0 { Some(Owned(v)) } else { None }
+            }
+        })
+    }
 
-            try!(write!(bufw, "\r\n"));
+    pub fn from (&self) -> Option<&'a str> {
+        self.from
+    }
+
+    pub fn message_type (&self) -> &MessageType {
+        &self.message_type
+    }
+
+    pub fn params (&self) -> &[&'a str] {
+        self.params.slice_to(self.param_count)
+    }
+
+    pub fn is_reply (&self) -> bool {
+        self.message_type.is_reply()
+    }
+
+    pub fn to_tags (&self) -> Vec<Tag> {
+        Message::parse_tags(self.tags)
+    }
+
+    pub fn to_message (&self) -> Message {
+        Message::new_with_tags(
+            self.to_tags(),
+            self.from.map(|s| s.to_string()),
+            self.message_type.clone(),
+            self.params().iter().map(|s| s.to_string()).collect()
+        )
+    }
+
+    fn push_param (&mut self, param: &'a str) {
+        self.params[self.param_count] = param;
+        self.param_count += 1;
+    }
+}
+
+// a message which keeps the bytes it was read as, so that text in an unknown
+// or broken encoding survives being relayed or logged. the parts are stored
+// as ranges into the line, and are only decoded when asked for.
+#[deriving(PartialEq, Eq, Clone)]
+pub struct RawMessage {
+    line: Vec<u8>,
+    tags: Option<(uint, uint)>,
+    from: Option<(uint, uint)>,
+    message_type: MessageType,
+    params: Vec<(uint, uint)>,
+}
+
+impl RawMessage {
+    // builds a message from parts which have already been encoded, with
+    // the same restrictions as Message::validate
+    pub fn new (from: Option<&[u8]>, message_type: MessageType, params: &[&[u8]]) -> Result<RawMessage, &'static str> {
+        fn is_forbidden_byte (b: u8) -> bool {
+            b == 0 || b == b'\r' || b == b'\n'
+        }
+
+        let mut line = vec![];
+        match from {
+            Some(f) if f.len() == 0 || f.iter().any(|&b| b == b' ' || is_forbidden_byte(b)) => {
+                return Err("invalid prefix");
+            },
+            Some(f) => {
+                line.push(b':');
+                line.push_all(f);
+                line.push(b' ');
+            },
+            None => {},
+        }
+
+        match message_type {
+            RawCommand(ref c) if parse_command(c.as_slice(), true).is_none() => {
+                return Err("invalid command");
+            },
+            Reply(i) if i > 999 => return Err("invalid command"),
+            _ => {},
+        }
+        line.push_all(message_type.to_command().as_bytes());
+
+        if params.len() > MAX_PARAMS {
+            return Err("too many parameters");
+        }
+        for (i, &param) in params.iter().enumerate() {
+            if param.iter().any(|&b| is_forbidden_byte(b)) {
+                return Err("parameter contains NUL, CR, or LF");
+            }
+            let needs_marker = param.len() == 0 || param[0] == b':' || param.contains(&b' ');
+            if i < params.len() - 1 && needs_marker {
+                return Err("only the last parameter can be empty, contain spaces, or start with ':'");
+            }
+            line.push(b' ');
+            if needs_marker {
+                line.push(b':');
+            }
+            line.push_all(param);
         }
 
-        let len = buf.iter().position(|&c| c == 0).unwrap_or(MAX_MESSAGE_LENGTH);
-        try!(w.write(buf.slice(0, len)));
-        try!(w.flush());
+        line.push_all(b"\r\n");
+        if line.len() > MAX_MESSAGE_LENGTH {
+            return Err("message too long");
+        }
 
+        // anything the checks above missed is still caught here
+        RawMessage::parse_with_mode(line.as_slice(), Strict).map_err(|_| "invalid message")
+    }
+
+    pub fn parse (line: &[u8]) -> Result<RawMessage, ParseError> {
+        RawMessage::parse_with_mode(line, Lenient)
+    }
+
+    pub fn parse_with_mode (line: &[u8], mode: ParseMode) -> Result<RawMessage, ParseError> {
+        fn range (base: &str, part: &str) -> (uint, uint) {
+            let start = part.as_ptr() as uint - base.as_ptr() as uint;
+            (start, start + part.len())
+        }
+
+        // the parser only cares about ascii, so anything else is replaced
+        // by a placeholder which means nothing to it. every part of the
+        // message is then at the same offset as in the original line.
+        let ascii: String = line.iter().map(|&b| if b < 0x80 { b as char } else { '\x1a' }).collect();
+        let ascii = ascii.as_slice();
+        let m = try!(MessageRef::parse_with_mode(ascii, mode));
+
+        Ok(RawMessage {
+            line: line.to_vec(),
+            tags: if m.tags.len() > 0 { Some(range(ascii, m.tags)) } else { None },
+            from: m.from.map(|f| range(ascii, f)),
+            message_type: m.message_type.clone(),
+            params: m.params().iter().map(|p| range(ascii, *p)).collect(),
+        })
+    }
+
+    // the line exactly as it was read, including its line ending
+    pub fn as_bytes (&self) -> &[u8] {
+        self.line.as_slice()
+    }
+
+    pub fn raw_tags (&self) -> Option<&[u8]> {
+        self.tags.map(|r| self.part(r))
+    }
+
+    // tags are always utf-8
+    pub fn tags (&self) -> Vec<Tag> {
+        match self.raw_tags() {
+            Some(tags) => Message::parse_tags(String::from_utf8_lossy(tags).as_slice()),
+            None => vec![],
+        }
+    }
+
+    pub fn from (&self) -> Option<&[u8]> {
+        self.from.map(|r| self.part(r))
+    }
+
+    pub fn message_type (&self) -> &MessageType {
+        &self.message_type
+    }
+
+    pub fn params (&self) -> Vec<&[u8]> {
+        self.params.iter().map(|&r| self.part(r)).collect()
+    }
+
+    pub fn param (&self, i: uint) -> Option<&[u8]> {
+        self.params.get(i).map(|&r| self.part(r))
+    }
+
+    pub fn is_reply (&self) -> bool {
+        self.message_type.is_reply()
+    }
+
+    // decodes the prefix and parameters in the encoding configured for the
+    // message's target, the same way Client::read does
+    pub fn decode (&self, encoding: &EncodingConfig) -> Message {
+        let target = raw_target(self.as_bytes());
+        let target = target.as_ref().map(|t| t.as_slice());
+        Message::new_with_tags(
+            self.tags(),
+            self.from().map(|f| encoding.decode(target, f)),
+            self.message_type.clone(),
+            self.params.iter().map(|&r| encoding.decode(target, self.part(r))).collect()
+        )
+    }
+
+    pub fn decode_param (&self, i: uint, encoding: &EncodingConfig) -> Option<String> {
+        let target = raw_target(self.as_bytes());
+        self.param(i).map(|p| encoding.decode(target.as_ref().map(|t| t.as_slice()), p))
+    }
+
+    // writes the original bytes back out. lines which were parsed without
+    // a line ending get one, so that they don't run into the next line.
+    pub fn write_to<W: Writer> (&self, w: &mut W) -> io::IoResult<()> {
+        try!(w.write(self.as_bytes()));
+        if !self.line.as_slice().ends_with(b"\n") {
+            try!(w.write(b"\r\n"));
+        }
         Ok(())
     }
 
-    pub fn to_protocol_string (&self) -> String {
-        let mut w = io::MemWriter::new();
-        // this should never fail, so unwrap is fine
-        self.write_protocol_string(&mut w).unwrap();
-        // XXX encoding
-        String::from_utf8_lossy(w.unwrap().as_slice()).into_string()
+    fn part (&self, (start, end): (uint, uint)) -> &[u8] {
+        self.line.slice(start, end)
     }
+}
+
+impl Show for RawMessage {
+    fn fmt (&self, f: &mut Formatter) -> Result<(), FormatError> {
+        write!(f, "RawMessage({})", String::from_utf8_lossy(self.as_bytes()))
+    }
+}
 
-    fn parse_params(params: &str) -> Vec<String> {
-        let mut offset = 0;
-        let len = params.len();
-        let mut ret = vec![];
+// iterates over the tags in the raw tags section of a message, yielding
+// values with their escapes intact
+pub struct TagIter<'a> {
+    rest: &'a str,
+}
 
+impl<'a> Iterator<(&'a str, Option<&'a str>)> for TagIter<'a> {
+    fn next (&mut self) -> Option<(&'a str, Option<&'a str>)> {
         loop {
-            if offset >= len {
-                return ret;
+            if self.rest.len() == 0 {
+                return None;
             }
 
-            if params.char_at(offset) == ':' {
-                ret.push(params.slice(offset + 1, len).to_string());
-                return ret;
+            let tag = match self.rest.find(';') {
+                Some(idx) => {
+                    let tag = self.rest.slice_to(idx);
+                    self.rest = self.rest.slice_from(idx + 1);
+                    tag
+                },
+                None => {
+                    let tag = self.rest;
+                    self.rest = "";
+                    tag
+                },
+            };
+            if tag.len() == 0 {
+                continue;
             }
 
-            let remaining = params.slice(offset, len);
-            match remaining.find(' ') {
-                Some(next) => {
-                    ret.push(remaining.slice(0, next).to_string());
-                    offset += next + 1;
+            return match tag.find('=') {
+                Some(idx) => {
+                    let value = tag.slice_from(idx + 1);
+                    Some((tag.slice_to(idx), if value.len() > 0 { Some(value) } else { None }))
                 },
-                None => {
-                    ret.push(remaining.to_string());
-                    return ret;
-                }
+                None => Some((tag, None)),
+            };
+        }
+    }
+}
+
+fn is_forbidden (c: char) -> bool {
+    c == '\0' || c == '\r' || c == '\n'
+}
+
+fn needs_trailing_marker (param: &str) -> bool {
+    param.len() == 0 || param.starts_with(":") || param.contains_char(' ')
+}
+
+// returns the position after the separator starting at pos, which must be
+// exactly one space in strict mode
+fn skip_spaces (line: &str, pos: uint, strict: bool) -> Result<uint, ParseError> {
+    let mut end = pos;
+    while end < line.len() && line.as_bytes()[end] == b' ' {
+        end += 1;
+    }
+    if strict && end > pos + 1 {
+        return Err(ParseError::new(UnexpectedSpace, pos + 1));
+    }
+    Ok(end)
+}
+
+// commands are case insensitive, but unknown ones are passed through as-is
+fn parse_command (command: &str, strict: bool) -> Option<MessageType> {
+    fn is_letter (c: char) -> bool {
+        (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z')
+    }
+
+    let valid = if command.len() == 0 {
+        false
+    }
+    else if command.len() == 3 && command.chars().all(|c| c >= '0' && c <= '9') {
+        true
+    }
+    else if strict {
+        command.chars().all(is_letter)
+    }
+    else {
+        command.chars().all(|c| is_letter(c) || (c >= '0' && c <= '9'))
+    };
+    if !valid {
+        return None;
+    }
+
+    if command.chars().any(|c| c >= 'a' && c <= 'z') {
+        match from_str(command.to_ascii_upper().as_slice()) {
+            Some(RawCommand(_)) => Some(RawCommand(command.to_string())),
+            other => other,
+        }
+    }
+    else {
+        from_str(command)
+    }
+}
+
+pub fn escape_tag_value (value: &str) -> String {
+    let mut ret = String::new();
+    for c in value.chars() {
+        match c {
+            ';' => ret.push_str("\\:"),
+            ' ' => ret.push_str("\\s"),
+            '\\' => ret.push_str("\\\\"),
+            '\r' => ret.push_str("\\r"),
+            '\n' => ret.push_str("\\n"),
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+pub fn unescape_tag_value (value: &str) -> String {
+    let mut ret = String::new();
+    let mut escaped = false;
+    for c in value.chars() {
+        if escaped {
+            match c {
+                ':' => ret.push(';'),
+                's' => ret.push(' '),
+                'r' => ret.push('\r'),
+                'n' => ret.push('\n'),
+                c => ret.push(c),
             }
+            escaped = false;
+        }
+        else if c == '\\' {
+            escaped = true;
+        }
+        else {
+            ret.push(c);
         }
     }
+    ret
 }
 
 #[test]
@@ -133,6 +861,7 @@ fn test_message_parser () {
             Message::parse(msg),
             Ok(
                 Message {
+                    tags: vec![],
                     from: None,
                     message_type: Pass,
                     params: vec!["secretpasswordhere".to_string()],
@@ -147,6 +876,7 @@ fn test_message_parser () {
             Message::parse(msg),
             Ok(
                 Message {
+                    tags: vec![],
                     from: Some("WiZ".to_string()),
                     message_type: Nick,
                     params: vec!["Kilroy".to_string()],
@@ -161,6 +891,7 @@ fn test_message_parser () {
             Message::parse(msg),
             Ok(
                 Message {
+                    tags: vec![],
                     from: None,
                     message_type: Quit,
                     params: vec!["Gone to have lunch".to_string()],
@@ -175,6 +906,7 @@ fn test_message_parser () {
             Message::parse(msg),
             Ok(
                 Message {
+                    tags: vec![],
                     from: Some("Trillian".to_string()),
                     message_type: Squit,
                     params: vec![
@@ -192,6 +924,7 @@ fn test_message_parser () {
             Message::parse(msg),
             Ok(
                 Message {
+                    tags: vec![],
                     from: None,
                     message_type: Reply(ERR_NOSUCHNICK),
                     params: vec![
@@ -202,4 +935,508 @@ fn test_message_parser () {
             )
         );
     }
+
+    {
+        let msg = "@time=2014-10-01T12:00:00.000Z;msgid=abc\\:123;+draft/typing :WiZ PRIVMSG #chan :hi there\r\n";
+        assert_eq!(
+            Message::parse(msg),
+            Ok(
+                Message {
+                    tags: vec![
+                        ("time".to_string(), Some("2014-10-01T12:00:00.000Z".to_string())),
+                        ("msgid".to_string(), Some("abc;123".to_string())),
+                        ("+draft/typing".to_string(), None),
+                    ],
+                    from: Some("WiZ".to_string()),
+                    message_type: Privmsg,
+                    params: vec![
+                        "#chan".to_string(),
+                        "hi there".to_string(),
+                    ],
+                }
+            )
+        );
+        assert_eq!(Message::parse(msg).unwrap().to_protocol_string().as_slice(), msg);
+    }
+}
+
+#[test]
+fn test_message_ref_parser () {
+    use constants::*;
+
+    let lines = [
+        "PASS secretpasswordhere\r\n",
+        ":WiZ NICK Kilroy\r\n",
+        ":Trillian SQUIT cm22.eng.umd.edu :Server out of control\r\n",
+        "401 doy :No such nick/channel\r\n",
+        "@time=2014-10-01T12:00:00.000Z;msgid=abc\\:123;+draft/typing :WiZ PRIVMSG #chan :hi there\r\n",
+        ":irc.host CAP * LS :multi-prefix sasl\r\n",
+    ];
+    for line in lines.iter() {
+        assert_eq!(MessageRef::parse(*line).unwrap().to_message(), Message::parse(*line).unwrap());
+    }
+
+    {
+        let msg = "@msgid=abc\\:123;time=now;+typing :WiZ!w@host PRIVMSG #chan :hi there\r\n";
+        let m = MessageRef::parse(msg).unwrap();
+        assert_eq!(m.from(), Some("WiZ!w@host"));
+        assert_eq!(*m.message_type(), Privmsg);
+        assert_eq!(m.params(), ["#chan", "hi there"].as_slice());
+        assert!(m.has_tag("+typing"));
+        assert_eq!(m.raw_tag_value("msgid"), Some("abc\\:123"));
+        assert_eq!(m.tag_value("msgid").unwrap().as_slice(), "abc;123");
+        assert_eq!(m.tag_value("time").unwrap().as_slice(), "now");
+        assert!(m.tag_value("+typing").is_none());
+    }
+
+    {
+        let msg = "CMD a b c d e f g h i j k l m n o p q\r\n";
+        let m = MessageRef::parse(msg).unwrap();
+        assert_eq!(m.params().len(), MAX_PARAMS);
+        assert_eq!(m.params()[MAX_PARAMS - 1], "o p q");
+    }
+
+    assert_eq!(*MessageRef::parse("privmsg #chan :hi\r\n").unwrap().message_type(), Privmsg);
+    assert_eq!(
+        MessageRef::parse(": PRIVMSG #chan :hi\r\n").err(),
+        Some(ParseError::new(EmptyPrefix, 0))
+    );
+    assert_eq!(
+        MessageRef::parse("PRIVMSG #chan :hi\rthere\r\n").err(),
+        Some(ParseError::new(InvalidCharacter, 17))
+    );
+}
+
+#[test]
+fn test_parse_modes () {
+    {
+        let msg = ":irc.host MODE  #chan +n \n";
+        assert_eq!(
+            Message::parse_with_mode(msg, Strict).err(),
+            Some(ParseError::new(MissingLineEnding, msg.len()))
+        );
+        let m = Message::parse_with_mode(msg, Lenient).unwrap();
+        assert_eq!(m.params(), &vec!["#chan".to_string(), "+n".to_string()]);
+    }
+
+    assert_eq!(
+        Message::parse_with_mode(":irc.host MODE  #chan +n\r\n", Strict).err(),
+        Some(ParseError::new(UnexpectedSpace, 15))
+    );
+    assert_eq!(
+        Message::parse_with_mode(":irc.host MODE #chan +n \r\n", Strict).err(),
+        Some(ParseError::new(UnexpectedSpace, 23))
+    );
+    assert_eq!(
+        Message::parse_with_mode(":irc.host MO-DE #chan\r\n", Strict).err(),
+        Some(ParseError::new(InvalidCommand, 10))
+    );
+    assert_eq!(
+        Message::parse_with_mode(":irc.host\r\n", Strict).err(),
+        Some(ParseError::new(MissingCommand, 9))
+    );
+    assert_eq!(
+        Message::parse_with_mode("@a=b PING :x\r\n", Strict).unwrap().tag_value("a"),
+        Some("b")
+    );
+
+    {
+        let long = format!("PRIVMSG #chan :{}\r\n", "a".repeat(MAX_MESSAGE_LENGTH));
+        assert_eq!(
+            Message::parse_with_mode(long.as_slice(), Strict).err().map(|e| e.kind()),
+            Some(MessageTooLong)
+        );
+        assert!(Message::parse_with_mode(long.as_slice(), Lenient).is_ok());
+    }
+
+    assert_eq!(
+        format!("{}", ParseError::new(InvalidCommand, 3)).as_slice(),
+        "invalid command at position 3"
+    );
+}
+
+// the msg-split vectors from https://github.com/ircdocs/parser-tests
+#[test]
+fn test_parser_tests_msg_split () {
+    let vectors: Vec<(&str, Vec<(&str, Option<&str>)>, Option<&str>, &str, Vec<&str>)> = vec![
+        ("foo bar baz asdf", vec![], None, "foo", vec!["bar", "baz", "asdf"]),
+        (":coolguy foo bar baz asdf", vec![], Some("coolguy"), "foo", vec!["bar", "baz", "asdf"]),
+        ("foo bar baz :asdf quux", vec![], None, "foo", vec!["bar", "baz", "asdf quux"]),
+        ("foo bar baz :", vec![], None, "foo", vec!["bar", "baz", ""]),
+        ("foo bar baz ::asdf", vec![], None, "foo", vec!["bar", "baz", ":asdf"]),
+        (":coolguy foo bar baz :asdf quux", vec![], Some("coolguy"), "foo", vec!["bar", "baz", "asdf quux"]),
+        (":coolguy foo bar baz :  asdf quux ", vec![], Some("coolguy"), "foo", vec!["bar", "baz", "  asdf quux "]),
+        (":coolguy PRIVMSG bar :lol :) ", vec![], Some("coolguy"), "PRIVMSG", vec!["bar", "lol :) "]),
+        (":coolguy foo bar baz :", vec![], Some("coolguy"), "foo", vec!["bar", "baz", ""]),
+        (":coolguy foo bar baz :  ", vec![], Some("coolguy"), "foo", vec!["bar", "baz", "  "]),
+        (
+            "@a=b;c=32;k;rt=ql7 foo",
+            vec![("a", Some("b")), ("c", Some("32")), ("k", None), ("rt", Some("ql7"))],
+            None, "foo", vec![]
+        ),
+        (
+            "@a=b\\\\and\\nk;c=72\\s45;d=gh\\:764 foo",
+            vec![("a", Some("b\\and\nk")), ("c", Some("72 45")), ("d", Some("gh;764"))],
+            None, "foo", vec![]
+        ),
+        (
+            "@c;h=;a=b :quux ab cd",
+            vec![("c", None), ("h", None), ("a", Some("b"))],
+            Some("quux"), "ab", vec!["cd"]
+        ),
+        (":src JOIN #chan", vec![], Some("src"), "JOIN", vec!["#chan"]),
+        (":src JOIN :#chan", vec![], Some("src"), "JOIN", vec!["#chan"]),
+        (":src AWAY", vec![], Some("src"), "AWAY", vec![]),
+        (":src AWAY ", vec![], Some("src"), "AWAY", vec![]),
+        (":cool\tguy foo bar baz", vec![], Some("cool\tguy"), "foo", vec!["bar", "baz"]),
+        (
+            ":coolguy!ag@net\x035w\x03ork.admin PRIVMSG foo :bar baz",
+            vec![], Some("coolguy!ag@net\x035w\x03ork.admin"), "PRIVMSG", vec!["foo", "bar baz"]
+        ),
+        (
+            ":coolguy!~ag@n\x02et\x0305w\x0fork.admin PRIVMSG foo :bar baz",
+            vec![], Some("coolguy!~ag@n\x02et\x0305w\x0fork.admin"), "PRIVMSG", vec!["foo", "bar baz"]
+        ),
+        (
+            "@tag1=value1;tag2;vendor1/tag3=value2;vendor2/tag4= :irc.example.com COMMAND param1 param2 :param3 param3",
+            vec![("tag1", Some("value1")), ("tag2", None), ("vendor1/tag3", Some("value2")), ("vendor2/tag4", None)],
+            Some("irc.example.com"), "COMMAND", vec!["param1", "param2", "param3 param3"]
+        ),
+        (
+            ":irc.example.com COMMAND param1 param2 :param3 param3",
+            vec![], Some("irc.example.com"), "COMMAND", vec!["param1", "param2", "param3 param3"]
+        ),
+        (
+            "@tag1=value1;tag2;vendor1/tag3=value2;vendor2/tag4 COMMAND param1 param2 :param3 param3",
+            vec![("tag1", Some("value1")), ("tag2", None), ("vendor1/tag3", Some("value2")), ("vendor2/tag4", None)],
+            None, "COMMAND", vec!["param1", "param2", "param3 param3"]
+        ),
+        ("COMMAND", vec![], None, "COMMAND", vec![]),
+        (
+            "@foo=\\\\\\\\\\:\\\\s\\s\\r\\n COMMAND",
+            vec![("foo", Some("\\\\;\\s \r\n"))],
+            None, "COMMAND", vec![]
+        ),
+        (
+            ":gravel.mozilla.org 432  #momo :Erroneous Nickname: Illegal characters",
+            vec![], Some("gravel.mozilla.org"), "432", vec!["#momo", "Erroneous Nickname: Illegal characters"]
+        ),
+        (":gravel.mozilla.org MODE #tckk +n ", vec![], Some("gravel.mozilla.org"), "MODE", vec!["#tckk", "+n"]),
+        (
+            ":services.esper.net MODE #foo-bar +o foobar  ",
+            vec![], Some("services.esper.net"), "MODE", vec!["#foo-bar", "+o", "foobar"]
+        ),
+        ("@tag1=value\\\\ntest COMMAND", vec![("tag1", Some("value\\ntest"))], None, "COMMAND", vec![]),
+        ("@tag1=value\\1 COMMAND", vec![("tag1", Some("value1"))], None, "COMMAND", vec![]),
+        ("@tag1=value1\\ COMMAND", vec![("tag1", Some("value1"))], None, "COMMAND", vec![]),
+        (
+            "@tag1=1;tag2=3;tag3=4;tag1=5 COMMAND",
+            vec![("tag1", Some("5")), ("tag2", Some("3")), ("tag3", Some("4"))],
+            None, "COMMAND", vec![]
+        ),
+        (":SomeOp MODE #channel :+i", vec![], Some("SomeOp"), "MODE", vec!["#channel", "+i"]),
+        (
+            ":SomeOp MODE #channel +oo SomeUser :AnotherUser",
+            vec![], Some("SomeOp"), "MODE", vec!["#channel", "+oo", "SomeUser", "AnotherUser"]
+        ),
+    ];
+
+    for &(line, ref tags, from, command, ref params) in vectors.iter() {
+        let m = Message::parse(line).unwrap();
+        for &(key, value) in tags.iter() {
+            assert!(m.has_tag(key));
+            assert_eq!(m.tag_value(key), value);
+        }
+        assert_eq!(m.from().as_ref().map(|s| s.as_slice()), from);
+        assert_eq!(m.message_type().to_command().as_slice(), command);
+        let p: Vec<&str> = m.params().iter().map(|s| s.as_slice()).collect();
+        assert_eq!(p, *params);
+    }
+}
+
+#[test]
+fn test_serialize () {
+    use constants::*;
+
+    {
+        let m = Message::new(None, Privmsg, vec!["#chan".to_string(), "".to_string()]);
+        assert_eq!(m.to_protocol_string().as_slice(), "PRIVMSG #chan :\r\n");
+    }
+
+    {
+        let m = Message::new(None, Privmsg, vec!["#chan".to_string(), ":)".to_string()]);
+        assert_eq!(m.to_protocol_string().as_slice(), "PRIVMSG #chan ::)\r\n");
+        assert_eq!(Message::parse(m.to_protocol_string().as_slice()), Ok(m));
+    }
+
+    {
+        let m = Message::new(None, Privmsg, vec!["#chan".to_string(), "hi\r\nQUIT :bye".to_string()]);
+        assert!(m.validate().is_err());
+        let mut w = io::MemWriter::new();
+        assert_eq!(m.write_protocol_string(&mut w).err().map(|e| e.kind), Some(io::InvalidInput));
+        assert_eq!(w.unwrap().len(), 0);
+        assert_eq!(m.sanitize().to_protocol_string().as_slice(), "PRIVMSG #chan :hi  QUIT :bye\r\n");
+    }
+
+    assert!(Message::new(None, Privmsg, vec!["#a b".to_string(), "hi".to_string()]).validate().is_err());
+    assert!(Message::new(None, Privmsg, vec!["".to_string(), "hi".to_string()]).validate().is_err());
+    assert!(Message::new(None, Privmsg, vec![":a".to_string(), "hi".to_string()]).validate().is_err());
+    assert!(Message::new(Some("a b".to_string()), Ping, vec![]).validate().is_err());
+    assert!(Message::new(None, RawCommand("NOT VALID".to_string()), vec![]).validate().is_err());
+    assert!(Message::new(None, RawCommand(String::new()), vec![]).validate().is_err());
+    assert!(Message::new_with_tags(vec![("a;b".to_string(), None)], None, Ping, vec![]).validate().is_err());
+    assert!(Message::new(None, Privmsg, vec!["#chan".to_string(), "a".repeat(600)]).validate().is_err());
+
+    assert_eq!(
+        Message::new_with_tags(vec![("a".to_string(), Some("".to_string()))], None, Ping, vec![]).tags(),
+        &vec![("a".to_string(), None)]
+    );
+}
+
+#[cfg(test)]
+fn random_string<R: ::std::rand::Rng> (rng: &mut R, chars: &[char], min: uint, max: uint) -> String {
+    let len = rng.gen_range(min, max + 1);
+    range(0, len).map(|_| *rng.choose(chars).unwrap()).collect()
+}
+
+// parse(serialize(m)) == m for any valid message
+#[test]
+fn test_round_trip () {
+    use constants::*;
+    use std::rand::{task_rng, Rng};
+
+    let tag_chars = ['a', 'z', '0', '-', '/', '+', '.'];
+    let value_chars = ['a', 'b', ' ', ';', '\\', '\r', '\n', '=', ':', '\u00e9'];
+    let from_chars = ['a', 'z', '!', '@', '.', ':', '\u00e9'];
+    let middle_chars = ['a', 'b', '#', ':', '!', '@', ',', '\u00e9', '\x01'];
+    let trailing_chars = ['a', ' ', ':', '#', '\u00e9', '\x01', '\t'];
+    let types = [
+        Privmsg, Notice, Join, Mode, Tagmsg,
+        RawCommand("FOO".to_string()), Reply(1), Reply(433),
+    ];
+
+    let mut rng = task_rng();
+    for _ in range(0u, 1000) {
+        let tag_count = rng.gen_range(0u, 4);
+        let tags = range(0, tag_count).map(|_| {
+            let key = random_string(&mut rng, tag_chars.as_slice(), 1, 8);
+            let value = if rng.gen() {
+                Some(random_string(&mut rng, value_chars.as_slice(), 0, 8))
+            }
+            else {
+                None
+            };
+            (key, value)
+        }).collect();
+
+        let from = if rng.gen() {
+            Some(random_string(&mut rng, from_chars.as_slice(), 1, 16))
+        }
+        else {
+            None
+        };
+
+        let param_count = rng.gen_range(0u, MAX_PARAMS + 1);
+        let mut params = vec![];
+        for i in range(0, param_count) {
+            if i == param_count - 1 {
+                params.push(random_string(&mut rng, trailing_chars.as_slice(), 0, 16));
+            }
+            else {
+                let param = random_string(&mut rng, middle_chars.as_slice(), 1, 16);
+                params.push(if param.as_slice().starts_with(":") { format!("x{}", param) } else { param });
+            }
+        }
+
+        let message_type = rng.choose(types.as_slice()).unwrap().clone();
+        let m = Message::new_with_tags(tags, from, message_type, params);
+        assert_eq!(m.validate(), Ok(()));
+
+        let line = m.to_protocol_string();
+        assert_eq!(Message::parse_with_mode(line.as_slice(), Strict), Ok(m.clone()));
+        assert_eq!(Message::parse_with_mode(line.as_slice(), Lenient), Ok(m));
+    }
+}
+
+#[test]
+fn test_builder () {
+    use constants::Privmsg;
+
+    let m = MessageBuilder::new("knock").param("#chan").param("let me in").build();
+    assert_eq!(
+        m.map(|m| m.to_protocol_string()),
+        Ok("KNOCK #chan :let me in\r\n".to_string())
+    );
+
+    let m = MessageBuilder::new("privmsg").param("#chan").param("hi").build().unwrap();
+    assert_eq!(*m.message_type(), Privmsg);
+
+    let m = MessageBuilder::new(Reply(1))
+        .tag("time", Some("2014-11-01T00:00:00.000Z"))
+        .tag("bot", None)
+        .from("irc.example.com")
+        .param("nick")
+        .param(5u)
+        .param(String::from_str("Welcome"))
+        .build()
+        .unwrap();
+    assert_eq!(
+        m.to_protocol_string().as_slice(),
+        "@time=2014-11-01T00:00:00.000Z;bot :irc.example.com 001 nick 5 Welcome\r\n"
+    );
+
+    let m = MessageBuilder::new(330u16).params(["me", "them", "account"].as_slice()).build().unwrap();
+    assert_eq!(*m.message_type(), Reply(330));
+
+    assert!(MessageBuilder::new("NOT VALID").build().is_err());
+    assert!(MessageBuilder::new("").build().is_err());
+    assert!(MessageBuilder::new("CHGHOST").param("a b").param("c").build().is_err());
+    assert!(MessageBuilder::new("SAJOIN").param("nick").param("#a\r\nQUIT").build().is_err());
+    assert!(MessageBuilder::new("PING").from("").build().is_err());
+}
+
+#[test]
+fn test_modern_commands_round_trip () {
+    use constants::*;
+
+    let messages = vec![
+        (Cap, vec!["*", "LS", "multi-prefix sasl"]),
+        (Authenticate, vec!["PLAIN"]),
+        (Authenticate, vec!["+"]),
+        (Account, vec!["accountname"]),
+        (Account, vec!["*"]),
+        (Batch, vec!["+yXNAbvnRHTRBv", "netsplit", "irc.hub", "other.host"]),
+        (Batch, vec!["-yXNAbvnRHTRBv"]),
+        (Chghost, vec!["newuser", "new.host.goes.here"]),
+        (Knock, vec!["#chan", "let me in"]),
+        (Monitor, vec!["+", "nick1,nick2"]),
+        (Monitor, vec!["C"]),
+        (Setname, vec!["a new realname"]),
+        (Watch, vec!["+nick1", "-nick2", "L"]),
+        (Silence, vec!["+*!*@bad.host"]),
+        (Cprivmsg, vec!["nick", "#chan", "hello there"]),
+        (Cnotice, vec!["nick", "#chan", "hello there"]),
+        (Webirc, vec!["password", "gateway", "user.host", "192.0.2.1"]),
+        (Fail, vec!["ACCOUNT", "REG_INVALID_CALLBACK", "REGISTER", "invalid callback"]),
+        (Warn, vec!["REHASH", "CERTS_EXPIRED", "certificate expired"]),
+        (Note, vec!["*", "OPER_MESSAGE", "the message"]),
+    ];
+    for (message_type, params) in messages.into_iter() {
+        let m = Message::new(
+            Some("nick!user@host".to_string()),
+            message_type,
+            params.iter().map(|s| s.to_string()).collect()
+        );
+        let line = m.to_protocol_string();
+        assert_eq!(Message::parse_with_mode(line.as_slice(), Strict), Ok(m.clone()));
+        let parsed = MessageRef::parse_with_mode(line.as_slice(), Strict).unwrap();
+        assert_eq!(parsed.message_type(), m.message_type());
+    }
+}
+
+// inputs found by the fuzzer in fuzz/ which used to be mishandled, or which
+// sit right at the edges of the parser
+#[test]
+fn test_parser_regressions () {
+    use constants::*;
+
+    let errors = [
+        ("", Strict, MissingLineEnding, 0),
+        ("", Lenient, MissingCommand, 0),
+        ("\r\n", Strict, MissingCommand, 0),
+        ("\n", Lenient, MissingCommand, 0),
+        ("\r", Lenient, InvalidCharacter, 0),
+        (":\r\n", Strict, MissingCommand, 1),
+        ("@\r\n", Strict, MissingCommand, 1),
+        (":  CMD\r\n", Lenient, EmptyPrefix, 0),
+        ("CMD a  b\r\n", Strict, UnexpectedSpace, 6),
+        ("@a  CMD\r\n", Strict, UnexpectedSpace, 3),
+        ("CMD a\x00b\r\n", Lenient, InvalidCharacter, 5),
+    ];
+    for &(line, mode, kind, position) in errors.iter() {
+        assert_eq!(
+            MessageRef::parse_with_mode(line, mode).err(),
+            Some(ParseError::new(kind, position))
+        );
+    }
+
+    let m = MessageRef::parse_with_mode("@ CMD\r\n", Strict).unwrap();
+    assert_eq!(m.tags().count(), 0);
+    assert_eq!(*m.message_type(), RawCommand("CMD".to_string()));
+
+    let m = MessageRef::parse_with_mode("CMD :\r\n", Strict).unwrap();
+    assert_eq!(m.params(), [""].as_slice());
+
+    let m = MessageRef::parse_with_mode("CMD a  b\r\n", Lenient).unwrap();
+    assert_eq!(m.params(), ["a", "b"].as_slice());
+
+    // a value which is only a dangling escape unescapes to nothing, which
+    // has to mean the same as no value at all for it to round trip
+    let m = MessageRef::parse("@a=\\ CMD\r\n").unwrap();
+    assert_eq!(m.raw_tag_value("a"), Some("\\"));
+    assert!(m.tag_value("a").is_none());
+    let owned = m.to_message();
+    assert_eq!(*owned.tags(), vec![("a".to_string(), None)]);
+    assert_eq!(Message::parse_with_mode(owned.to_protocol_string().as_slice(), Strict), Ok(owned.clone()));
+
+    // the length limit counts bytes, not characters
+    let text: String = range(0u, 249).map(|_| '\u00e9').collect();
+    let line = format!("PRIVMSG #c :{}\r\n", text);
+    assert_eq!(line.len(), MAX_MESSAGE_LENGTH);
+    let m = Message::parse_with_mode(line.as_slice(), Strict).unwrap();
+    assert_eq!(m.validate(), Ok(()));
+    let line = format!("PRIVMSG #c :{}\u00e9\r\n", text);
+    assert_eq!(
+        MessageRef::parse_with_mode(line.as_slice(), Strict).err(),
+        Some(ParseError::new(MessageTooLong, 0))
+    );
+}
+
+#[test]
+fn test_raw_message () {
+    use constants::*;
+    use encoding::encoding_from_label;
+
+    let line = b"@time=now :n\xe9ck!u@host PRIVMSG #caf\xe9 :caf\xe9 \xff\xfe\r\n";
+    let m = RawMessage::parse_with_mode(line, Strict).unwrap();
+    assert_eq!(m.as_bytes(), line);
+    assert_eq!(m.tags(), vec![("time".to_string(), Some("now".to_string()))]);
+    assert_eq!(m.from(), Some(b"n\xe9ck!u@host"));
+    assert_eq!(*m.message_type(), Privmsg);
+    assert_eq!(m.params(), vec![b"#caf\xe9", b"caf\xe9 \xff\xfe"]);
+
+    let mut out = io::MemWriter::new();
+    m.write_to(&mut out).unwrap();
+    assert_eq!(out.get_ref(), line);
+
+    // bytes which aren't valid in the configured encoding are only lost when
+    // decoding, not when reading
+    let decoded = m.decode(&EncodingConfig::new());
+    assert_eq!(decoded.params()[1].as_slice(), "caf� ��");
+    let mut encoding = EncodingConfig::new();
+    encoding.set_default(encoding_from_label("latin1").unwrap());
+    let decoded = m.decode(&encoding);
+    assert_eq!(*decoded.from(), Some("néck!u@host".to_string()));
+    assert_eq!(
+        *decoded.params(),
+        vec!["#café".to_string(), "café ÿþ".to_string()]
+    );
+    assert_eq!(m.decode_param(0, &encoding), Some("#café".to_string()));
+    assert_eq!(m.decode_param(2, &encoding), None);
+
+    let relayed = RawMessage::new(None, Privmsg, [b"#other", m.param(1).unwrap()].as_slice()).unwrap();
+    assert_eq!(relayed.as_bytes(), b"PRIVMSG #other :caf\xe9 \xff\xfe\r\n");
+    assert_eq!(relayed.param(1), m.param(1));
+    assert!(RawMessage::new(None, Privmsg, [b"a b", b"c"].as_slice()).is_err());
+    assert!(RawMessage::new(Some(b""), Privmsg, [b"#a"].as_slice()).is_err());
+    assert!(RawMessage::new(None, Privmsg, [b"#a", b"x\r\nQUIT"].as_slice()).is_err());
+    assert!(RawMessage::new(None, RawCommand(String::new()), [].as_slice()).is_err());
+
+    // lenient lines without a line ending get one when written back out
+    let m = RawMessage::parse(b"PING :x\xff").unwrap();
+    let mut out = io::MemWriter::new();
+    m.write_to(&mut out).unwrap();
+    assert_eq!(out.get_ref(), b"PING :x\xff\r\n");
+
+    assert_eq!(RawMessage::parse(b"\xff CMD\r\n").err(), Some(ParseError::new(InvalidCommand, 0)));
 }
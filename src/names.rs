@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use constants::{Reply, RPL_ISUPPORT, RPL_NAMREPLY, RPL_ENDOFNAMES};
+use message::MessageRef;
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub struct NameEntry {
+    modes: Vec<char>,
+    nick: String,
+    user: Option<String>,
+    host: Option<String>,
+}
+
+impl NameEntry {
+    pub fn parse (entry: &str, prefixes: &[(char, char)]) -> NameEntry {
+        let mut modes = vec![];
+        let mut offset = 0;
+        for c in entry.chars() {
+            match prefixes.iter().find(|&&(_, p)| p == c) {
+                Some(&(mode, _)) => {
+                    modes.push(mode);
+                    offset += c.len_utf8_bytes();
+                },
+                None => break,
+            }
+        }
+
+        let rest = entry.slice_from(offset);
+        let (nick, user, host) = match rest.find('!') {
+            Some(bang) => {
+                let userhost = rest.slice_from(bang + 1);
+                match userhost.find('@') {
+                    Some(at) => (
+                        rest.slice_to(bang),
+                        Some(userhost.slice_to(at).to_string()),
+                        Some(userhost.slice_from(at + 1).to_string())
+                    ),
+                    None => (rest.slice_to(bang), Some(userhost.to_string()), None),
+                }
+            },
+            None => (rest, None, None),
+        };
+
+        NameEntry {
+            modes: modes,
+            nick: nick.to_string(),
+            user: user,
+            host: host,
+        }
+    }
+
+    pub fn modes (&self) -> &[char] {
+        self.modes.as_slice()
+    }
+
+    pub fn has_mode (&self, mode: char) -> bool {
+        self.modes.contains(&mode)
+    }
+
+    pub fn nick (&self) -> &str {
+        self.nick.as_slice()
+    }
+
+    pub fn user (&self) -> Option<&str> {
+        self.user.as_ref().map(|s| s.as_slice())
+    }
+
+    pub fn host (&self) -> Option<&str> {
+        self.host.as_ref().map(|s| s.as_slice())
+    }
+}
+
+// collects RPL_NAMREPLY lines until RPL_ENDOFNAMES. entries with several
+// prefixes or a full nick!user@host are parsed when the server sends them,
+// but servers only do that once the multi-prefix and userhost-in-names caps
+// are enabled, and the client doesn't request those unless they're passed
+// to ClientBuilder::request_cap.
+pub struct NamesCollector {
+    prefixes: Vec<(char, char)>,
+    pending: HashMap<String, Vec<NameEntry>>,
+}
+
+impl NamesCollector {
+    pub fn new () -> NamesCollector {
+        NamesCollector {
+            // the rfc only defines ops and voice
+            prefixes: vec![('o', '@'), ('v', '+')],
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn prefixes (&self) -> &[(char, char)] {
+        self.prefixes.as_slice()
+    }
+
+    // parses the value of the PREFIX isupport token, which looks like
+    // "(ohv)@%+"
+    pub fn set_prefix (&mut self, value: &str) {
+        if !value.starts_with("(") {
+            return;
+        }
+        match value.find(')') {
+            Some(idx) => {
+                let modes = value.slice(1, idx);
+                let prefixes = value.slice_from(idx + 1);
+                self.prefixes = modes.chars().zip(prefixes.chars()).collect();
+            },
+            None => {},
+        }
+    }
+
+    pub fn process (&mut self, m: &MessageRef) -> Option<(String, Vec<NameEntry>)> {
+        let p = m.params();
+        match *m.message_type() {
+            Reply(RPL_ISUPPORT) => {
+                for token in p.iter() {
+                    if token.starts_with("PREFIX=") {
+                        self.set_prefix(token.slice_from(7));
+                    }
+                }
+                None
+            },
+            // some servers leave out the channel type symbol, so find the
+            // channel and names relative to the end
+            Reply(RPL_NAMREPLY) if p.len() >= 3 => {
+                let channel = p[p.len() - 2].to_string();
+                let entries: Vec<NameEntry> = p[p.len() - 1].split(' ')
+                    .filter(|e| e.len() > 0)
+                    .map(|e| NameEntry::parse(e, self.prefixes.as_slice()))
+                    .collect();
+                let found = match self.pending.find_mut(&channel) {
+                    Some(list) => { list.push_all(entries.as_slice()); true },
+                    None => false,
+                };
+                if !found {
+                    self.pending.insert(channel, entries);
+                }
+                None
+            },
+            Reply(RPL_ENDOFNAMES) if p.len() >= 2 => {
+                let channel = p[1].to_string();
+                // a NAMES for an empty or unknown channel gets only the end
+                // reply
+                let entries = self.pending.pop(&channel).unwrap_or(vec![]);
+                Some((channel, entries))
+            },
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_name_entry () {
+    let prefixes = [('q', '~'), ('o', '@'), ('h', '%'), ('v', '+')];
+
+    {
+        let entry = NameEntry::parse("@+doy", prefixes);
+        assert_eq!(entry.modes(), ['o', 'v'].as_slice());
+        assert_eq!(entry.nick(), "doy");
+        assert_eq!(entry.user(), None);
+        assert_eq!(entry.host(), None);
+    }
+
+    {
+        let entry = NameEntry::parse("~doy!jesse@tozt.net", prefixes);
+        assert!(entry.has_mode('q'));
+        assert!(!entry.has_mode('o'));
+        assert_eq!(entry.nick(), "doy");
+        assert_eq!(entry.user(), Some("jesse"));
+        assert_eq!(entry.host(), Some("tozt.net"));
+    }
+
+    {
+        let entry = NameEntry::parse("plain", prefixes);
+        assert_eq!(entry.modes().len(), 0);
+        assert_eq!(entry.nick(), "plain");
+    }
+}
+
+#[test]
+fn test_names_collector () {
+    let mut names = NamesCollector::new();
+
+    let lines = [
+        ":irc.host 005 doy CHANTYPES=# PREFIX=(ohv)@%+ :are supported by this server\r\n",
+        ":irc.host 353 doy = #chan :@%+op!o@host +voice!v@host\r\n",
+        ":irc.host 353 doy = #chan :%half!h@host someone!s@host\r\n",
+    ];
+    for line in lines.iter() {
+        assert_eq!(names.process(&MessageRef::parse(*line).unwrap()), None);
+    }
+
+    let (channel, members) = names.process(
+        &MessageRef::parse(":irc.host 366 doy #chan :End of /NAMES list.\r\n").unwrap()
+    ).unwrap();
+    assert_eq!(channel.as_slice(), "#chan");
+    assert_eq!(members.len(), 4);
+    assert_eq!(members[0].modes(), ['o', 'h', 'v'].as_slice());
+    assert_eq!(members[0].nick(), "op");
+    assert_eq!(members[2].modes(), ['h'].as_slice());
+    assert_eq!(members[3].modes().len(), 0);
+    assert_eq!(members[3].host(), Some("host"));
+}
@@ -0,0 +1,189 @@
+use std::ascii::AsciiExt;
+use std::io;
+
+#[cfg(feature = "ssl")]
+use openssl::{nid, ssl};
+#[cfg(feature = "ssl")]
+use openssl::x509::X509;
+
+pub enum NetStream {
+    PlainStream(io::TcpStream),
+    #[cfg(feature = "ssl")]
+    TlsStream(ssl::SslStream<io::TcpStream>),
+}
+
+impl NetStream {
+    pub fn connect (host: &str, port: u16, secure: bool) -> io::IoResult<NetStream> {
+        let stream = try!(io::TcpStream::connect(host, port));
+        if secure {
+            NetStream::wrap_tls(stream, host)
+        }
+        else {
+            Ok(PlainStream(stream))
+        }
+    }
+
+    // the certificate has to chain up to one of the system's trusted cas and
+    // be issued for the host we meant to connect to, or anyone in the middle
+    // could pretend to be the server
+    #[cfg(feature = "ssl")]
+    fn wrap_tls (stream: io::TcpStream, host: &str) -> io::IoResult<NetStream> {
+        let mut ctx = match ssl::SslContext::new(ssl::Sslv23) {
+            Ok(ctx) => ctx,
+            Err(e) => return Err(tls_error(e.to_string())),
+        };
+        ctx.set_verify(ssl::SslVerifyPeer, None);
+        match ctx.set_default_verify_paths() {
+            Ok(()) => {},
+            Err(e) => return Err(tls_error(e.to_string())),
+        }
+        let s = match ssl::SslStream::new(&ctx, stream) {
+            Ok(s) => s,
+            Err(e) => return Err(tls_error(e.to_string())),
+        };
+        let names = match s.get_peer_certificate() {
+            Some(cert) => cert_names(&cert),
+            None => vec![],
+        };
+        if !names.iter().any(|name| hostname_matches(name.as_slice(), host)) {
+            return Err(tls_error(format!("the certificate isn't valid for {}", host)));
+        }
+        Ok(TlsStream(s))
+    }
+
+    #[cfg(not(feature = "ssl"))]
+    fn wrap_tls (_stream: io::TcpStream, _host: &str) -> io::IoResult<NetStream> {
+        Err(tls_error("this crate was built without the ssl feature".to_string()))
+    }
+
+    // another handle on the same connection, so that it can be read on one
+    // task while another writes. tls streams can't be shared like this.
+    pub fn try_clone (&self) -> io::IoResult<NetStream> {
+        match self {
+            &PlainStream(ref s) => Ok(PlainStream(s.clone())),
+            #[cfg(feature = "ssl")]
+            &TlsStream(_) => Err(io::IoError {
+                kind: io::OtherIoError,
+                desc: "tls connections can't be split",
+                detail: None,
+            }),
+        }
+    }
+
+    // wakes up anything blocked reading from another handle on this
+    // connection
+    pub fn close_read (&mut self) -> io::IoResult<()> {
+        match self {
+            &PlainStream(ref mut s) => s.close_read(),
+            #[cfg(feature = "ssl")]
+            &TlsStream(ref mut s) => s.get_mut().close_read(),
+        }
+    }
+
+    pub fn is_secure (&self) -> bool {
+        match self {
+            &PlainStream(_) => false,
+            #[cfg(feature = "ssl")]
+            &TlsStream(_) => true,
+        }
+    }
+
+    // reads which take longer than this many milliseconds fail with
+    // io::TimedOut
+    pub fn set_read_timeout (&mut self, timeout_ms: Option<u64>) {
+        match self {
+            &PlainStream(ref mut s) => s.set_read_timeout(timeout_ms),
+            #[cfg(feature = "ssl")]
+            &TlsStream(ref mut s) => s.get_mut().set_read_timeout(timeout_ms),
+        }
+    }
+
+    pub fn socket_name (&mut self) -> io::IoResult<io::net::ip::SocketAddr> {
+        match self {
+            &PlainStream(ref mut s) => s.socket_name(),
+            #[cfg(feature = "ssl")]
+            &TlsStream(ref mut s) => s.get_mut().socket_name(),
+        }
+    }
+}
+
+impl Reader for NetStream {
+    fn read (&mut self, buf: &mut [u8]) -> io::IoResult<uint> {
+        match self {
+            &PlainStream(ref mut s) => s.read(buf),
+            #[cfg(feature = "ssl")]
+            &TlsStream(ref mut s) => s.read(buf),
+        }
+    }
+}
+
+impl Writer for NetStream {
+    fn write (&mut self, buf: &[u8]) -> io::IoResult<()> {
+        match self {
+            &PlainStream(ref mut s) => s.write(buf),
+            #[cfg(feature = "ssl")]
+            &TlsStream(ref mut s) => s.write(buf),
+        }
+    }
+
+    fn flush (&mut self) -> io::IoResult<()> {
+        match self {
+            &PlainStream(ref mut s) => s.flush(),
+            #[cfg(feature = "ssl")]
+            &TlsStream(ref mut s) => s.flush(),
+        }
+    }
+}
+
+// the dns names in the subject alt name extension, or the common name if
+// there aren't any
+#[cfg(feature = "ssl")]
+fn cert_names (cert: &X509) -> Vec<String> {
+    let alt_names: Vec<String> = match cert.subject_alt_names() {
+        Some(names) => names.iter().filter_map(|n| n.dnsname().map(|s| s.to_string())).collect(),
+        None => vec![],
+    };
+    if alt_names.len() > 0 {
+        return alt_names;
+    }
+    match cert.subject_name().text_by_nid(nid::CN) {
+        Some(cn) => vec![cn.to_string()],
+        None => vec![],
+    }
+}
+
+// a wildcard only stands for the whole of the leftmost label, and never
+// matches more than one label
+fn hostname_matches (pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim_right_chars('.').to_ascii_lower();
+    let host = host.trim_right_chars('.').to_ascii_lower();
+    if pattern.as_slice().starts_with("*.") {
+        let suffix = pattern.as_slice().slice_from(1);
+        match host.as_slice().find('.') {
+            Some(idx) if idx > 0 => host.as_slice().slice_from(idx) == suffix,
+            _ => false,
+        }
+    }
+    else {
+        pattern == host
+    }
+}
+
+fn tls_error (detail: String) -> io::IoError {
+    io::IoError {
+        kind: io::OtherIoError,
+        desc: "tls connection failed",
+        detail: Some(detail),
+    }
+}
+
+#[test]
+fn test_hostname_matches () {
+    assert!(hostname_matches("irc.example.org", "irc.example.org"));
+    assert!(hostname_matches("IRC.Example.org", "irc.example.ORG."));
+    assert!(hostname_matches("*.example.org", "irc.example.org"));
+    assert!(!hostname_matches("*.example.org", "example.org"));
+    assert!(!hostname_matches("*.example.org", "a.irc.example.org"));
+    assert!(!hostname_matches("irc.example.org", "irc.example.net"));
+    assert!(!hostname_matches("irc*.example.org", "irc1.example.org"));
+}
@@ -0,0 +1,430 @@
+use std::ascii::AsciiExt;
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum NumericOrigin {
+    Rfc1459,
+    Rfc2812,
+    Ircu,
+    Hybrid,
+    Ratbox,
+    Bahamut,
+    UnrealIrcd,
+    InspIrcd,
+    Freenode,
+    Ircv3,
+}
+
+// everything known about one numeric reply. params describes what the
+// server sends after the command, in the usual notation where <client> is
+// the nick of the client the reply is being sent to.
+pub struct NumericInfo {
+    code: u16,
+    name: &'static str,
+    origin: NumericOrigin,
+    params: &'static str,
+    description: &'static str,
+}
+
+impl NumericInfo {
+    pub fn code (&self) -> u16 {
+        self.code
+    }
+
+    pub fn name (&self) -> &'static str {
+        self.name
+    }
+
+    pub fn origin (&self) -> NumericOrigin {
+        self.origin
+    }
+
+    pub fn params (&self) -> &'static str {
+        self.params
+    }
+
+    pub fn description (&self) -> &'static str {
+        self.description
+    }
+}
+
+// finds the usual name for a numeric. some codes have been used for
+// different things by different servers; in that case, this returns the
+// meaning most servers use today.
+pub fn numeric (code: u16) -> Option<&'static NumericInfo> {
+    NUMERICS.iter().find(|n| n.code == code)
+}
+
+pub fn numeric_by_name (name: &str) -> Option<&'static NumericInfo> {
+    NUMERICS.iter().find(|n| n.name.eq_ignore_ascii_case(name))
+}
+
+// all of the meanings a code has had
+pub fn numerics_for_code (code: u16) -> Vec<&'static NumericInfo> {
+    NUMERICS.iter().filter(|n| n.code == code).collect()
+}
+
+// the kinds of request a numeric can be part of the response to
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum NumericFamily {
+    WelcomeReplies,
+    LusersReplies,
+    MotdReplies,
+    WhoisReplies,
+    WhowasReplies,
+    WhoReplies,
+    NamesReplies,
+    ListReplies,
+    TopicReplies,
+    BanListReplies,
+    ExceptListReplies,
+    InviteListReplies,
+    StatsReplies,
+    TraceReplies,
+    LinksReplies,
+    InfoReplies,
+    AdminReplies,
+    UsersReplies,
+    HelpReplies,
+    MonitorReplies,
+    SaslReplies,
+}
+
+// 4xx and 5xx are errors, as is anything else the registry knows as an
+// ERR_ numeric (the sasl and monitor errors, for instance)
+pub fn is_error_numeric (code: u16) -> bool {
+    if code >= 400 && code < 600 {
+        return true;
+    }
+    match numeric(code) {
+        Some(info) => info.name.starts_with("ERR_"),
+        None => false,
+    }
+}
+
+// numerics which servers send on their own when a client connects, rather
+// than in response to a query
+pub fn is_registration_numeric (code: u16) -> bool {
+    match code {
+        1...5 | 42 | 250...255 | 265 | 266 | 372 | 375 | 376 | 396 | 422 => true,
+        _ => false,
+    }
+}
+
+pub fn numeric_family (code: u16) -> Option<NumericFamily> {
+    match code {
+        1...5 | 42 => Some(WelcomeReplies),
+        250...255 | 265 | 266 => Some(LusersReplies),
+        372 | 375 | 376 | 422 => Some(MotdReplies),
+        276 | 301 | 307 | 311 | 312 | 313 | 317 | 318 | 319 | 320 | 330 | 335
+            | 338 | 378 | 379 | 671 => Some(WhoisReplies),
+        314 | 369 | 406 => Some(WhowasReplies),
+        315 | 352 | 354 => Some(WhoReplies),
+        353 | 366 => Some(NamesReplies),
+        321 | 322 | 323 => Some(ListReplies),
+        331 | 332 | 333 => Some(TopicReplies),
+        367 | 368 => Some(BanListReplies),
+        348 | 349 => Some(ExceptListReplies),
+        346 | 347 => Some(InviteListReplies),
+        211...219 | 240...247 => Some(StatsReplies),
+        200...210 | 261 | 262 => Some(TraceReplies),
+        364 | 365 => Some(LinksReplies),
+        371 | 373 | 374 => Some(InfoReplies),
+        256...259 | 423 => Some(AdminReplies),
+        392...395 | 446 => Some(UsersReplies),
+        524 | 704 | 705 | 706 => Some(HelpReplies),
+        730...734 => Some(MonitorReplies),
+        900...908 => Some(SaslReplies),
+        _ => None,
+    }
+}
+
+// numerics which mark the end of a multi-line response. errors which
+// replace a whole response (like ERR_NOMOTD) count too.
+pub fn ends_response (code: u16) -> bool {
+    match code {
+        // RPL_ENDOFSTATS, RPL_SERVLISTEND, RPL_ADMINEMAIL, RPL_TRACEEND
+        219 | 235 | 259 | 262
+        // RPL_ENDOFWHO, RPL_ENDOFWHOIS, RPL_LISTEND, RPL_ENDOFINVITELIST,
+        // RPL_ENDOFEXCEPTLIST
+            | 315 | 318 | 323 | 347 | 349
+        // RPL_ENDOFLINKS, RPL_ENDOFNAMES, RPL_ENDOFBANLIST, RPL_ENDOFWHOWAS,
+        // RPL_ENDOFINFO, RPL_ENDOFMOTD, RPL_ENDOFUSERS, RPL_NOUSERS
+            | 365 | 366 | 368 | 369 | 374 | 376 | 394 | 395
+        // ERR_NOMOTD, ERR_HELPNOTFOUND, RPL_ENDOFHELP, RPL_ENDOFMONLIST
+            | 422 | 524 | 706 | 733
+        // the end of sasl authentication, one way or another
+            | 903 | 904 | 905 | 906 | 907 => true,
+        _ => false,
+    }
+}
+
+// sorted by code. where a code has more than one meaning, the one in
+// common use comes first.
+pub static NUMERICS: &'static [NumericInfo] = &[
+    NumericInfo { code: 1, name: "RPL_WELCOME", origin: Rfc2812, params: "<client> :<text>", description: "the first reply after registration succeeds" },
+    NumericInfo { code: 2, name: "RPL_YOURHOST", origin: Rfc2812, params: "<client> :<text>", description: "the name and version of the server" },
+    NumericInfo { code: 3, name: "RPL_CREATED", origin: Rfc2812, params: "<client> :<text>", description: "when the server was started" },
+    NumericInfo { code: 4, name: "RPL_MYINFO", origin: Rfc2812, params: "<client> <servername> <version> <user modes> <channel modes> [<channel modes with a parameter>]", description: "the server's name, version, and supported modes" },
+    NumericInfo { code: 5, name: "RPL_ISUPPORT", origin: Ircu, params: "<client> <token>{ <token>} :are supported by this server", description: "the features the server supports" },
+    NumericInfo { code: 5, name: "RPL_BOUNCE", origin: Rfc2812, params: "<client> :Try server <server name>, port <port number>", description: "another server to connect to instead" },
+    NumericInfo { code: 10, name: "RPL_REDIR", origin: Hybrid, params: "<client> <hostname> <port> :<info>", description: "another server to connect to instead" },
+    NumericInfo { code: 42, name: "RPL_YOURID", origin: Rfc2812, params: "<client> <id> :your unique ID", description: "the client's unique id on the network" },
+
+    NumericInfo { code: 200, name: "RPL_TRACELINK", origin: Rfc1459, params: "<client> Link <version> <destination> <next server> [V<protocol version> <link uptime> <backstream sendq> <upstream sendq>]", description: "a server link in a TRACE" },
+    NumericInfo { code: 201, name: "RPL_TRACECONNECTING", origin: Rfc1459, params: "<client> Try. <class> <server>", description: "a connection which hasn't been established yet in a TRACE" },
+    NumericInfo { code: 202, name: "RPL_TRACEHANDSHAKE", origin: Rfc1459, params: "<client> H.S. <class> <server>", description: "a server handshake in a TRACE" },
+    NumericInfo { code: 203, name: "RPL_TRACEUNKNOWN", origin: Rfc1459, params: "<client> ???? <class> [<connection address>]", description: "an unregistered connection in a TRACE" },
+    NumericInfo { code: 204, name: "RPL_TRACEOPERATOR", origin: Rfc1459, params: "<client> Oper <class> <nick>", description: "an operator in a TRACE" },
+    NumericInfo { code: 205, name: "RPL_TRACEUSER", origin: Rfc1459, params: "<client> User <class> <nick>", description: "a user in a TRACE" },
+    NumericInfo { code: 206, name: "RPL_TRACESERVER", origin: Rfc1459, params: "<client> Serv <class> <int>S <int>C <server> <nick!user|*!*>@<host|server> V<protocol version>", description: "a server in a TRACE" },
+    NumericInfo { code: 207, name: "RPL_TRACESERVICE", origin: Rfc2812, params: "<client> Service <class> <name> <type> <active type>", description: "a service in a TRACE" },
+    NumericInfo { code: 208, name: "RPL_TRACENEWTYPE", origin: Rfc1459, params: "<client> <newtype> 0 <client name>", description: "a connection of an unknown type in a TRACE" },
+    NumericInfo { code: 209, name: "RPL_TRACECLASS", origin: Rfc2812, params: "<client> Class <class> <count>", description: "a connection class in a TRACE" },
+    NumericInfo { code: 210, name: "RPL_TRACERECONNECT", origin: Rfc2812, params: "<client> Retry. <class> <server>", description: "unused" },
+    NumericInfo { code: 211, name: "RPL_STATSLINKINFO", origin: Rfc1459, params: "<client> <linkname> <sendq> <sent messages> <sent kbytes> <received messages> <received kbytes> <time open>", description: "a server link in a STATS l" },
+    NumericInfo { code: 212, name: "RPL_STATSCOMMANDS", origin: Rfc1459, params: "<client> <command> <count> [<byte count> <remote count>]", description: "command usage in a STATS m" },
+    NumericInfo { code: 213, name: "RPL_STATSCLINE", origin: Rfc1459, params: "<client> C <host> * <name> <port> <class>", description: "a connect line in a STATS c" },
+    NumericInfo { code: 214, name: "RPL_STATSNLINE", origin: Rfc1459, params: "<client> N <host> * <name> <port> <class>", description: "a server line in a STATS n" },
+    NumericInfo { code: 215, name: "RPL_STATSILINE", origin: Rfc1459, params: "<client> I <host> * <host> <port> <class>", description: "an auth line in a STATS i" },
+    NumericInfo { code: 216, name: "RPL_STATSKLINE", origin: Rfc1459, params: "<client> K <host> * <username> <port> <class>", description: "a ban in a STATS k" },
+    NumericInfo { code: 217, name: "RPL_STATSQLINE", origin: Rfc1459, params: "<client> Q <host> * <name> <port> <class>", description: "a reserved name in a STATS q" },
+    NumericInfo { code: 218, name: "RPL_STATSYLINE", origin: Rfc1459, params: "<client> Y <class> <ping frequency> <connect frequency> <max sendq>", description: "a connection class in a STATS y" },
+    NumericInfo { code: 219, name: "RPL_ENDOFSTATS", origin: Rfc1459, params: "<client> <stats letter> :End of /STATS report", description: "the end of a STATS reply" },
+    NumericInfo { code: 221, name: "RPL_UMODEIS", origin: Rfc1459, params: "<client> <user modes> [<mode params>]", description: "the client's current user modes" },
+    NumericInfo { code: 231, name: "RPL_SERVICEINFO", origin: Rfc1459, params: "<client>", description: "unused" },
+    NumericInfo { code: 232, name: "RPL_ENDOFSERVICES", origin: Rfc1459, params: "<client>", description: "unused" },
+    NumericInfo { code: 233, name: "RPL_SERVICE", origin: Rfc1459, params: "<client>", description: "unused" },
+    NumericInfo { code: 234, name: "RPL_SERVLIST", origin: Rfc2812, params: "<client> <name> <server> <mask> <type> <hopcount> <info>", description: "a service in a SERVLIST" },
+    NumericInfo { code: 235, name: "RPL_SERVLISTEND", origin: Rfc2812, params: "<client> <mask> <type> :End of service listing", description: "the end of a SERVLIST" },
+    NumericInfo { code: 240, name: "RPL_STATSVLINE", origin: Rfc2812, params: "<client>", description: "unused" },
+    NumericInfo { code: 241, name: "RPL_STATSLLINE", origin: Rfc1459, params: "<client> L <hostmask> * <servername> <maxdepth>", description: "a leaf line in a STATS l" },
+    NumericInfo { code: 242, name: "RPL_STATSUPTIME", origin: Rfc1459, params: "<client> :Server Up <days> days <hours>:<minutes>:<seconds>", description: "the server's uptime in a STATS u" },
+    NumericInfo { code: 243, name: "RPL_STATSOLINE", origin: Rfc1459, params: "<client> O <hostmask> * <name> [<port> <class>]", description: "an operator block in a STATS o" },
+    NumericInfo { code: 244, name: "RPL_STATSHLINE", origin: Rfc1459, params: "<client> H <hostmask> * <servername>", description: "a hub line in a STATS h" },
+    NumericInfo { code: 246, name: "RPL_STATSPING", origin: Rfc2812, params: "<client>", description: "unused" },
+    NumericInfo { code: 247, name: "RPL_STATSBLINE", origin: Rfc2812, params: "<client>", description: "unused" },
+    NumericInfo { code: 250, name: "RPL_STATSCONN", origin: Ircu, params: "<client> :Highest connection count: <count> (<client count> clients)", description: "the highest number of connections seen" },
+    NumericInfo { code: 250, name: "RPL_STATSDLINE", origin: Rfc2812, params: "<client>", description: "unused" },
+    NumericInfo { code: 251, name: "RPL_LUSERCLIENT", origin: Rfc1459, params: "<client> :There are <u> users and <i> invisible on <s> servers", description: "the number of users on the network" },
+    NumericInfo { code: 252, name: "RPL_LUSEROP", origin: Rfc1459, params: "<client> <ops> :operator(s) online", description: "the number of operators online" },
+    NumericInfo { code: 253, name: "RPL_LUSERUNKNOWN", origin: Rfc1459, params: "<client> <connections> :unknown connection(s)", description: "the number of unregistered connections" },
+    NumericInfo { code: 254, name: "RPL_LUSERCHANNELS", origin: Rfc1459, params: "<client> <channels> :channels formed", description: "the number of channels" },
+    NumericInfo { code: 255, name: "RPL_LUSERME", origin: Rfc1459, params: "<client> :I have <c> clients and <s> servers", description: "the number of clients and servers on this server" },
+    NumericInfo { code: 256, name: "RPL_ADMINME", origin: Rfc1459, params: "<client> [<server>] :Administrative info", description: "the start of an ADMIN reply" },
+    NumericInfo { code: 257, name: "RPL_ADMINLOC1", origin: Rfc1459, params: "<client> :<info>", description: "where the server is" },
+    NumericInfo { code: 258, name: "RPL_ADMINLOC2", origin: Rfc1459, params: "<client> :<info>", description: "who runs the server" },
+    NumericInfo { code: 259, name: "RPL_ADMINEMAIL", origin: Rfc1459, params: "<client> :<info>", description: "the server administrator's email address" },
+    NumericInfo { code: 261, name: "RPL_TRACELOG", origin: Rfc1459, params: "<client> File <logfile> <debug level>", description: "a log file in a TRACE" },
+    NumericInfo { code: 262, name: "RPL_TRACEEND", origin: Rfc2812, params: "<client> <server name> <version> :End of TRACE", description: "the end of a TRACE" },
+    NumericInfo { code: 263, name: "RPL_TRYAGAIN", origin: Rfc2812, params: "<client> <command> :Please wait a while and try again.", description: "the command was dropped because the server is busy" },
+    NumericInfo { code: 265, name: "RPL_LOCALUSERS", origin: Hybrid, params: "<client> [<u> <m>] :Current local users <u>, max <m>", description: "the current and highest number of users on this server" },
+    NumericInfo { code: 266, name: "RPL_GLOBALUSERS", origin: Hybrid, params: "<client> [<u> <m>] :Current global users <u>, max <m>", description: "the current and highest number of users on the network" },
+    NumericInfo { code: 276, name: "RPL_WHOISCERTFP", origin: Hybrid, params: "<client> <nick> :has client certificate fingerprint <fingerprint>", description: "the fingerprint of a user's tls certificate in a WHOIS" },
+
+    NumericInfo { code: 300, name: "RPL_NONE", origin: Rfc1459, params: "<client>", description: "unused" },
+    NumericInfo { code: 301, name: "RPL_AWAY", origin: Rfc1459, params: "<client> <nick> :<message>", description: "the target of a message or WHOIS is away" },
+    NumericInfo { code: 302, name: "RPL_USERHOST", origin: Rfc1459, params: "<client> :[<reply>{ <reply>}]", description: "the reply to USERHOST" },
+    NumericInfo { code: 303, name: "RPL_ISON", origin: Rfc1459, params: "<client> :[<nickname>{ <nickname>}]", description: "the reply to ISON" },
+    NumericInfo { code: 305, name: "RPL_UNAWAY", origin: Rfc1459, params: "<client> :You are no longer marked as being away", description: "the client is no longer away" },
+    NumericInfo { code: 306, name: "RPL_NOWAWAY", origin: Rfc1459, params: "<client> :You have been marked as being away", description: "the client is now away" },
+    NumericInfo { code: 307, name: "RPL_WHOISREGNICK", origin: UnrealIrcd, params: "<client> <nick> :has identified for this nick", description: "a user is logged in to their nick in a WHOIS" },
+    NumericInfo { code: 311, name: "RPL_WHOISUSER", origin: Rfc1459, params: "<client> <nick> <username> <host> * :<realname>", description: "the start of a WHOIS" },
+    NumericInfo { code: 312, name: "RPL_WHOISSERVER", origin: Rfc1459, params: "<client> <nick> <server> :<server info>", description: "the server a user is on in a WHOIS" },
+    NumericInfo { code: 313, name: "RPL_WHOISOPERATOR", origin: Rfc1459, params: "<client> <nick> :is an IRC operator", description: "a user is an operator in a WHOIS" },
+    NumericInfo { code: 314, name: "RPL_WHOWASUSER", origin: Rfc1459, params: "<client> <nick> <username> <host> * :<realname>", description: "a former user in a WHOWAS" },
+    NumericInfo { code: 315, name: "RPL_ENDOFWHO", origin: Rfc1459, params: "<client> <mask> :End of WHO list", description: "the end of a WHO" },
+    NumericInfo { code: 316, name: "RPL_WHOISCHANOP", origin: Rfc1459, params: "<client>", description: "unused" },
+    NumericInfo { code: 317, name: "RPL_WHOISIDLE", origin: Rfc1459, params: "<client> <nick> <secs> [<signon>] :seconds idle, signon time", description: "how long a user has been idle in a WHOIS" },
+    NumericInfo { code: 318, name: "RPL_ENDOFWHOIS", origin: Rfc1459, params: "<client> <nick> :End of /WHOIS list", description: "the end of a WHOIS" },
+    NumericInfo { code: 319, name: "RPL_WHOISCHANNELS", origin: Rfc1459, params: "<client> <nick> :[prefix]<channel>{ [prefix]<channel>}", description: "the channels a user is in in a WHOIS" },
+    NumericInfo { code: 320, name: "RPL_WHOISSPECIAL", origin: UnrealIrcd, params: "<client> <nick> :<text>", description: "extra information about a user in a WHOIS" },
+    NumericInfo { code: 321, name: "RPL_LISTSTART", origin: Rfc1459, params: "<client> Channel :Users  Name", description: "the start of a LIST" },
+    NumericInfo { code: 322, name: "RPL_LIST", origin: Rfc1459, params: "<client> <channel> <client count> :<topic>", description: "a channel in a LIST" },
+    NumericInfo { code: 323, name: "RPL_LISTEND", origin: Rfc1459, params: "<client> :End of /LIST", description: "the end of a LIST" },
+    NumericInfo { code: 324, name: "RPL_CHANNELMODEIS", origin: Rfc1459, params: "<client> <channel> <modestring> <mode arguments>...", description: "a channel's current modes" },
+    NumericInfo { code: 325, name: "RPL_UNIQOPIS", origin: Rfc2812, params: "<client> <channel> <nickname>", description: "the creator of a safe channel" },
+    NumericInfo { code: 329, name: "RPL_CREATIONTIME", origin: Bahamut, params: "<client> <channel> <creationtime>", description: "when a channel was created, in seconds since the epoch" },
+    NumericInfo { code: 330, name: "RPL_WHOISACCOUNT", origin: Ircu, params: "<client> <nick> <account> :is logged in as", description: "the account a user is logged in to in a WHOIS" },
+    NumericInfo { code: 331, name: "RPL_NOTOPIC", origin: Rfc1459, params: "<client> <channel> :No topic is set", description: "a channel has no topic" },
+    NumericInfo { code: 332, name: "RPL_TOPIC", origin: Rfc1459, params: "<client> <channel> :<topic>", description: "a channel's topic" },
+    NumericInfo { code: 333, name: "RPL_TOPICWHOTIME", origin: Ircu, params: "<client> <channel> <nick> <setat>", description: "who set a channel's topic, and when, in seconds since the epoch" },
+    NumericInfo { code: 335, name: "RPL_WHOISBOT", origin: UnrealIrcd, params: "<client> <nick> :<message>", description: "a user is a bot in a WHOIS" },
+    NumericInfo { code: 338, name: "RPL_WHOISACTUALLY", origin: Ircu, params: "<client> <nick> [<host|ip>] :Is actually using host", description: "the real host or ip of a user in a WHOIS" },
+    NumericInfo { code: 341, name: "RPL_INVITING", origin: Rfc1459, params: "<client> <nick> <channel>", description: "an INVITE was sent" },
+    NumericInfo { code: 342, name: "RPL_SUMMONING", origin: Rfc1459, params: "<client> <user> :Summoning user to IRC", description: "a SUMMON was sent" },
+    NumericInfo { code: 346, name: "RPL_INVITELIST", origin: Rfc2812, params: "<client> <channel> <mask>", description: "an invite exception in a channel's list" },
+    NumericInfo { code: 347, name: "RPL_ENDOFINVITELIST", origin: Rfc2812, params: "<client> <channel> :End of channel invite list", description: "the end of a channel's invite exceptions" },
+    NumericInfo { code: 348, name: "RPL_EXCEPTLIST", origin: Rfc2812, params: "<client> <channel> <mask>", description: "a ban exception in a channel's list" },
+    NumericInfo { code: 349, name: "RPL_ENDOFEXCEPTLIST", origin: Rfc2812, params: "<client> <channel> :End of channel exception list", description: "the end of a channel's ban exceptions" },
+    NumericInfo { code: 351, name: "RPL_VERSION", origin: Rfc1459, params: "<client> <version> <server> :<comments>", description: "the reply to VERSION" },
+    NumericInfo { code: 352, name: "RPL_WHOREPLY", origin: Rfc1459, params: "<client> <channel> <username> <host> <server> <nick> <flags> :<hopcount> <realname>", description: "a user in a WHO" },
+    NumericInfo { code: 353, name: "RPL_NAMREPLY", origin: Rfc1459, params: "<client> <symbol> <channel> :[prefix]<nick>{ [prefix]<nick>}", description: "users in a channel in a NAMES" },
+    NumericInfo { code: 354, name: "RPL_WHOSPCRPL", origin: Ircu, params: "<client> [<token>] <requested fields>...", description: "a user in a WHOX" },
+    NumericInfo { code: 361, name: "RPL_KILLDONE", origin: Rfc1459, params: "<client>", description: "unused" },
+    NumericInfo { code: 362, name: "RPL_CLOSING", origin: Rfc1459, params: "<client>", description: "unused" },
+    NumericInfo { code: 363, name: "RPL_CLOSEEND", origin: Rfc1459, params: "<client>", description: "unused" },
+    NumericInfo { code: 364, name: "RPL_LINKS", origin: Rfc1459, params: "<client> * <server> :<hopcount> <server info>", description: "a server in a LINKS" },
+    NumericInfo { code: 365, name: "RPL_ENDOFLINKS", origin: Rfc1459, params: "<client> * :End of /LINKS list", description: "the end of a LINKS" },
+    NumericInfo { code: 366, name: "RPL_ENDOFNAMES", origin: Rfc1459, params: "<client> <channel> :End of /NAMES list", description: "the end of a NAMES" },
+    NumericInfo { code: 367, name: "RPL_BANLIST", origin: Rfc1459, params: "<client> <channel> <mask> [<who> <set-ts>]", description: "a ban in a channel's list" },
+    NumericInfo { code: 368, name: "RPL_ENDOFBANLIST", origin: Rfc1459, params: "<client> <channel> :End of channel ban list", description: "the end of a channel's bans" },
+    NumericInfo { code: 369, name: "RPL_ENDOFWHOWAS", origin: Rfc1459, params: "<client> <nick> :End of WHOWAS", description: "the end of a WHOWAS" },
+    NumericInfo { code: 371, name: "RPL_INFO", origin: Rfc1459, params: "<client> :<string>", description: "a line of an INFO" },
+    NumericInfo { code: 372, name: "RPL_MOTD", origin: Rfc1459, params: "<client> :<line of the motd>", description: "a line of the message of the day" },
+    NumericInfo { code: 373, name: "RPL_INFOSTART", origin: Rfc1459, params: "<client>", description: "unused" },
+    NumericInfo { code: 374, name: "RPL_ENDOFINFO", origin: Rfc1459, params: "<client> :End of INFO list", description: "the end of an INFO" },
+    NumericInfo { code: 375, name: "RPL_MOTDSTART", origin: Rfc1459, params: "<client> :- <server> Message of the day - ", description: "the start of the message of the day" },
+    NumericInfo { code: 376, name: "RPL_ENDOFMOTD", origin: Rfc1459, params: "<client> :End of /MOTD command.", description: "the end of the message of the day" },
+    NumericInfo { code: 378, name: "RPL_WHOISHOST", origin: UnrealIrcd, params: "<client> <nick> :is connecting from *@localhost 127.0.0.1", description: "the host a user is connecting from in a WHOIS" },
+    NumericInfo { code: 379, name: "RPL_WHOISMODES", origin: UnrealIrcd, params: "<client> <nick> :is using modes <modes>", description: "a user's modes in a WHOIS" },
+    NumericInfo { code: 381, name: "RPL_YOUREOPER", origin: Rfc1459, params: "<client> :You are now an IRC operator", description: "an OPER succeeded" },
+    NumericInfo { code: 382, name: "RPL_REHASHING", origin: Rfc1459, params: "<client> <config file> :Rehashing", description: "the server is reloading its configuration" },
+    NumericInfo { code: 383, name: "RPL_YOURESERVICE", origin: Rfc2812, params: "<client> :You are service <servicename>", description: "registration as a service succeeded" },
+    NumericInfo { code: 384, name: "RPL_MYPORTIS", origin: Rfc1459, params: "<client>", description: "unused" },
+    NumericInfo { code: 391, name: "RPL_TIME", origin: Rfc1459, params: "<client> <server> [<timestamp> [<TS offset>]] :<human-readable time>", description: "the reply to TIME" },
+    NumericInfo { code: 392, name: "RPL_USERSSTART", origin: Rfc1459, params: "<client> :UserID   Terminal  Host", description: "the start of a USERS" },
+    NumericInfo { code: 393, name: "RPL_USERS", origin: Rfc1459, params: "<client> :<username> <ttyline> <hostname>", description: "a user in a USERS" },
+    NumericInfo { code: 394, name: "RPL_ENDOFUSERS", origin: Rfc1459, params: "<client> :End of users", description: "the end of a USERS" },
+    NumericInfo { code: 395, name: "RPL_NOUSERS", origin: Rfc1459, params: "<client> :Nobody logged in", description: "nobody is logged in for USERS" },
+    NumericInfo { code: 396, name: "RPL_VISIBLEHOST", origin: Ircu, params: "<client> <hostname> :is now your displayed host", description: "the client's host has been changed" },
+
+    NumericInfo { code: 400, name: "ERR_UNKNOWNERROR", origin: Ircv3, params: "<client> <command>{ <subcommand>} :<info>", description: "a command failed for an unspecified reason" },
+    NumericInfo { code: 401, name: "ERR_NOSUCHNICK", origin: Rfc1459, params: "<client> <nickname> :No such nick/channel", description: "the given nick doesn't exist" },
+    NumericInfo { code: 402, name: "ERR_NOSUCHSERVER", origin: Rfc1459, params: "<client> <server name> :No such server", description: "the given server doesn't exist" },
+    NumericInfo { code: 403, name: "ERR_NOSUCHCHANNEL", origin: Rfc1459, params: "<client> <channel> :No such channel", description: "the given channel doesn't exist" },
+    NumericInfo { code: 404, name: "ERR_CANNOTSENDTOCHAN", origin: Rfc1459, params: "<client> <channel> :Cannot send to channel", description: "a message couldn't be delivered to a channel" },
+    NumericInfo { code: 405, name: "ERR_TOOMANYCHANNELS", origin: Rfc1459, params: "<client> <channel> :You have joined too many channels", description: "the client is in too many channels to join another" },
+    NumericInfo { code: 406, name: "ERR_WASNOSUCHNICK", origin: Rfc1459, params: "<client> <nickname> :There was no such nickname", description: "WHOWAS found nothing for the given nick" },
+    NumericInfo { code: 407, name: "ERR_TOOMANYTARGETS", origin: Rfc1459, params: "<client> <target> :Duplicate recipients. No message delivered", description: "a message had too many targets" },
+    NumericInfo { code: 408, name: "ERR_NOSUCHSERVICE", origin: Rfc2812, params: "<client> <service name> :No such service", description: "the given service doesn't exist" },
+    NumericInfo { code: 409, name: "ERR_NOORIGIN", origin: Rfc1459, params: "<client> :No origin specified", description: "a PING or PONG was missing its parameter" },
+    NumericInfo { code: 411, name: "ERR_NORECIPIENT", origin: Rfc1459, params: "<client> :No recipient given (<command>)", description: "a message had no target" },
+    NumericInfo { code: 412, name: "ERR_NOTEXTTOSEND", origin: Rfc1459, params: "<client> :No text to send", description: "a message had no text" },
+    NumericInfo { code: 413, name: "ERR_NOTOPLEVEL", origin: Rfc1459, params: "<client> <mask> :No toplevel domain specified", description: "a host mask target was invalid" },
+    NumericInfo { code: 414, name: "ERR_WILDTOPLEVEL", origin: Rfc1459, params: "<client> <mask> :Wildcard in toplevel domain", description: "a host mask target was invalid" },
+    NumericInfo { code: 415, name: "ERR_BADMASK", origin: Rfc2812, params: "<client> <mask> :Bad Server/host mask", description: "a server or host mask target was invalid" },
+    NumericInfo { code: 417, name: "ERR_INPUTTOOLONG", origin: Ircv3, params: "<client> :Input line was too long", description: "a line the client sent was too long" },
+    NumericInfo { code: 421, name: "ERR_UNKNOWNCOMMAND", origin: Rfc1459, params: "<client> <command> :Unknown command", description: "the server doesn't know the command" },
+    NumericInfo { code: 422, name: "ERR_NOMOTD", origin: Rfc1459, params: "<client> :MOTD File is missing", description: "there is no message of the day" },
+    NumericInfo { code: 423, name: "ERR_NOADMININFO", origin: Rfc1459, params: "<client> <server> :No administrative info available", description: "the server has no ADMIN information" },
+    NumericInfo { code: 424, name: "ERR_FILEERROR", origin: Rfc1459, params: "<client> :File error doing <file op> on <file>", description: "a file operation on the server failed" },
+    NumericInfo { code: 431, name: "ERR_NONICKNAMEGIVEN", origin: Rfc1459, params: "<client> :No nickname given", description: "NICK was sent without a nick" },
+    NumericInfo { code: 432, name: "ERR_ERRONEUSNICKNAME", origin: Rfc1459, params: "<client> <nick> :Erroneus nickname", description: "the requested nick isn't valid" },
+    NumericInfo { code: 433, name: "ERR_NICKNAMEINUSE", origin: Rfc1459, params: "<client> <nick> :Nickname is already in use", description: "the requested nick is taken" },
+    NumericInfo { code: 436, name: "ERR_NICKCOLLISION", origin: Rfc1459, params: "<client> <nick> :Nickname collision KILL from <user>@<host>", description: "two servers gave the same nick to different users" },
+    NumericInfo { code: 437, name: "ERR_UNAVAILRESOURCE", origin: Rfc2812, params: "<client> <nick/channel> :Nick/channel is temporarily unavailable", description: "the nick or channel is blocked for now" },
+    NumericInfo { code: 441, name: "ERR_USERNOTINCHANNEL", origin: Rfc1459, params: "<client> <nick> <channel> :They aren't on that channel", description: "the target of a channel operation isn't in the channel" },
+    NumericInfo { code: 442, name: "ERR_NOTONCHANNEL", origin: Rfc1459, params: "<client> <channel> :You're not on that channel", description: "the client isn't in the channel" },
+    NumericInfo { code: 443, name: "ERR_USERONCHANNEL", origin: Rfc1459, params: "<client> <nick> <channel> :is already on channel", description: "the invited user is already in the channel" },
+    NumericInfo { code: 444, name: "ERR_NOLOGIN", origin: Rfc1459, params: "<client> <user> :User not logged in", description: "SUMMON found nobody" },
+    NumericInfo { code: 445, name: "ERR_SUMMONDISABLED", origin: Rfc1459, params: "<client> :SUMMON has been disabled", description: "SUMMON isn't supported" },
+    NumericInfo { code: 446, name: "ERR_USERSDISABLED", origin: Rfc1459, params: "<client> :USERS has been disabled", description: "USERS isn't supported" },
+    NumericInfo { code: 451, name: "ERR_NOTREGISTERED", origin: Rfc1459, params: "<client> :You have not registered", description: "the command needs the client to be registered" },
+    NumericInfo { code: 461, name: "ERR_NEEDMOREPARAMS", origin: Rfc1459, params: "<client> <command> :Not enough parameters", description: "a command was missing parameters" },
+    NumericInfo { code: 462, name: "ERR_ALREADYREGISTERED", origin: Rfc1459, params: "<client> :You may not reregister", description: "registration commands were sent after registering" },
+    NumericInfo { code: 463, name: "ERR_NOPERMFORHOST", origin: Rfc1459, params: "<client> :Your host isn't among the privileged", description: "the server doesn't accept connections from this host" },
+    NumericInfo { code: 464, name: "ERR_PASSWDMISMATCH", origin: Rfc1459, params: "<client> :Password incorrect", description: "the connection password was wrong or missing" },
+    NumericInfo { code: 465, name: "ERR_YOUREBANNEDCREEP", origin: Rfc1459, params: "<client> :You are banned from this server.", description: "the client is banned from the server" },
+    NumericInfo { code: 466, name: "ERR_YOUWILLBEBANNED", origin: Rfc1459, params: "<client>", description: "the client is about to be banned" },
+    NumericInfo { code: 467, name: "ERR_KEYSET", origin: Rfc1459, params: "<client> <channel> :Channel key already set", description: "the channel already has a key" },
+    NumericInfo { code: 471, name: "ERR_CHANNELISFULL", origin: Rfc1459, params: "<client> <channel> :Cannot join channel (+l)", description: "the channel's user limit has been reached" },
+    NumericInfo { code: 472, name: "ERR_UNKNOWNMODE", origin: Rfc1459, params: "<client> <modechar> :is unknown mode char to me", description: "the server doesn't know the mode" },
+    NumericInfo { code: 473, name: "ERR_INVITEONLYCHAN", origin: Rfc1459, params: "<client> <channel> :Cannot join channel (+i)", description: "the channel is invite only" },
+    NumericInfo { code: 474, name: "ERR_BANNEDFROMCHAN", origin: Rfc1459, params: "<client> <channel> :Cannot join channel (+b)", description: "the client is banned from the channel" },
+    NumericInfo { code: 475, name: "ERR_BADCHANNELKEY", origin: Rfc1459, params: "<client> <channel> :Cannot join channel (+k)", description: "the channel key was wrong or missing" },
+    NumericInfo { code: 476, name: "ERR_BADCHANMASK", origin: Rfc2812, params: "<channel> :Bad Channel Mask", description: "the channel name isn't valid" },
+    NumericInfo { code: 477, name: "ERR_NOCHANMODES", origin: Rfc2812, params: "<client> <channel> :Channel doesn't support modes", description: "the channel doesn't support modes" },
+    NumericInfo { code: 478, name: "ERR_BANLISTFULL", origin: Rfc2812, params: "<client> <channel> <char> :Channel list is full", description: "a channel list mode has no room left" },
+    NumericInfo { code: 481, name: "ERR_NOPRIVILEGES", origin: Rfc1459, params: "<client> :Permission Denied- You're not an IRC operator", description: "the command needs operator privileges" },
+    NumericInfo { code: 482, name: "ERR_CHANOPRIVSNEEDED", origin: Rfc1459, params: "<client> <channel> :You're not channel operator", description: "the command needs channel operator privileges" },
+    NumericInfo { code: 483, name: "ERR_CANTKILLSERVER", origin: Rfc1459, params: "<client> :You cant kill a server!", description: "KILL was used on a server" },
+    NumericInfo { code: 484, name: "ERR_RESTRICTED", origin: Rfc2812, params: "<client> :Your connection is restricted!", description: "the client's connection is restricted" },
+    NumericInfo { code: 485, name: "ERR_UNIQOPPRIVSNEEDED", origin: Rfc2812, params: "<client> :You're not the original channel operator", description: "the command needs the channel creator" },
+    NumericInfo { code: 491, name: "ERR_NOOPERHOST", origin: Rfc1459, params: "<client> :No O-lines for your host", description: "OPER isn't allowed from this host" },
+    NumericInfo { code: 492, name: "ERR_NOSERVICEHOST", origin: Rfc1459, params: "<client>", description: "unused" },
+
+    NumericInfo { code: 501, name: "ERR_UMODEUNKNOWNFLAG", origin: Rfc1459, params: "<client> :Unknown MODE flag", description: "the server doesn't know a user mode" },
+    NumericInfo { code: 502, name: "ERR_USERSDONTMATCH", origin: Rfc1459, params: "<client> :Cant change mode for other users", description: "the client tried to change another user's modes" },
+    NumericInfo { code: 505, name: "ERR_MSGFORBIDDEN", origin: Freenode, params: "<client> <nick> :Private messages from unregistered users are currently blocked", description: "the target only accepts messages from registered users" },
+    NumericInfo { code: 524, name: "ERR_HELPNOTFOUND", origin: Ratbox, params: "<client> <subject> :No help available on this topic", description: "HELP has nothing on the subject" },
+    NumericInfo { code: 525, name: "ERR_INVALIDKEY", origin: Ircv3, params: "<client> <target chan> :Key is not well-formed", description: "a channel key isn't valid" },
+
+    NumericInfo { code: 670, name: "RPL_STARTTLS", origin: Ircv3, params: "<client> :STARTTLS successful, proceed with TLS handshake", description: "the server is ready for the tls handshake" },
+    NumericInfo { code: 671, name: "RPL_WHOISSECURE", origin: UnrealIrcd, params: "<client> <nick> :is using a secure connection", description: "a user is connected with tls in a WHOIS" },
+    NumericInfo { code: 691, name: "ERR_STARTTLS", origin: Ircv3, params: "<client> :STARTTLS failed (Wrong moon phase)", description: "STARTTLS failed" },
+    NumericInfo { code: 696, name: "ERR_INVALIDMODEPARAM", origin: InspIrcd, params: "<client> <target chan/user> <mode char> <parameter> :<description>", description: "a mode parameter isn't valid" },
+    NumericInfo { code: 704, name: "RPL_HELPSTART", origin: Ratbox, params: "<client> <subject> :<first line of help section>", description: "the start of a HELP" },
+    NumericInfo { code: 705, name: "RPL_HELPTXT", origin: Ratbox, params: "<client> <subject> :<line of help text>", description: "a line of a HELP" },
+    NumericInfo { code: 706, name: "RPL_ENDOFHELP", origin: Ratbox, params: "<client> <subject> :<last line of help text>", description: "the end of a HELP" },
+    NumericInfo { code: 723, name: "ERR_NOPRIVS", origin: Ratbox, params: "<client> <priv> :Insufficient oper privileges.", description: "the client's operator block lacks a privilege" },
+    NumericInfo { code: 730, name: "RPL_MONONLINE", origin: Ircv3, params: "<client> :<target>{,<target>}", description: "monitored users are online" },
+    NumericInfo { code: 731, name: "RPL_MONOFFLINE", origin: Ircv3, params: "<client> :<target>{,<target>}", description: "monitored users are offline" },
+    NumericInfo { code: 732, name: "RPL_MONLIST", origin: Ircv3, params: "<client> :<target>{,<target>}", description: "entries in the monitor list" },
+    NumericInfo { code: 733, name: "RPL_ENDOFMONLIST", origin: Ircv3, params: "<client> :End of MONITOR list", description: "the end of the monitor list" },
+    NumericInfo { code: 734, name: "ERR_MONLISTFULL", origin: Ircv3, params: "<client> <limit> <targets> :Monitor list is full.", description: "the monitor list has no room left" },
+
+    NumericInfo { code: 900, name: "RPL_LOGGEDIN", origin: Ircv3, params: "<client> <nick>!<user>@<host> <account> :You are now logged in as <username>", description: "the client is now logged in to an account" },
+    NumericInfo { code: 901, name: "RPL_LOGGEDOUT", origin: Ircv3, params: "<client> <nick>!<user>@<host> :You are now logged out", description: "the client is no longer logged in" },
+    NumericInfo { code: 902, name: "ERR_NICKLOCKED", origin: Ircv3, params: "<client> :You must use a nick assigned to you", description: "sasl failed because the account is locked" },
+    NumericInfo { code: 903, name: "RPL_SASLSUCCESS", origin: Ircv3, params: "<client> :SASL authentication successful", description: "sasl authentication succeeded" },
+    NumericInfo { code: 904, name: "ERR_SASLFAIL", origin: Ircv3, params: "<client> :SASL authentication failed", description: "sasl authentication failed" },
+    NumericInfo { code: 905, name: "ERR_SASLTOOLONG", origin: Ircv3, params: "<client> :SASL message too long", description: "an AUTHENTICATE parameter was too long" },
+    NumericInfo { code: 906, name: "ERR_SASLABORTED", origin: Ircv3, params: "<client> :SASL authentication aborted", description: "sasl authentication was aborted" },
+    NumericInfo { code: 907, name: "ERR_SASLALREADY", origin: Ircv3, params: "<client> :You have already authenticated using SASL", description: "the client is already authenticated" },
+    NumericInfo { code: 908, name: "RPL_SASLMECHS", origin: Ircv3, params: "<client> <mechanisms> :are available SASL mechanisms", description: "the sasl mechanisms the server supports" },
+];
+
+#[test]
+fn test_numeric () {
+    let info = numeric(330).unwrap();
+    assert_eq!(info.name(), "RPL_WHOISACCOUNT");
+    assert_eq!(info.origin(), Ircu);
+    assert_eq!(numeric(5).unwrap().name(), "RPL_ISUPPORT");
+    assert_eq!(numerics_for_code(5).len(), 2);
+    assert!(numeric(999).is_none());
+
+    assert_eq!(numeric_by_name("ERR_SASLFAIL").map(|n| n.code()), Some(904));
+    assert_eq!(numeric_by_name("rpl_bounce").map(|n| n.code()), Some(5));
+    assert!(numeric_by_name("RPL_NOTANUMERIC").is_none());
+}
+
+#[test]
+fn test_numerics_sorted () {
+    for pair in NUMERICS.windows(2) {
+        assert!(pair[0].code <= pair[1].code, "{} is out of order", pair[1].name);
+    }
+    for info in NUMERICS.iter() {
+        assert!(info.code < 1000);
+        assert_eq!(numeric_by_name(info.name).map(|n| n.code), Some(info.code));
+    }
+}
+
+#[test]
+fn test_classification () {
+    assert!(is_error_numeric(433));
+    assert!(is_error_numeric(599));
+    assert!(is_error_numeric(904));
+    assert!(!is_error_numeric(903));
+    assert!(!is_error_numeric(1));
+    assert!(!is_error_numeric(330));
+
+    assert!(is_registration_numeric(5));
+    assert!(is_registration_numeric(376));
+    assert!(!is_registration_numeric(311));
+
+    assert_eq!(numeric_family(330), Some(WhoisReplies));
+    assert_eq!(numeric_family(366), Some(NamesReplies));
+    assert_eq!(numeric_family(212), Some(StatsReplies));
+    assert_eq!(numeric_family(999), None);
+
+    assert!(ends_response(318));
+    assert!(ends_response(422));
+    assert!(!ends_response(311));
+
+    // everything which ends a response belongs to some family
+    for info in NUMERICS.iter() {
+        if ends_response(info.code) && info.code != 235 {
+            assert!(numeric_family(info.code).is_some(), "{} has no family", info.name);
+        }
+    }
+}
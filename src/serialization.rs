@@ -0,0 +1,222 @@
+// Encodable and Decodable impls for messages, enabled by the serde feature.
+// with serialize::json, a message looks like this:
+//
+//   {
+//     "tags": [
+//       {"key": "time", "value": "2014-11-01T12:00:00.000Z"},
+//       {"key": "bot", "value": null}
+//     ],
+//     "source": {"raw": "nick!user@host", "nick": "nick", "user": "user", "host": "host"},
+//     "command": {"command": "330", "numeric": 330, "name": "RPL_WHOISACCOUNT"},
+//     "params": ["me", "nick", "account", "is logged in as"]
+//   }
+//
+// tags are a list rather than an object, since keys can repeat and order
+// matters. tag values are unescaped, and a tag without a value has a null
+// value. source is null if the message has no prefix. nick and user are null
+// when the prefix doesn't contain them, so a server name is only a host.
+//
+// command.command is the command as it appears on the wire. numeric and name
+// are only set for numeric replies, and name is null for numerics which
+// aren't in the registry. commands without their own MessageType variant are
+// kept as strings, in whatever case they were received in.
+//
+// only the raw source and command.command are read back when decoding, the
+// other fields are there for the benefit of consumers of the json.
+
+use serialize::{Decodable, Decoder, Encodable, Encoder};
+
+use constants::{MessageType, Reply};
+use message::{Message, Tag};
+use numerics::numeric;
+
+fn emit_opt_str<S: Encoder<E>, E> (s: &mut S, value: Option<&str>) -> Result<(), E> {
+    s.emit_option(|s| {
+        match value {
+            Some(v) => s.emit_option_some(|s| s.emit_str(v)),
+            None => s.emit_option_none(),
+        }
+    })
+}
+
+// splits nick!user@host into its parts
+fn parse_source (source: &str) -> (Option<&str>, Option<&str>, Option<&str>) {
+    let (rest, host) = match source.find('@') {
+        Some(idx) => (source.slice_to(idx), Some(source.slice_from(idx + 1))),
+        None => (source, None),
+    };
+    let (nick, user) = match rest.find('!') {
+        Some(idx) => (rest.slice_to(idx), Some(rest.slice_from(idx + 1))),
+        None => (rest, None),
+    };
+    if host.is_none() && user.is_none() && nick.contains(".") {
+        (None, None, Some(nick))
+    }
+    else {
+        (Some(nick), user, host)
+    }
+}
+
+impl<S: Encoder<E>, E> Encodable<S, E> for MessageType {
+    fn encode (&self, s: &mut S) -> Result<(), E> {
+        s.emit_struct("MessageType", 3, |s| {
+            try!(s.emit_struct_field("command", 0, |s| s.emit_str(self.to_command().as_slice())));
+            let code = match self {
+                &Reply(i) => Some(i),
+                _ => None,
+            };
+            try!(s.emit_struct_field("numeric", 1, |s| code.encode(s)));
+            let name = code.and_then(|i| numeric(i)).map(|info| info.name());
+            s.emit_struct_field("name", 2, |s| emit_opt_str(s, name))
+        })
+    }
+}
+
+impl<D: Decoder<E>, E> Decodable<D, E> for MessageType {
+    fn decode (d: &mut D) -> Result<MessageType, E> {
+        d.read_struct("MessageType", 3, |d| {
+            let command: String = try!(d.read_struct_field("command", 0, |d| Decodable::decode(d)));
+            match from_str(command.as_slice()) {
+                Some(t) => Ok(t),
+                None => Err(d.error("invalid command")),
+            }
+        })
+    }
+}
+
+impl<S: Encoder<E>, E> Encodable<S, E> for Message {
+    fn encode (&self, s: &mut S) -> Result<(), E> {
+        s.emit_struct("Message", 4, |s| {
+            try!(s.emit_struct_field("tags", 0, |s| {
+                s.emit_seq(self.tags().len(), |s| {
+                    for (i, &(ref key, ref value)) in self.tags().iter().enumerate() {
+                        try!(s.emit_seq_elt(i, |s| {
+                            s.emit_struct("Tag", 2, |s| {
+                                try!(s.emit_struct_field("key", 0, |s| s.emit_str(key.as_slice())));
+                                s.emit_struct_field("value", 1, |s| {
+                                    emit_opt_str(s, value.as_ref().map(|v| v.as_slice()))
+                                })
+                            })
+                        }));
+                    }
+                    Ok(())
+                })
+            }));
+
+            try!(s.emit_struct_field("source", 1, |s| {
+                s.emit_option(|s| {
+                    match *self.from() {
+                        Some(ref from) => {
+                            s.emit_option_some(|s| {
+                                let (nick, user, host) = parse_source(from.as_slice());
+                                s.emit_struct("Source", 4, |s| {
+                                    try!(s.emit_struct_field("raw", 0, |s| s.emit_str(from.as_slice())));
+                                    try!(s.emit_struct_field("nick", 1, |s| emit_opt_str(s, nick)));
+                                    try!(s.emit_struct_field("user", 2, |s| emit_opt_str(s, user)));
+                                    s.emit_struct_field("host", 3, |s| emit_opt_str(s, host))
+                                })
+                            })
+                        },
+                        None => s.emit_option_none(),
+                    }
+                })
+            }));
+
+            try!(s.emit_struct_field("command", 2, |s| self.message_type().encode(s)));
+            s.emit_struct_field("params", 3, |s| self.params().encode(s))
+        })
+    }
+}
+
+impl<D: Decoder<E>, E> Decodable<D, E> for Message {
+    fn decode (d: &mut D) -> Result<Message, E> {
+        d.read_struct("Message", 4, |d| {
+            let tags: Vec<Tag> = try!(d.read_struct_field("tags", 0, |d| {
+                d.read_seq(|d, len| {
+                    let mut tags = Vec::with_capacity(len);
+                    for i in range(0, len) {
+                        tags.push(try!(d.read_seq_elt(i, |d| {
+                            d.read_struct("Tag", 2, |d| {
+                                let key = try!(d.read_struct_field("key", 0, |d| Decodable::decode(d)));
+                                let value = try!(d.read_struct_field("value", 1, |d| Decodable::decode(d)));
+                                Ok((key, value))
+                            })
+                        })));
+                    }
+                    Ok(tags)
+                })
+            }));
+
+            let from: Option<String> = try!(d.read_struct_field("source", 1, |d| {
+                d.read_option(|d, present| {
+                    if present {
+                        d.read_struct("Source", 4, |d| {
+                            d.read_struct_field("raw", 0, |d| Decodable::decode(d)).map(|raw| Some(raw))
+                        })
+                    }
+                    else {
+                        Ok(None)
+                    }
+                })
+            }));
+
+            let message_type = try!(d.read_struct_field("command", 2, |d| Decodable::decode(d)));
+            let params = try!(d.read_struct_field("params", 3, |d| Decodable::decode(d)));
+
+            Ok(Message::new_with_tags(tags, from, message_type, params))
+        })
+    }
+}
+
+#[test]
+fn test_json_schema () {
+    use serialize::json;
+
+    let m = Message::parse(
+        "@time=2014-11-01T12:00:00.000Z;bot :irc.host 330 me nick account :is logged in as\r\n"
+    ).unwrap();
+    assert_eq!(
+        json::encode(&m).as_slice(),
+        concat!(
+            r#"{"tags":[{"key":"time","value":"2014-11-01T12:00:00.000Z"},{"key":"bot","value":null}],"#,
+            r#""source":{"raw":"irc.host","nick":null,"user":null,"host":"irc.host"},"#,
+            r#""command":{"command":"330","numeric":330,"name":"RPL_WHOISACCOUNT"},"#,
+            r#""params":["me","nick","account","is logged in as"]}"#
+        )
+    );
+
+    let m = Message::parse("PRIVMSG #chan :hi\r\n").unwrap();
+    assert_eq!(
+        json::encode(&m).as_slice(),
+        concat!(
+            r#"{"tags":[],"source":null,"#,
+            r#""command":{"command":"PRIVMSG","numeric":null,"name":null},"#,
+            r#""params":["#chan","hi"]}"#
+        )
+    );
+}
+
+#[test]
+fn test_json_round_trip () {
+    use serialize::json;
+
+    let lines = [
+        "@time=2014-11-01T12:00:00.000Z;msgid=a\\sb :nick!user@host PRIVMSG #chan :hello world\r\n",
+        ":nick@host NOTICE me :hi\r\n",
+        ":irc.host 999 me :unknown numeric\r\n",
+        ":irc.host 001 me :Welcome\r\n",
+        "FOO bar baz\r\n",
+        "@+typing=active TAGMSG #chan\r\n",
+    ];
+    for line in lines.iter() {
+        let m = Message::parse(*line).unwrap();
+        let json = json::encode(&m);
+        let decoded: Message = json::decode(json.as_slice()).unwrap();
+        assert_eq!(decoded, m);
+    }
+
+    assert_eq!(parse_source("nick!user@host"), (Some("nick"), Some("user"), Some("host")));
+    assert_eq!(parse_source("nick@host"), (Some("nick"), None, Some("host")));
+    assert_eq!(parse_source("nick"), (Some("nick"), None, None));
+    assert_eq!(parse_source("irc.example.com"), (None, None, Some("irc.example.com")));
+}
@@ -0,0 +1,131 @@
+use constants::{MessageType, Fail, Warn, Note};
+use message::{Message, MessageRef};
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum ReplyLevel {
+    ReplyFail,
+    ReplyWarn,
+    ReplyNote,
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub struct StandardReply {
+    level: ReplyLevel,
+    command: String,
+    code: String,
+    context: Vec<String>,
+    description: String,
+}
+
+impl StandardReply {
+    pub fn new (level: ReplyLevel, command: &str, code: &str, context: &[&str], description: &str) -> StandardReply {
+        StandardReply {
+            level: level,
+            command: command.to_string(),
+            code: code.to_string(),
+            context: context.iter().map(|s| s.to_string()).collect(),
+            description: description.to_string(),
+        }
+    }
+
+    pub fn from_message (m: &Message) -> Option<StandardReply> {
+        let params: Vec<&str> = m.params().iter().map(|s| s.as_slice()).collect();
+        StandardReply::from_parts(m.message_type(), params.as_slice())
+    }
+
+    pub fn from_message_ref (m: &MessageRef) -> Option<StandardReply> {
+        StandardReply::from_parts(m.message_type(), m.params())
+    }
+
+    fn from_parts (message_type: &MessageType, p: &[&str]) -> Option<StandardReply> {
+        let level = match *message_type {
+            Fail => ReplyFail,
+            Warn => ReplyWarn,
+            Note => ReplyNote,
+            _ => return None,
+        };
+
+        if p.len() < 3 {
+            return None;
+        }
+
+        Some(StandardReply {
+            level: level,
+            command: p[0].to_string(),
+            code: p[1].to_string(),
+            context: p.slice(2, p.len() - 1).iter().map(|s| s.to_string()).collect(),
+            description: p[p.len() - 1].to_string(),
+        })
+    }
+
+    pub fn to_message (&self) -> Message {
+        let message_type = match self.level {
+            ReplyFail => Fail,
+            ReplyWarn => Warn,
+            ReplyNote => Note,
+        };
+        let params = vec![self.command.clone(), self.code.clone()]
+            .append(self.context.as_slice())
+            .append([self.description.clone()]);
+        Message::new(None, message_type, params)
+    }
+
+    pub fn level (&self) -> ReplyLevel {
+        self.level.clone()
+    }
+
+    // "*" when the reply isn't related to a specific command
+    pub fn command (&self) -> &str {
+        self.command.as_slice()
+    }
+
+    pub fn code (&self) -> &str {
+        self.code.as_slice()
+    }
+
+    pub fn context (&self) -> &[String] {
+        self.context.as_slice()
+    }
+
+    pub fn description (&self) -> &str {
+        self.description.as_slice()
+    }
+}
+
+#[test]
+fn test_standard_reply () {
+    {
+        let m = Message::parse(":irc.example.com FAIL CHATHISTORY MESSAGE_ERROR the_given_command #chan :Messages could not be retrieved\r\n").unwrap();
+        let reply = StandardReply::from_message(&m).unwrap();
+        assert_eq!(reply.level(), ReplyFail);
+        assert_eq!(reply.command(), "CHATHISTORY");
+        assert_eq!(reply.code(), "MESSAGE_ERROR");
+        assert_eq!(reply.context(), ["the_given_command".to_string(), "#chan".to_string()].as_slice());
+        assert_eq!(reply.description(), "Messages could not be retrieved");
+    }
+
+    {
+        let m = Message::parse(":irc.example.com NOTE * OPER_MESSAGE :The message\r\n").unwrap();
+        let reply = StandardReply::from_message(&m).unwrap();
+        assert_eq!(reply.level(), ReplyNote);
+        assert_eq!(reply.command(), "*");
+        assert_eq!(reply.context().len(), 0);
+        assert_eq!(
+            reply.to_message().to_protocol_string().as_slice(),
+            "NOTE * OPER_MESSAGE :The message\r\n"
+        );
+    }
+
+    {
+        let m = Message::parse(":irc.example.com WARN REHASH\r\n").unwrap();
+        assert_eq!(StandardReply::from_message(&m), None);
+    }
+
+    {
+        let line = ":irc.example.com WARN REHASH CONFIG_BAD :Failed to reload\r\n";
+        assert_eq!(
+            StandardReply::from_message_ref(&MessageRef::parse(line).unwrap()),
+            StandardReply::from_message(&Message::parse(line).unwrap())
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use std::ascii::AsciiExt;
+use std::io;
+use std::io::fs::PathExtensions;
+use std::os;
+
+use time;
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub struct StsDirective {
+    port: Option<u16>,
+    duration: Option<i64>,
+    preload: bool,
+}
+
+impl StsDirective {
+    // parses the value of the sts cap, like "port=6697,duration=300"
+    pub fn parse (value: &str) -> StsDirective {
+        let mut directive = StsDirective { port: None, duration: None, preload: false };
+        for key in value.split(',') {
+            match key.find('=') {
+                Some(idx) => {
+                    match key.slice_to(idx) {
+                        "port" => directive.port = from_str(key.slice_from(idx + 1)),
+                        "duration" => directive.duration = from_str(key.slice_from(idx + 1)),
+                        _ => {},
+                    }
+                },
+                None => {
+                    if key == "preload" {
+                        directive.preload = true;
+                    }
+                },
+            }
+        }
+        directive
+    }
+
+    pub fn port (&self) -> Option<u16> {
+        self.port
+    }
+
+    pub fn duration (&self) -> Option<i64> {
+        self.duration
+    }
+
+    pub fn preload (&self) -> bool {
+        self.preload
+    }
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub struct StsPolicy {
+    host: String,
+    port: u16,
+    expires: i64,
+    preload: bool,
+}
+
+impl StsPolicy {
+    pub fn new (host: &str, port: u16, duration: i64, preload: bool) -> StsPolicy {
+        StsPolicy {
+            host: host.to_ascii_lower(),
+            port: port,
+            expires: time::get_time().sec + duration,
+            preload: preload,
+        }
+    }
+
+    pub fn host (&self) -> &str {
+        self.host.as_slice()
+    }
+
+    pub fn port (&self) -> u16 {
+        self.port
+    }
+
+    pub fn expires (&self) -> i64 {
+        self.expires
+    }
+
+    pub fn preload (&self) -> bool {
+        self.preload
+    }
+
+    pub fn is_expired (&self) -> bool {
+        self.expires <= time::get_time().sec
+    }
+}
+
+pub trait StsPolicyStore {
+    fn get (&mut self, host: &str) -> Option<StsPolicy>;
+    fn put (&mut self, policy: StsPolicy) -> io::IoResult<()>;
+    fn remove (&mut self, host: &str) -> io::IoResult<()>;
+}
+
+pub struct MemoryStsPolicyStore {
+    policies: Vec<StsPolicy>,
+}
+
+impl MemoryStsPolicyStore {
+    pub fn new () -> MemoryStsPolicyStore {
+        MemoryStsPolicyStore { policies: vec![] }
+    }
+}
+
+impl StsPolicyStore for MemoryStsPolicyStore {
+    fn get (&mut self, host: &str) -> Option<StsPolicy> {
+        let host = host.to_ascii_lower();
+        self.policies.retain(|p| !p.is_expired());
+        self.policies.iter().find(|p| p.host == host).map(|p| p.clone())
+    }
+
+    fn put (&mut self, policy: StsPolicy) -> io::IoResult<()> {
+        self.policies.retain(|p| p.host != policy.host);
+        self.policies.push(policy);
+        Ok(())
+    }
+
+    fn remove (&mut self, host: &str) -> io::IoResult<()> {
+        let host = host.to_ascii_lower();
+        self.policies.retain(|p| p.host != host);
+        Ok(())
+    }
+}
+
+// stores one policy per line, as "host port expires preload"
+pub struct FileStsPolicyStore {
+    path: Path,
+    policies: MemoryStsPolicyStore,
+}
+
+impl FileStsPolicyStore {
+    pub fn new (path: Path) -> FileStsPolicyStore {
+        let mut policies = MemoryStsPolicyStore::new();
+        if path.exists() {
+            match io::File::open(&path) {
+                Ok(file) => {
+                    let mut reader = io::BufferedReader::new(file);
+                    for line in reader.lines() {
+                        match line {
+                            Ok(line) => {
+                                match parse_policy_line(line.as_slice().trim()) {
+                                    Some(policy) => policies.policies.push(policy),
+                                    None => {},
+                                }
+                            },
+                            Err(_) => break,
+                        }
+                    }
+                },
+                Err(_) => {},
+            }
+        }
+        FileStsPolicyStore { path: path, policies: policies }
+    }
+
+    pub fn default_path () -> Option<Path> {
+        os::homedir().map(|home| home.join(".irc_sts_policies"))
+    }
+
+    fn save (&mut self) -> io::IoResult<()> {
+        let mut file = try!(io::File::create(&self.path));
+        for p in self.policies.policies.iter().filter(|p| !p.is_expired()) {
+            try!(write!(file, "{} {} {} {}\n", p.host, p.port, p.expires, if p.preload { 1u } else { 0u }));
+        }
+        Ok(())
+    }
+}
+
+impl StsPolicyStore for FileStsPolicyStore {
+    fn get (&mut self, host: &str) -> Option<StsPolicy> {
+        self.policies.get(host)
+    }
+
+    fn put (&mut self, policy: StsPolicy) -> io::IoResult<()> {
+        try!(self.policies.put(policy));
+        self.save()
+    }
+
+    fn remove (&mut self, host: &str) -> io::IoResult<()> {
+        try!(self.policies.remove(host));
+        self.save()
+    }
+}
+
+fn parse_policy_line (line: &str) -> Option<StsPolicy> {
+    let fields: Vec<&str> = line.split(' ').collect();
+    if fields.len() != 4 {
+        return None;
+    }
+    match (from_str(fields[1]), from_str(fields[2])) {
+        (Some(port), Some(expires)) => {
+            Some(StsPolicy {
+                host: fields[0].to_string(),
+                port: port,
+                expires: expires,
+                preload: fields[3] == "1",
+            })
+        },
+        _ => None,
+    }
+}
+
+#[test]
+fn test_sts_directive () {
+    let directive = StsDirective::parse("port=6697,duration=300,preload,foo=bar");
+    assert_eq!(directive.port(), Some(6697));
+    assert_eq!(directive.duration(), Some(300));
+    assert!(directive.preload());
+
+    let directive = StsDirective::parse("duration=0");
+    assert_eq!(directive.port(), None);
+    assert_eq!(directive.duration(), Some(0));
+    assert!(!directive.preload());
+}
+
+#[test]
+fn test_sts_store () {
+    let mut store = MemoryStsPolicyStore::new();
+    store.put(StsPolicy::new("IRC.Example.com", 6697, 300, false)).unwrap();
+    assert_eq!(store.get("irc.example.com").map(|p| p.port()), Some(6697));
+    store.put(StsPolicy::new("expired.example.com", 6697, 0, false)).unwrap();
+    assert_eq!(store.get("expired.example.com"), None);
+    store.remove("irc.example.com").unwrap();
+    assert_eq!(store.get("irc.example.com"), None);
+
+    assert_eq!(
+        parse_policy_line("irc.example.com 6697 1400000000 1"),
+        Some(StsPolicy {
+            host: "irc.example.com".to_string(),
+            port: 6697,
+            expires: 1400000000,
+            preload: true,
+        })
+    );
+    assert_eq!(parse_policy_line("garbage"), None);
+}
@@ -0,0 +1,298 @@
+// a client which doesn't tie up the calling task, built with the threaded
+// feature. this isn't async io: there's no non-blocking io to build on, so
+// each connection gets two tasks of its own, one which reads lines and one
+// which owns the Client and handles everything else. the caller talks to
+// them through channels:
+//
+//   let (mut reader, sender) = try!(threaded_client::connect(builder).unwrap());
+//   sender.with_client(proc(client) client.join(["#chan"], []));
+//   loop {
+//       match reader.try_recv() {
+//           Some(Ok(event)) => ...,
+//           Some(Err(e)) => break,
+//           None => do_other_work(),
+//       }
+//   }
+//
+// lines are framed by the same LineCodec, and the Client does the same
+// decoding, state tracking (registration, caps, names, chathistory) and ping
+// handling as in the blocking loops. only plain connections can be split
+// between tasks, so tls isn't supported, and an sts directive from the
+// server can't be followed: the reading task would be left on the old
+// connection. those directives are ignored, and connecting fails straight
+// away if the builder asks for tls or has a stored policy for the host.
+//
+// this stands in for the async client that was asked for (connecting and
+// reading as futures on an executor, with reads that are safe to cancel).
+// the standard library has no non-blocking io or executor to build that on,
+// so it was scoped down to this.
+
+use std::error::FromError;
+use std::io;
+use std::sync::Future;
+
+use client::{Client, ClientBuilder, ClientCallbacks};
+use codec::{FrameError, LineCodec, ReadError};
+use error::{IrcResult, ConnectionClosed};
+use events::{Event, EventDecoder};
+use message::{Message, MessageBuilder};
+use net::NetStream;
+
+// connects and registers on another task. the future resolves once the
+// connection is up and the reader and writer tasks are running.
+pub fn connect (mut builder: ClientBuilder) -> Future<IrcResult<(ThreadedReader, ThreadedSender)>> {
+    if builder.uses_tls() {
+        return Future::from_value(Err(FromError::from_error(io::IoError {
+            kind: io::InvalidInput,
+            desc: "the threaded client doesn't support tls",
+            detail: None,
+        })));
+    }
+    builder.set_sts_upgrade(false);
+    Future::spawn(proc() {
+        let client = try!(builder.try_connect());
+        let stream = try!(client.try_clone_stream());
+        let max_line_length = client.builder().max_line_length();
+
+        let (line_tx, line_rx) = channel();
+        let (event_tx, event_rx) = channel();
+        let (command_tx, command_rx) = channel();
+
+        spawn(proc() read_lines(stream, max_line_length, line_tx));
+        let driver_tx = command_tx.clone();
+        spawn(proc() drive(client, line_rx, command_rx, driver_tx, event_tx));
+
+        Ok((ThreadedReader { events: event_rx }, ThreadedSender { commands: command_tx }))
+    })
+}
+
+pub struct ThreadedReader {
+    events: Receiver<IrcResult<Event>>,
+}
+
+impl ThreadedReader {
+    // waits for the next event. the error which closed the connection is
+    // the last thing received, and after that this returns None.
+    pub fn recv (&mut self) -> Option<IrcResult<Event>> {
+        self.events.recv_opt().ok()
+    }
+
+    // returns None straight away if there's nothing yet. events stay queued
+    // until they're received, so giving up on a read never loses one.
+    pub fn try_recv (&mut self) -> Option<IrcResult<Event>> {
+        self.events.try_recv().ok()
+    }
+}
+
+impl Iterator<IrcResult<Event>> for ThreadedReader {
+    fn next (&mut self) -> Option<IrcResult<Event>> {
+        self.recv()
+    }
+}
+
+enum Command {
+    Write(Message),
+    WithClient(proc(&mut Client): Send -> io::IoResult<()>),
+}
+
+// queues things to send on the connection. sending fails once the
+// connection has closed.
+#[deriving(Clone)]
+pub struct ThreadedSender {
+    commands: Sender<Command>,
+}
+
+impl ThreadedSender {
+    pub fn write (&self, m: Message) -> io::IoResult<()> {
+        match m.validate() {
+            Ok(()) => {},
+            Err(e) => return Err(io::IoError { kind: io::InvalidInput, desc: e, detail: None }),
+        }
+        self.queue(Write(m))
+    }
+
+    pub fn send (&self, builder: MessageBuilder) -> io::IoResult<()> {
+        match builder.build() {
+            Ok(m) => self.queue(Write(m)),
+            Err(e) => Err(io::IoError { kind: io::InvalidInput, desc: e, detail: None }),
+        }
+    }
+
+    // runs f on the task which owns the client, for everything else Client
+    // can send. an error from f closes the connection.
+    pub fn with_client (&self, f: proc(&mut Client): Send -> io::IoResult<()>) -> io::IoResult<()> {
+        self.queue(WithClient(f))
+    }
+
+    fn queue (&self, command: Command) -> io::IoResult<()> {
+        self.commands.send_opt(command).map_err(|_| io::IoError {
+            kind: io::BrokenPipe,
+            desc: "connection closed",
+            detail: None,
+        })
+    }
+}
+
+// reading stops after an error which means the connection is gone. timeouts
+// are passed on too, since they're how ping timeouts are noticed.
+fn read_lines (mut stream: NetStream, max_line_length: uint, lines: Sender<Result<Vec<u8>, FrameError>>) {
+    let mut codec = LineCodec::new(max_line_length);
+    loop {
+        let line = codec.read_line(&mut stream);
+        let fatal = match line {
+            Err(ReadError(ref e)) => e.kind != io::TimedOut,
+            _ => false,
+        };
+        if lines.send_opt(line).is_err() || fatal {
+            return;
+        }
+    }
+}
+
+// keeping a sender of its own means the command channel never disconnects
+// while this is running, even if the caller drops every ThreadedSender
+fn drive (
+    mut client: Client,
+    lines: Receiver<Result<Vec<u8>, FrameError>>,
+    commands: Receiver<Command>,
+    _commands_tx: Sender<Command>,
+    events: Sender<IrcResult<Event>>
+) {
+    let mut decoder = EventDecoder::new();
+    let mut res = decoder.on_client_connect(&mut client);
+
+    while res.is_ok() {
+        res = select! {
+            line = lines.recv_opt() => {
+                let res = match line {
+                    Ok(Ok(line)) => {
+                        client.handle_read(Ok(line.as_slice()), |client, m| decoder.handle(client, m))
+                    },
+                    Ok(Err(e)) => {
+                        client.handle_read(Err(e), |client, m| decoder.handle(client, m))
+                    },
+                    // the reader sends the error it stopped on before it
+                    // goes away, so this only happens if it panicked
+                    Err(()) => Err(ConnectionClosed),
+                };
+                res.and_then(|()| decoder.check_parse_error(&mut client))
+            },
+            command = commands.recv() => {
+                let written = match command {
+                    Write(m) => client.write(m),
+                    WithClient(f) => f(&mut client),
+                };
+                written.map_err(|e| FromError::from_error(e))
+            }
+        };
+
+        loop {
+            match decoder.next_event() {
+                // nobody might be listening any more, but the sender can
+                // still be used, so keep going
+                Some(event) => { let _ = events.send_opt(Ok(event)); },
+                None => break,
+            }
+        }
+    }
+
+    // senders have to see the connection as closed by the time the reader
+    // gets the error, and the reading task has to stop too
+    drop(commands);
+    let _ = client.conn().get_mut().close_read();
+    let _ = decoder.on_client_disconnect(&mut client);
+    match res {
+        Err(e) => { let _ = events.send_opt(Err(e)); },
+        Ok(()) => {},
+    }
+}
+
+#[test]
+fn test_threaded_client () {
+    use std::io::{Acceptor, Listener, TcpListener};
+    use constants::{Cap, Reply, RPL_WELCOME};
+    use error::ServerError;
+    use events::{ReplyEvent, ErrorEvent, OtherEvent};
+
+    let mut listener = TcpListener::bind("127.0.0.1", 0).unwrap();
+    let port = listener.socket_name().unwrap().port;
+    let mut acceptor = listener.listen().unwrap();
+
+    let (tx, rx) = channel();
+    spawn(proc() {
+        let mut conn = io::BufferedStream::new(acceptor.accept().unwrap());
+        // the connection stays as it is rather than switching to tls
+        conn.write_str(":irc.host CAP * LS :sts=port=1,duration=300\r\n").unwrap();
+        conn.write_str(":irc.host 001 me :Welcome\r\n").unwrap();
+        conn.flush().unwrap();
+        loop {
+            let line = conn.read_line().unwrap();
+            if line.as_slice().starts_with("PRIVMSG") {
+                tx.send(line);
+                break;
+            }
+        }
+        conn.write_str("ERROR :bye\r\n").unwrap();
+        conn.flush().unwrap();
+    });
+
+    let mut builder = ClientBuilder::new("me", "127.0.0.1");
+    builder.set_port(port);
+    let (mut reader, sender) = connect(builder).unwrap().unwrap();
+
+    let other = sender.clone();
+    spawn(proc() {
+        other.with_client(proc(client) client.privmsg(["#chan"], "from another task")).unwrap();
+    });
+    assert_eq!(rx.recv().as_slice(), "PRIVMSG #chan :from another task\r\n");
+
+    match reader.recv() {
+        Some(Ok(OtherEvent(m))) => assert_eq!(*m.message_type(), Cap),
+        e => panic!("unexpected {}", e),
+    }
+    match reader.recv() {
+        Some(Ok(ReplyEvent(m))) => assert_eq!(*m.message_type(), Reply(RPL_WELCOME)),
+        e => panic!("unexpected {}", e),
+    }
+    match reader.recv() {
+        Some(Ok(e)) => assert_eq!(e, ErrorEvent("bye".to_string())),
+        e => panic!("unexpected {}", e),
+    }
+    match reader.recv() {
+        Some(Err(ServerError(text))) => assert_eq!(text.as_slice(), "bye"),
+        e => panic!("unexpected {}", e),
+    }
+    assert!(reader.recv().is_none());
+    assert!(sender.send(MessageBuilder::new("PING").param("x")).is_err());
+}
+
+#[test]
+fn test_threaded_client_tls () {
+    use error::IoError;
+
+    let mut builder = ClientBuilder::new("me", "127.0.0.1");
+    builder.set_ssl(true);
+    match connect(builder).unwrap() {
+        Err(IoError(e)) => assert_eq!(e.kind, io::InvalidInput),
+        Err(e) => panic!("unexpected {}", e),
+        Ok(_) => panic!("tls shouldn't be supported"),
+    }
+}
+
+// a stored policy only applies when tls is available
+#[cfg(feature = "ssl")]
+#[test]
+fn test_threaded_client_sts_policy () {
+    use error::IoError;
+    use sts::{MemoryStsPolicyStore, StsPolicy, StsPolicyStore};
+
+    let mut store = MemoryStsPolicyStore::new();
+    store.put(StsPolicy::new("127.0.0.1", 6697, 300, false)).unwrap();
+    let mut builder = ClientBuilder::new("me", "127.0.0.1");
+    builder.set_sts_store(Some(box store as Box<StsPolicyStore + Send>));
+    match connect(builder).unwrap() {
+        Err(IoError(e)) => assert_eq!(e.kind, io::InvalidInput),
+        Err(e) => panic!("unexpected {}", e),
+        Ok(_) => panic!("the stored policy should have been followed"),
+    }
+}